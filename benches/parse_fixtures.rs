@@ -0,0 +1,161 @@
+//! Parse time and peak heap memory over the fixture corpus.
+//!
+//! Run with `cargo bench --bench parse_fixtures`. Each wallet is loaded from
+//! a buffer ("read") and from a memory map ("mmap"):
+//!
+//! - Zecwallet Lite wallets are parsed in place from either.
+//! - Zingo wallets are parsed from an owned `Data`, so the mapped load copies
+//!   the mapping into one, as a mapped zingo path would have to.
+//! - zcashd wallets are parsed by `db_dump` in a child process ("db_dump"),
+//!   which reads the file itself. What zmigrate reads in process is the page
+//!   scan of `zcashd --salvage`, so that is what is compared.
+//!
+//! Peak heap counts allocations made by this process. Mapped pages are not
+//! heap: the OS reads them in on demand and can drop them again under
+//! memory pressure, as they are backed by the file.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser};
+use zewif_zingo::ZingoParser;
+use zewif::Data;
+use zmigrate::{
+    file_args::read_data, mapped_file::MappedFile, salvage::scan_pages, zecwallet::ZecwalletWallet,
+};
+
+/// The global allocator, wrapped to track current and peak heap use.
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+impl PeakAlloc {
+    fn grow(size: usize) {
+        let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn shrink(size: usize) {
+        CURRENT.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            Self::shrink(layout.size());
+            Self::grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+/// Run `f`, returning its time and the peak heap it used above what was
+/// already allocated.
+fn measure(f: impl FnOnce() -> Result<()>) -> Result<(Duration, usize)> {
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let start = Instant::now();
+    f()?;
+    let elapsed = start.elapsed();
+    Ok((elapsed, PEAK.load(Ordering::Relaxed) - baseline))
+}
+
+fn fixture_wallets(dir: &Path, wallets: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            // Node data directories hold chain data, not wallets.
+            if path.file_name().is_some_and(|name| name != "chain_cache") {
+                fixture_wallets(&path, wallets)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "dat") {
+            wallets.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn report(fixture: &str, mode: &str, (elapsed, peak): (Duration, usize)) {
+    println!(
+        "{:<60} {:<7} {:>10.2} ms {:>10} KiB",
+        fixture,
+        mode,
+        elapsed.as_secs_f64() * 1000.0,
+        peak / 1024
+    );
+}
+
+fn parse_zcashd(path: &Path) -> Result<()> {
+    let db_dump = BDBDump::from_file(path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    Ok(())
+}
+
+fn scan_zcashd(bytes: &[u8]) -> Result<()> {
+    scan_pages(bytes);
+    Ok(())
+}
+
+fn parse_zingo(file_data: &Data) -> Result<()> {
+    ZingoParser::new(file_data).parse().context("Parsing zingo wallet")?;
+    Ok(())
+}
+
+fn parse_zecwallet(bytes: &[u8]) -> Result<()> {
+    ZecwalletWallet::parse(bytes).context("Parsing Zecwallet wallet")?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+    let mut wallets = Vec::new();
+    fixture_wallets(&fixtures, &mut wallets)?;
+    wallets.sort();
+
+    println!("{:<60} {:<7} {:>13} {:>14}", "Fixture", "Input", "Time", "Peak heap");
+    for path in &wallets {
+        let fixture = path.strip_prefix(&fixtures)?.display().to_string();
+
+        if fixture.starts_with("zcashd") {
+            report(&fixture, "db_dump", measure(|| parse_zcashd(path))?);
+            report(&fixture, "read", measure(|| scan_zcashd(&std::fs::read(path)?))?);
+            report(&fixture, "mmap", measure(|| scan_zcashd(&MappedFile::open(path)?))?);
+        } else if fixture.starts_with("zingo") {
+            report(&fixture, "read", measure(|| parse_zingo(&read_data(path)?))?);
+            report(
+                &fixture,
+                "mmap",
+                measure(|| parse_zingo(&Data::from(MappedFile::open(path)?.to_vec())))?,
+            );
+        } else if fixture.starts_with("zecwallet") {
+            report(&fixture, "read", measure(|| parse_zecwallet(&std::fs::read(path)?))?);
+            report(&fixture, "mmap", measure(|| parse_zecwallet(&MappedFile::open(path)?))?);
+        }
+    }
+    Ok(())
+}
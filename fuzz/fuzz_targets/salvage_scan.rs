@@ -0,0 +1,10 @@
+//! The page scan that `zcashd --salvage` runs over a damaged wallet file.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zmigrate::salvage::scan_pages;
+
+fuzz_target!(|data: &[u8]| {
+    scan_pages(data);
+});
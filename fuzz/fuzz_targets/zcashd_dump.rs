@@ -0,0 +1,10 @@
+//! A whole set of zcashd records, parsed record by record and migrated.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zmigrate_fuzz::{RecordsInput, parse_zcashd_records};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_zcashd_records(&RecordsInput::decode(data));
+});
@@ -0,0 +1,23 @@
+//! A single zcashd record of any type, added to an otherwise valid wallet
+//! so that it reaches its record parser.
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use zmigrate_fuzz::{BASE_WALLET, RecordInput, WalletDump, fixtures_dir, parse_zcashd_records};
+
+static BASE: LazyLock<WalletDump> = LazyLock::new(|| {
+    let path = BASE_WALLET.iter().fold(fixtures_dir(), |path, element| path.join(element));
+    WalletDump::read(&path).expect("Reading the base wallet")
+});
+
+fuzz_target!(|data: &[u8]| {
+    let Some(record) = RecordInput::decode(data) else {
+        return;
+    };
+    let mut dump = BASE.clone();
+    dump.insert(record.record_key(), record.value.to_vec());
+    let _ = parse_zcashd_records(dump.records());
+});
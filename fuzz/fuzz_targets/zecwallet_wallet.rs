@@ -0,0 +1,12 @@
+//! A Zecwallet Lite wallet file, parsed and, if encrypted, decrypted.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zmigrate::zecwallet::ZecwalletWallet;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut wallet) = ZecwalletWallet::parse(data) {
+        let _ = wallet.decrypt("");
+    }
+});
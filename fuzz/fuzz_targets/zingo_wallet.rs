@@ -0,0 +1,10 @@
+//! A zingo wallet file, parsed from its top-level version onward.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zewif_zingo::ZingoParser;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ZingoParser::new(&data.to_vec().into()).parse();
+});
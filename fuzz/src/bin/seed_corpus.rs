@@ -0,0 +1,82 @@
+//! Seed the fuzz corpora from the wallets in `tests/fixtures`.
+//!
+//! Run with `cargo run --bin seed_corpus` from the `fuzz` directory. Whole
+//! wallet files seed `salvage_scan`, `zingo_wallet` and `zecwallet_wallet`,
+//! and the records of the zcashd wallets seed `zcashd_dump`, and
+//! `zcashd_record` with a few of each type.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use zmigrate_fuzz::{RecordInput, RecordsInput, WalletDump, fixtures_dir};
+
+/// The most records of each type to keep, as records of a type mostly
+/// differ only in their key material.
+const RECORDS_PER_TYPE: usize = 4;
+
+fn fixture_wallets(dir: &Path, wallets: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            // Node data directories hold chain data, not wallets.
+            if path.file_name().is_some_and(|name| name != "chain_cache") {
+                fixture_wallets(&path, wallets)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "dat") {
+            wallets.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn seed(target: &str, name: &str, data: &[u8]) -> Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+    std::fs::write(dir.join(name), data).with_context(|| format!("Writing {} seed {}", target, name))
+}
+
+fn main() -> Result<()> {
+    let fixtures = fixtures_dir();
+    let mut wallets = Vec::new();
+    fixture_wallets(&fixtures, &mut wallets)?;
+    wallets.sort();
+
+    let mut records: BTreeMap<String, Vec<Vec<u8>>> = BTreeMap::new();
+    for path in &wallets {
+        let name = path.strip_prefix(&fixtures)?.to_string_lossy().replace(['/', '\\'], "-");
+        let data = std::fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+        if name.starts_with("zingo") {
+            seed("zingo_wallet", &name, &data)?;
+            continue;
+        }
+        if name.starts_with("zecwallet") {
+            seed("zecwallet_wallet", &name, &data)?;
+            continue;
+        }
+        seed("salvage_scan", &name, &data)?;
+
+        let dump = WalletDump::read(path).with_context(|| format!("Dumping {}", path.display()))?;
+        seed("zcashd_dump", &name, &RecordsInput::encode(dump.records()))?;
+        for (key, value) in dump.records() {
+            let Some(input) = RecordInput::encode(key, value) else {
+                continue;
+            };
+            let keyname = RecordInput::decode(&input).unwrap().keyname.to_string();
+            let inputs = records.entry(keyname).or_default();
+            if inputs.len() < RECORDS_PER_TYPE && !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+    }
+    for (keyname, inputs) in &records {
+        for (i, input) in inputs.iter().enumerate() {
+            seed("zcashd_record", &format!("{}-{}", keyname, i), input)?;
+        }
+    }
+
+    println!("Seeded {} wallets and {} record types", wallets.len(), records.len());
+    Ok(())
+}
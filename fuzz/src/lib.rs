@@ -0,0 +1,160 @@
+//! Helpers shared by the fuzz targets and the seed corpus generator.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use zewif::Data;
+use zewif_zcashd::{ZcashdDump, ZcashdParser};
+use zmigrate::salvage::{KNOWN_KEYNAMES, run_bdb_utility};
+
+/// The zcashd wallet whose records surround a fuzzed record.
+pub const BASE_WALLET: &[&str] = &["zcashd", "golden-v5.6.0", "node0_wallet.dat"];
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("tests").join("fixtures")
+}
+
+/// Parse zcashd records the way `zmigrate zcashd` parses those of a wallet
+/// file, and migrate the result. The records are held in memory, so no
+/// Berkeley DB utility runs for each input.
+pub fn parse_zcashd_records(records: &[(Vec<u8>, Vec<u8>)]) -> Result<()> {
+    let records = records.iter().map(|(key, value)| (Data::from(key.clone()), Data::from(value.clone())));
+    let zcashd_dump = ZcashdDump::from_records(records).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, _) = ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    zewif_zcashd::migrate_to_zewif(&zcashd_wallet).context("Migrating to ZeWIF")?;
+    Ok(())
+}
+
+/// The records of a Berkeley DB file, as read once with `db_dump` to seed
+/// the corpora and to surround fuzzed records.
+#[derive(Debug, Clone)]
+pub struct WalletDump {
+    records: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl WalletDump {
+    pub fn read(path: &Path) -> Result<Self> {
+        let output = run_bdb_utility("dump", |command| {
+            command.arg(path);
+        })?;
+        if !output.status.success() {
+            bail!("db_dump failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let text = String::from_utf8(output.stdout).context("db_dump output is not UTF-8")?;
+
+        let mut data = Vec::new();
+        for line in text.lines() {
+            if let Some(hex_data) = line.strip_prefix(' ') {
+                data.push(hex::decode(hex_data).context("Decoding db_dump data line")?);
+            } else if line == "DATA=END" {
+                break;
+            }
+        }
+        if data.len() % 2 != 0 {
+            bail!("db_dump output has a key without a value");
+        }
+        let mut data = data.into_iter();
+        let mut records = Vec::new();
+        while let (Some(key), Some(value)) = (data.next(), data.next()) {
+            records.push((key, value));
+        }
+        Ok(Self { records })
+    }
+
+    pub fn records(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.records
+    }
+
+    /// Add a record, replacing any with the same key.
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.records.retain(|(k, _)| *k != key);
+        self.records.push((key, value));
+    }
+}
+
+/// A fuzzed zcashd record: the record type, chosen by the first byte, the
+/// two-byte little-endian length of the rest of the key, the rest of the
+/// key, and then the value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordInput<'a> {
+    pub keyname: &'static str,
+    pub key: &'a [u8],
+    pub value: &'a [u8],
+}
+
+impl<'a> RecordInput<'a> {
+    pub fn decode(data: &'a [u8]) -> Option<Self> {
+        let (&selector, rest) = data.split_first()?;
+        let keyname = KNOWN_KEYNAMES[selector as usize % KNOWN_KEYNAMES.len()];
+        let key_len = u16::from_le_bytes(rest.get(..2)?.try_into().unwrap()) as usize;
+        let key = rest.get(2..2 + key_len)?;
+        let value = &rest[2 + key_len..];
+        Some(Self { keyname, key, value })
+    }
+
+    /// The input that decodes to a record, if its key begins with a known
+    /// record name.
+    pub fn encode(record_key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let (&name_len, rest) = record_key.split_first()?;
+        let name = rest.get(..name_len as usize)?;
+        let selector = KNOWN_KEYNAMES.iter().position(|keyname| keyname.as_bytes() == name)?;
+        let key = &rest[name_len as usize..];
+        let key_len = u16::try_from(key.len()).ok()?;
+
+        let mut data = vec![selector as u8];
+        data.extend_from_slice(&key_len.to_le_bytes());
+        data.extend_from_slice(key);
+        data.extend_from_slice(value);
+        Some(data)
+    }
+
+    /// The record's key as zcashd writes it: the length-prefixed record
+    /// name followed by the rest of the key.
+    pub fn record_key(&self) -> Vec<u8> {
+        let mut key = vec![self.keyname.len() as u8];
+        key.extend_from_slice(self.keyname.as_bytes());
+        key.extend_from_slice(self.key);
+        key
+    }
+}
+
+/// A fuzzed set of zcashd records, each a two-byte little-endian key
+/// length, the key, a four-byte little-endian value length and the value.
+/// A record cut short ends the set.
+pub struct RecordsInput;
+
+impl RecordsInput {
+    pub fn decode(mut data: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut records = Vec::new();
+        while let Some((key, value, rest)) = Self::decode_record(data) {
+            records.push((key.to_vec(), value.to_vec()));
+            data = rest;
+        }
+        records
+    }
+
+    fn decode_record(data: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+        let key_len = u16::from_le_bytes(data.get(..2)?.try_into().unwrap()) as usize;
+        let key = data.get(2..2 + key_len)?;
+        let rest = &data[2 + key_len..];
+        let value_len = u32::from_le_bytes(rest.get(..4)?.try_into().unwrap()) as usize;
+        let value = rest.get(4..4 + value_len)?;
+        Some((key, value, &rest[4 + value_len..]))
+    }
+
+    /// The input that decodes to `records`, leaving out any too long to
+    /// encode.
+    pub fn encode(records: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (key, value) in records {
+            let (Ok(key_len), Ok(value_len)) = (u16::try_from(key.len()), u32::try_from(value.len())) else {
+                continue;
+            };
+            data.extend_from_slice(&key_len.to_le_bytes());
+            data.extend_from_slice(key);
+            data.extend_from_slice(&value_len.to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+}
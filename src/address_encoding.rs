@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use zcash_address::{
+    ZcashAddress,
+    unified::{self, Encoding},
+};
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_protocol::consensus::NetworkType;
+use zewif::Network;
+
+/// Convert a ZeWIF network to the network type used by the `zcash_*` crates.
+pub fn network_type(network: Network) -> NetworkType {
+    match network {
+        Network::Main => NetworkType::Main,
+        Network::Test => NetworkType::Test,
+        Network::Regtest => NetworkType::Regtest,
+    }
+}
+
+/// Encode a raw Sapling payment address as a `zs` string.
+pub fn encode_sapling(network: Network, address: &sapling::PaymentAddress) -> String {
+    ZcashAddress::from_sapling(network_type(network), address.to_bytes()).encode()
+}
+
+/// Decode a `zs` string into a raw Sapling payment address.
+pub fn decode_sapling(encoded: &str) -> Result<sapling::PaymentAddress> {
+    let (_, data) = bech32::decode(encoded)
+        .with_context(|| format!("Decoding Sapling address {}", encoded))?;
+    let bytes = <[u8; 43]>::try_from(data.as_slice())
+        .map_err(|_| anyhow::anyhow!("Sapling address {} is not 43 bytes", encoded))?;
+    Option::from(sapling::PaymentAddress::from_bytes(&bytes))
+        .with_context(|| format!("Sapling address {} is not a valid payment address", encoded))
+}
+
+/// Encode a raw Orchard address as a unified address with a single receiver.
+pub fn encode_orchard(network: Network, address: &orchard::Address) -> Result<String> {
+    let ua = unified::Address::try_from_items(vec![unified::Receiver::Orchard(
+        address.to_raw_address_bytes(),
+    )])
+    .context("Building Orchard-only unified address")?;
+    Ok(ZcashAddress::from_unified(network_type(network), ua).encode())
+}
+
+/// The raw Sapling and Orchard receivers of an encoded unified address.
+pub fn unified_receivers(encoded: &str) -> Result<Vec<unified::Receiver>> {
+    let (_, ua) = unified::Address::decode(encoded)
+        .with_context(|| format!("Decoding unified address {}", encoded))?;
+    Ok(unified::Container::items(&ua))
+}
+
+/// Encode a transparent address as a `t` string.
+pub fn encode_transparent(network: Network, address: &TransparentAddress) -> String {
+    let network = network_type(network);
+    match address {
+        TransparentAddress::PublicKeyHash(hash) => {
+            ZcashAddress::from_transparent_p2pkh(network, *hash).encode()
+        }
+        TransparentAddress::ScriptHash(hash) => {
+            ZcashAddress::from_transparent_p2sh(network, *hash).encode()
+        }
+    }
+}
+
+/// The HASH160 of a public key, as paid by a P2PKH address.
+pub fn pubkey_hash(pubkey: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(pubkey)).into()
+}
+
+/// Encode the P2PKH address of a transparent public key as a `t` string.
+pub fn encode_p2pkh(network: Network, pubkey: &[u8]) -> String {
+    encode_transparent(network, &TransparentAddress::PublicKeyHash(pubkey_hash(pubkey)))
+}
+
+/// Encode a unified address from its raw receivers.
+pub fn encode_unified(network: Network, receivers: Vec<unified::Receiver>) -> Result<String> {
+    let ua = unified::Address::try_from_items(receivers).context("Building unified address")?;
+    Ok(ZcashAddress::from_unified(network_type(network), ua).encode())
+}
@@ -0,0 +1,328 @@
+#[cfg(feature = "with-context")]
+use std::cell::RefCell;
+use std::{collections::HashSet, io::Write};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+/// The bytes a parser consumed for one field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpan {
+    /// The offset of the field from the start of the parsed buffer.
+    pub offset: usize,
+    pub len: usize,
+    /// The field's path through the parsed structures, outermost first and
+    /// separated by dots.
+    pub path: String,
+}
+
+impl FieldSpan {
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+}
+
+#[cfg(feature = "with-context")]
+thread_local! {
+    static RECORDING: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+/// The spans recorded so far by a parse, and the names of the fields it is
+/// within.
+#[cfg(feature = "with-context")]
+#[derive(Debug, Default)]
+struct Recording {
+    path: Vec<String>,
+    spans: Vec<FieldSpan>,
+}
+
+/// Run a parse, recording the span of every field that it reads: those read
+/// as a [`Field`] by zmigrate's own parsers, and those read by the `parse!`
+/// macro of the `zewif` crates, which reports them through the field
+/// observer hook of `zewif::parser`.
+///
+/// The spans of the fields parsed before an error are returned along with
+/// it, as they show how far the parse got. Spans are recorded only when
+/// zmigrate is built with the `with-context` feature.
+#[cfg(feature = "with-context")]
+pub fn record_spans<T>(parse: impl FnOnce() -> Result<T>) -> Result<(Result<T>, Vec<FieldSpan>)> {
+    let outer = RECORDING.with(|recording| recording.replace(Some(Recording::default())));
+    let result = zewif::parser::observe_fields(&ZewifFields, parse);
+    let recording = RECORDING.with(|recording| recording.replace(outer));
+    Ok((result, recording.map(|recording| recording.spans).unwrap_or_default()))
+}
+
+/// Records the fields read by the `parse!` macro of the `zewif` crates.
+///
+/// With `with-context`, `parse!` begins a field named by its context string
+/// before each parse, and ends it with the parser's offsets before and after
+/// it, or abandons it if the parse fails. The offsets are from the start of
+/// the buffer being parsed: the file for zingo wallets, and the record's
+/// value for zcashd records.
+#[cfg(feature = "with-context")]
+struct ZewifFields;
+
+#[cfg(feature = "with-context")]
+impl zewif::parser::FieldObserver for ZewifFields {
+    fn begin(&self, context: &str) {
+        begin_field(context);
+    }
+
+    fn end(&self, start: usize, end: usize) {
+        end_field(start, end);
+    }
+
+    fn abandon(&self) {
+        abandon_field();
+    }
+}
+
+/// Enter a field, if spans are being recorded, and say whether they are.
+#[cfg(feature = "with-context")]
+fn begin_field(name: &str) -> bool {
+    RECORDING.with(|recording| match recording.borrow_mut().as_mut() {
+        Some(recording) => {
+            recording.path.push(name.to_string());
+            true
+        }
+        None => false,
+    })
+}
+
+/// Record the span of the innermost field, and leave it.
+#[cfg(feature = "with-context")]
+fn end_field(offset: usize, end: usize) {
+    RECORDING.with(|recording| {
+        if let Some(recording) = recording.borrow_mut().as_mut() {
+            let path = recording.path.join(".");
+            recording.path.pop();
+            recording.spans.push(FieldSpan { offset, len: end.saturating_sub(offset), path });
+        }
+    });
+}
+
+/// Leave the innermost field without recording it.
+#[cfg(feature = "with-context")]
+fn abandon_field() {
+    RECORDING.with(|recording| {
+        if let Some(recording) = recording.borrow_mut().as_mut() {
+            recording.path.pop();
+        }
+    });
+}
+
+#[cfg(not(feature = "with-context"))]
+pub fn record_spans<T>(_parse: impl FnOnce() -> Result<T>) -> Result<(Result<T>, Vec<FieldSpan>)> {
+    anyhow::bail!("Field spans are recorded only when zmigrate is built with `--features with-context`")
+}
+
+/// A field being parsed, within the fields begun before it and not yet
+/// ended. Its span is recorded when it ends, if spans are being recorded; a
+/// field dropped without ending, as when its parse fails, records nothing.
+#[derive(Debug)]
+pub struct Field {
+    recording: bool,
+}
+
+impl Field {
+    #[cfg(feature = "with-context")]
+    pub fn begin(name: &str) -> Self {
+        Self { recording: begin_field(name) }
+    }
+
+    #[cfg(not(feature = "with-context"))]
+    pub fn begin(_name: &str) -> Self {
+        Self { recording: false }
+    }
+
+    /// End the field, which was parsed from `offset` up to `end`.
+    pub fn end(mut self, offset: usize, end: usize) {
+        if !self.recording {
+            return;
+        }
+        self.recording = false;
+        #[cfg(feature = "with-context")]
+        end_field(offset, end);
+        #[cfg(not(feature = "with-context"))]
+        let _ = (offset, end);
+    }
+}
+
+impl Drop for Field {
+    fn drop(&mut self) {
+        #[cfg(feature = "with-context")]
+        if self.recording {
+            abandon_field();
+        }
+    }
+}
+
+/// How to write the fields of an annotated buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AnnotationFormat {
+    /// A line for each field: its offset, length, path and value, with the
+    /// bytes no field covers marked as unparsed.
+    #[default]
+    Hexdump,
+    /// An ImHex pattern placing each innermost field at its offset.
+    Imhex,
+    /// A Kaitai Struct definition with an instance for each innermost field.
+    Kaitai,
+}
+
+/// Write the fields parsed from `bytes`. `name` identifies the buffer in
+/// ImHex and Kaitai output.
+pub fn write_annotations(
+    name: &str,
+    bytes: &[u8],
+    spans: &[FieldSpan],
+    format: AnnotationFormat,
+    out: &mut impl Write,
+) -> Result<()> {
+    let spans = sorted_spans(spans);
+    match format {
+        AnnotationFormat::Hexdump => write_hexdump(bytes, &spans, out),
+        AnnotationFormat::Imhex => write_imhex(name, &spans, out),
+        AnnotationFormat::Kaitai => write_kaitai(name, &spans, out),
+    }
+}
+
+/// Spans that cover bytes, in offset order with each enclosing field ahead
+/// of the fields within it.
+fn sorted_spans(spans: &[FieldSpan]) -> Vec<FieldSpan> {
+    let mut sorted: Vec<FieldSpan> = spans.iter().filter(|span| span.len > 0).cloned().collect();
+    sorted.sort_by(|a, b| a.offset.cmp(&b.offset).then(b.len.cmp(&a.len)).then(a.path.cmp(&b.path)));
+    sorted.dedup();
+    sorted
+}
+
+/// The innermost of sorted spans: those with no other field starting within
+/// them.
+fn leaf_spans(spans: &[FieldSpan]) -> Vec<&FieldSpan> {
+    spans
+        .iter()
+        .enumerate()
+        .filter(|(i, span)| spans.get(i + 1).is_none_or(|next| next.offset >= span.end()))
+        .map(|(_, span)| span)
+        .collect()
+}
+
+fn write_hexdump(bytes: &[u8], spans: &[FieldSpan], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "{:<10} {:>8}  {:<48} Value", "Offset", "Length", "Field")?;
+    let mut covered = 0;
+    for span in spans {
+        if span.offset > covered {
+            write_line(bytes, covered, span.offset, "(unparsed)", out)?;
+        }
+        write_line(bytes, span.offset, span.end(), &span.path, out)?;
+        covered = covered.max(span.end());
+    }
+    if bytes.len() > covered {
+        write_line(bytes, covered, bytes.len(), "(unparsed)", out)?;
+    }
+    Ok(())
+}
+
+fn write_line(bytes: &[u8], start: usize, end: usize, field: &str, out: &mut impl Write) -> Result<()> {
+    let value = bytes.get(start..end).map(describe_value).unwrap_or_else(|| "(past the end)".to_string());
+    writeln!(out, "{:08x}   {:>8}  {:<48} {}", start, end - start, field, value)?;
+    Ok(())
+}
+
+/// The bytes of a field in hex, with their reading as a little-endian
+/// integer or as text where they could be one.
+pub fn describe_value(bytes: &[u8]) -> String {
+    const SHOWN: usize = 32;
+    let mut value = hex::encode(&bytes[..bytes.len().min(SHOWN)]);
+    if bytes.len() > SHOWN {
+        value.push('…');
+    }
+    let integer = match bytes.len() {
+        1 => Some(bytes[0] as u64),
+        2 => Some(u16::from_le_bytes(bytes.try_into().unwrap()) as u64),
+        4 => Some(u32::from_le_bytes(bytes.try_into().unwrap()) as u64),
+        8 => Some(u64::from_le_bytes(bytes.try_into().unwrap())),
+        _ => None,
+    };
+    if let Some(integer) = integer {
+        value.push_str(&format!(" ({})", integer));
+    } else if bytes.len() >= 4 && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        value.push_str(&format!(" {:?}", String::from_utf8_lossy(bytes)));
+    }
+    value
+}
+
+/// A conventional hexdump of `bytes`, sixteen to a line, each line giving
+/// the offset of its first byte counted from `base_offset`.
+pub fn hex_lines(bytes: &[u8], base_offset: usize) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let text: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            format!("{:08x}  {:<47}  |{}|", base_offset + i * 16, hex.join(" "), text)
+        })
+        .collect()
+}
+
+/// Identifiers for the innermost fields, unique within the output.
+fn field_names<'a>(spans: &[&'a FieldSpan]) -> Vec<(String, &'a FieldSpan)> {
+    let mut used = HashSet::new();
+    spans
+        .iter()
+        .map(|span| {
+            let base = identifier(&span.path);
+            let mut name = base.clone();
+            let mut n = 2;
+            while !used.insert(name.clone()) {
+                name = format!("{}_{}", base, n);
+                n += 1;
+            }
+            (name, *span)
+        })
+        .collect()
+}
+
+/// A snake_case identifier for a field path.
+fn identifier(path: &str) -> String {
+    let mut identifier = String::new();
+    for c in path.chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c.to_ascii_lowercase());
+        } else if !identifier.is_empty() && !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_end_matches('_').to_string();
+    match identifier.chars().next() {
+        None => "field".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{}", identifier),
+        Some(_) => identifier,
+    }
+}
+
+fn write_imhex(name: &str, spans: &[FieldSpan], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "// Fields of {}, as parsed by zmigrate", name)?;
+    for (field_name, span) in field_names(&leaf_spans(spans)) {
+        writeln!(out, "u8 {}[{}] @ {:#x}; // {}", field_name, span.len, span.offset, span.path)?;
+    }
+    Ok(())
+}
+
+fn write_kaitai(name: &str, spans: &[FieldSpan], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "meta:")?;
+    writeln!(out, "  id: {}", identifier(name))?;
+    writeln!(out, "  endian: le")?;
+    writeln!(out, "instances:")?;
+    for (field_name, span) in field_names(&leaf_spans(spans)) {
+        writeln!(out, "  {}:", field_name)?;
+        writeln!(out, "    doc: {:?}", span.path)?;
+        writeln!(out, "    pos: {:#x}", span.offset)?;
+        writeln!(out, "    size: {}", span.len)?;
+    }
+    Ok(())
+}
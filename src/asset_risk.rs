@@ -0,0 +1,295 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use owo_colors::{OwoColorize, Stream};
+use zewif_zcashd::ZcashdWallet;
+
+use crate::{address_encoding::decode_sapling, viewing_keys::UnifiedAccountFvks};
+
+/// How likely a finding is to lead to a loss of funds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Data that may not carry over, but does not control funds.
+    Info,
+    /// Keys or data whose loss could make funds harder to recover.
+    Warning,
+    /// Keys whose loss would make funds unrecoverable.
+    Critical,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// The kinds of wallet data that a target wallet may be unable to import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskKind {
+    /// A Sprout spending key.
+    SproutKey,
+    /// A spending key that was imported or randomly generated, so it cannot
+    /// be restored from the wallet's seed.
+    NonHdKey,
+    /// A watch-only script or P2SH redeem script.
+    WatchOnlyScript,
+    /// An address or account for which the wallet holds viewing keys but no
+    /// spending key.
+    ViewingOnlyKey,
+    /// A wallet record that zmigrate could not interpret.
+    UnrecognizedRecord,
+}
+
+impl RiskKind {
+    /// The severity of a finding whose data the target wallet can import.
+    fn base_severity(&self) -> Severity {
+        match self {
+            RiskKind::SproutKey | RiskKind::NonHdKey | RiskKind::UnrecognizedRecord => {
+                Severity::Warning
+            }
+            RiskKind::WatchOnlyScript | RiskKind::ViewingOnlyKey => Severity::Info,
+        }
+    }
+
+    /// The severity of a finding whose data the target wallet will drop.
+    fn loss_severity(&self) -> Severity {
+        match self {
+            RiskKind::SproutKey | RiskKind::NonHdKey | RiskKind::UnrecognizedRecord => {
+                Severity::Critical
+            }
+            RiskKind::WatchOnlyScript | RiskKind::ViewingOnlyKey => Severity::Warning,
+        }
+    }
+}
+
+impl std::fmt::Display for RiskKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskKind::SproutKey => write!(f, "Sprout key"),
+            RiskKind::NonHdKey => write!(f, "non-HD key"),
+            RiskKind::WatchOnlyScript => write!(f, "watch-only script"),
+            RiskKind::ViewingOnlyKey => write!(f, "viewing-only key"),
+            RiskKind::UnrecognizedRecord => write!(f, "unrecognized record"),
+        }
+    }
+}
+
+/// A wallet that migrated data is destined for, used to decide which
+/// findings will be lost at import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    Zcashd,
+    Zingo,
+    Zecwallet,
+    Ywallet,
+}
+
+impl Target {
+    /// Whether the target wallet can import data of the given kind.
+    pub fn can_import(&self, kind: RiskKind) -> bool {
+        match (self, kind) {
+            // No target can import a record that zmigrate could not read.
+            (_, RiskKind::UnrecognizedRecord) => false,
+            (Target::Zcashd, _) => true,
+            // Zingo restores everything from a single seed or UFVK.
+            (Target::Zingo, RiskKind::ViewingOnlyKey) => true,
+            (Target::Zingo, _) => false,
+            // Zecwallet and YWallet import individual spending and viewing
+            // keys, but have no Sprout or script support.
+            (Target::Zecwallet | Target::Ywallet, RiskKind::NonHdKey | RiskKind::ViewingOnlyKey) => {
+                true
+            }
+            (Target::Zecwallet | Target::Ywallet, _) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Zcashd => write!(f, "zcashd"),
+            Target::Zingo => write!(f, "zingo"),
+            Target::Zecwallet => write!(f, "zecwallet"),
+            Target::Ywallet => write!(f, "ywallet"),
+        }
+    }
+}
+
+/// One piece of wallet data at risk of not carrying over.
+#[derive(Debug, Clone)]
+pub struct RiskFinding {
+    kind: RiskKind,
+    subject: String,
+    lost_at_import: bool,
+}
+
+impl RiskFinding {
+    pub fn kind(&self) -> RiskKind {
+        self.kind
+    }
+
+    /// An identifier for the affected data: an address, key or record name.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Whether the target wallet will drop the data. Always false when no
+    /// target was given.
+    pub fn lost_at_import(&self) -> bool {
+        self.lost_at_import
+    }
+
+    pub fn severity(&self) -> Severity {
+        if self.lost_at_import {
+            self.kind.loss_severity()
+        } else {
+            self.kind.base_severity()
+        }
+    }
+}
+
+/// The wallet data that might not survive migration into a target wallet.
+#[derive(Debug)]
+pub struct AssetRisk {
+    target: Option<Target>,
+    findings: Vec<RiskFinding>,
+}
+
+impl AssetRisk {
+    /// Analyze a parsed zcashd wallet. `unparsed_keynames` holds the record
+    /// names of any keys the parser did not recognize; zcashd stores
+    /// watch-only and redeem scripts in `watchs` and `cscript` records.
+    pub fn run(wallet: &ZcashdWallet, unparsed_keynames: &[String], target: Option<Target>) -> Result<Self> {
+        let network = wallet.network();
+        let unified_accounts = UnifiedAccountFvks::from_zcashd_wallet(wallet)?;
+        let mut findings = Vec::new();
+        let mut push = |kind: RiskKind, subject: String| {
+            let lost_at_import = target.is_some_and(|target| !target.can_import(kind));
+            findings.push(RiskFinding { kind, subject, lost_at_import });
+        };
+
+        if let Some(sprout_keys) = wallet.sprout_keys() {
+            for address in sprout_keys.keys() {
+                push(RiskKind::SproutKey, address.to_string(network));
+            }
+        }
+
+        for keypair in wallet.keys().keypairs() {
+            if keypair.metadata().hd_keypath().is_none() {
+                push(RiskKind::NonHdKey, hex::encode(keypair.pubkey().as_ref()));
+            }
+        }
+
+        // zcashd records no path for the Sapling addresses of unified
+        // accounts, which are derived from the mnemonic seed all the same.
+        for (address, ivk) in wallet.sapling_z_addresses() {
+            let encoded = address.to_string(network);
+            match wallet.sapling_keys().get(ivk) {
+                Some(key) if key.metadata().hd_keypath().is_none() => {
+                    let payment_address = decode_sapling(&encoded)?;
+                    if !unified_accounts.iter().any(|fvks| fvks.owns_sapling(&payment_address)) {
+                        push(RiskKind::NonHdKey, encoded);
+                    }
+                }
+                Some(_) => {}
+                None => push(RiskKind::ViewingOnlyKey, encoded),
+            }
+        }
+
+        // Unified accounts are derived from the mnemonic seed; without it,
+        // only their full viewing keys remain.
+        if wallet.bip39_mnemonic().is_none() {
+            if let Some(unified_accounts) = wallet.unified_accounts() {
+                for ufvk_fingerprint in unified_accounts.full_viewing_keys().keys() {
+                    push(
+                        RiskKind::ViewingOnlyKey,
+                        format!("unified account {}", hex::encode(ufvk_fingerprint.as_ref())),
+                    );
+                }
+            }
+        }
+
+        let mut unparsed_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for keyname in unparsed_keynames {
+            *unparsed_counts.entry(keyname.as_str()).or_default() += 1;
+        }
+        for (keyname, count) in unparsed_counts {
+            let kind = match keyname {
+                "watchs" | "cscript" => RiskKind::WatchOnlyScript,
+                _ => RiskKind::UnrecognizedRecord,
+            };
+            push(kind, format!("{} ({} records)", keyname, count));
+        }
+
+        Ok(Self { target, findings })
+    }
+
+    pub fn target(&self) -> Option<Target> {
+        self.target
+    }
+
+    pub fn findings(&self) -> &[RiskFinding] {
+        &self.findings
+    }
+
+    /// The highest severity among the findings, if there are any.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(RiskFinding::severity).max()
+    }
+
+    /// Whether any finding puts funds at risk, failing a `--fail-on-risk`
+    /// run. Only keys and records the target wallet will drop are critical,
+    /// so without a target nothing is.
+    pub fn has_risk(&self) -> bool {
+        self.max_severity().is_some_and(|severity| severity >= Severity::Critical)
+    }
+
+    /// Append the findings to a migration report, most severe first.
+    /// Critical findings are shown in bold red and warnings in yellow.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        match self.target {
+            Some(target) => writeln!(report, "- Asset Risks (target: {}):", target)?,
+            None => writeln!(report, "- Asset Risks:")?,
+        }
+        if self.findings.is_empty() {
+            writeln!(report, "  * None found")?;
+            return Ok(());
+        }
+
+        let mut findings: Vec<_> = self.findings.iter().collect();
+        findings.sort_by(|a, b| b.severity().cmp(&a.severity()).then(a.kind.cmp(&b.kind)));
+        for finding in findings {
+            let consequence = if finding.lost_at_import {
+                "will be lost at import; sweep or export it before migrating"
+            } else {
+                "may not carry over to every wallet"
+            };
+            let line = format!(
+                "[{}] {} {} {}",
+                finding.severity(),
+                finding.kind,
+                finding.subject,
+                consequence
+            );
+            match finding.severity() {
+                Severity::Critical => writeln!(
+                    report,
+                    "  🔴 {}",
+                    line.if_supports_color(Stream::Stdout, |text| text.red().bold())
+                )?,
+                Severity::Warning => writeln!(
+                    report,
+                    "  ⚠️ {}",
+                    line.if_supports_color(Stream::Stdout, |text| text.yellow())
+                )?,
+                Severity::Info => writeln!(report, "  * {}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use anyhow::{Result, bail};
+use bc_envelope::prelude::*;
+use orchard::keys::FullViewingKey as OrchardFullViewingKey;
+use sapling::keys::{ExpandedSpendingKey, NullifierDerivingKey};
+use zewif::{Account, TxId, ZewifTop};
+use zewif_zcashd::ZcashdWallet;
+use zip32::Scope;
+
+use crate::{
+    address_encoding::{encode_p2pkh, encode_transparent},
+    note_decryption::{NoteDecryption, Pool, ReceivedNote, attach_to_output},
+    tx_decode::decode_transaction,
+    viewing_keys::{KeySource, UnifiedAccountFvks},
+};
+
+/// A value pool, including the transparent pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ValuePool {
+    Transparent,
+    Sapling,
+    Orchard,
+}
+
+impl From<Pool> for ValuePool {
+    fn from(pool: Pool) -> Self {
+        match pool {
+            Pool::Sapling => ValuePool::Sapling,
+            Pool::Orchard => ValuePool::Orchard,
+        }
+    }
+}
+
+impl std::fmt::Display for ValuePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValuePool::Transparent => write!(f, "Transparent"),
+            ValuePool::Sapling => write!(f, "Sapling"),
+            ValuePool::Orchard => write!(f, "Orchard"),
+        }
+    }
+}
+
+/// Format a zatoshi amount as ZEC with eight decimal places.
+pub fn format_zec(zatoshis: u64) -> String {
+    format!("{}.{:08} ZEC", zatoshis / 100_000_000, zatoshis % 100_000_000)
+}
+
+/// Where the nullifier used to determine a note's spentness came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullifierSource {
+    /// The nullifier stored in the wallet's note data.
+    Stored,
+    /// Derived from the note and the wallet's spending or full viewing key.
+    Derived,
+}
+
+/// A note or transparent output received by the wallet, with its spentness.
+#[derive(Debug, Clone)]
+pub struct NoteStatus {
+    txid: TxId,
+    pool: ValuePool,
+    index: u32,
+    value: u64,
+    account: String,
+    /// `None` when no nullifier could be obtained for a shielded note.
+    spent: Option<bool>,
+    nullifier: Option<([u8; 32], NullifierSource)>,
+}
+
+impl NoteStatus {
+    pub fn txid(&self) -> &TxId {
+        &self.txid
+    }
+
+    pub fn pool(&self) -> ValuePool {
+        self.pool
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The name of the migrated account that received the note.
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    pub fn spent(&self) -> Option<bool> {
+        self.spent
+    }
+
+    pub fn nullifier_source(&self) -> Option<NullifierSource> {
+        self.nullifier.map(|(_, source)| source)
+    }
+}
+
+/// Spentness of every note the wallet received, and the resulting balances,
+/// computed from the wallet's own transactions without chain access.
+#[derive(Debug, Default)]
+pub struct Balances {
+    notes: Vec<NoteStatus>,
+}
+
+impl Balances {
+    /// Determine which of the wallet's received notes and transparent outputs
+    /// are spent by any of the wallet's own transactions.
+    ///
+    /// Shielded notes come from `decryption`. Their nullifiers are taken from
+    /// the wallet's stored note data where present, and otherwise derived with
+    /// the spending key (legacy Sapling addresses) or full viewing key
+    /// (unified accounts).
+    pub fn run(
+        zcashd_wallet: &ZcashdWallet,
+        zewif: &ZewifTop,
+        decryption: &NoteDecryption,
+    ) -> Result<Self> {
+        let network = zcashd_wallet.network();
+        let fvks = UnifiedAccountFvks::from_zcashd_wallet(zcashd_wallet)?;
+        let sapling_nks = sapling_nullifier_keys(zcashd_wallet, &fvks)?;
+        let orchard_fvks: HashMap<&str, &OrchardFullViewingKey> = fvks
+            .iter()
+            .filter_map(|f| f.orchard().map(|fvk| (f.ufvk_id(), fvk)))
+            .collect();
+        let transparent_addresses = receiving_addresses(
+            zcashd_wallet
+                .keys()
+                .keypairs()
+                .map(|keypair| encode_p2pkh(network, keypair.pubkey().as_ref())),
+            zcashd_wallet
+                .address_purposes()
+                .iter()
+                .map(|(address, purpose)| (address.to_string(), purpose.as_str())),
+        );
+
+        // Everything the wallet's transactions spend.
+        let mut spent_sapling = HashSet::new();
+        let mut spent_orchard = HashSet::new();
+        let mut spent_outpoints = HashSet::new();
+        let mut transparent_received = Vec::new();
+        for (txid, tx) in zewif.transactions() {
+            let Ok(Some(decoded)) = decode_transaction(tx) else {
+                continue;
+            };
+            if let Some(bundle) = decoded.sapling_bundle() {
+                spent_sapling.extend(bundle.shielded_spends().iter().map(|s| s.nullifier().0));
+            }
+            if let Some(bundle) = decoded.orchard_bundle() {
+                spent_orchard.extend(bundle.actions().iter().map(|a| a.nullifier().to_bytes()));
+            }
+            if let Some(bundle) = decoded.transparent_bundle() {
+                for input in &bundle.vin {
+                    spent_outpoints.insert((*input.prevout.hash(), input.prevout.n()));
+                }
+                for (index, output) in bundle.vout.iter().enumerate() {
+                    let Some(address) = output.recipient_address() else {
+                        continue;
+                    };
+                    let address = encode_transparent(network, &address);
+                    if transparent_addresses.contains(&address) {
+                        transparent_received.push((
+                            txid.clone(),
+                            *decoded.txid().as_ref(),
+                            index as u32,
+                            u64::from(output.value),
+                            address,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut notes = Vec::new();
+
+        for (txid, txid_bytes, index, value, address) in transparent_received {
+            notes.push(NoteStatus {
+                txid,
+                pool: ValuePool::Transparent,
+                index,
+                value,
+                account: account_name(zewif, |account| {
+                    account.addresses().values().any(|a| a.as_string() == address)
+                }),
+                spent: Some(spent_outpoints.contains(&(txid_bytes, index))),
+                nullifier: None,
+            });
+        }
+
+        for decrypted in decryption.notes() {
+            let nullifier = match decrypted.note() {
+                ReceivedNote::Sapling(note) => {
+                    match stored_sapling_nullifier(zcashd_wallet, decrypted.txid(), decrypted.index()) {
+                        Some(nf) => Some((nf, NullifierSource::Stored)),
+                        None => sapling_nks.get(decrypted.recipient()).and_then(|nk| {
+                            let position = sapling_note_position(zewif, decrypted.txid(), decrypted.index())?;
+                            Some((note.nf(nk, position).0, NullifierSource::Derived))
+                        }),
+                    }
+                }
+                ReceivedNote::Orchard(note) => match decrypted.recipient() {
+                    KeySource::UnifiedAccount { ufvk_id, .. } => orchard_fvks
+                        .get(ufvk_id.as_str())
+                        .map(|fvk| (note.nullifier(fvk).to_bytes(), NullifierSource::Derived)),
+                    KeySource::SaplingAddress(_) => None,
+                },
+            };
+            let spent = nullifier.map(|(nf, _)| match decrypted.pool() {
+                Pool::Sapling => spent_sapling.contains(&nf),
+                Pool::Orchard => spent_orchard.contains(&nf),
+            });
+            notes.push(NoteStatus {
+                txid: decrypted.txid().clone(),
+                pool: decrypted.pool().into(),
+                index: decrypted.index(),
+                value: decrypted.value(),
+                account: account_name(zewif, |account| decrypted.recipient().owns(account)),
+                spent,
+                nullifier,
+            });
+        }
+
+        notes.sort_by_key(|n| (n.account.clone(), n.pool, format!("{:?}", n.txid), n.index));
+        Ok(Self { notes })
+    }
+
+    pub fn notes(&self) -> &[NoteStatus] {
+        &self.notes
+    }
+
+    pub fn unspent_notes(&self) -> impl Iterator<Item = &NoteStatus> {
+        self.notes.iter().filter(|n| n.spent == Some(false))
+    }
+
+    /// The unspent balance of each account, by pool.
+    pub fn account_balances(&self) -> BTreeMap<&str, BTreeMap<ValuePool, u64>> {
+        let mut balances: BTreeMap<&str, BTreeMap<ValuePool, u64>> = BTreeMap::new();
+        for note in self.unspent_notes() {
+            *balances
+                .entry(note.account.as_str())
+                .or_default()
+                .entry(note.pool)
+                .or_default() += note.value;
+        }
+        balances
+    }
+
+    /// The unspent balance of the whole wallet in `pool`.
+    pub fn pool_balance(&self, pool: ValuePool) -> u64 {
+        self.unspent_notes().filter(|n| n.pool == pool).map(|n| n.value).sum()
+    }
+
+    /// Attach a spent/unspent flag and the nullifier to each shielded note of
+    /// the migrated wallet.
+    pub fn attach_to(&self, zewif: &mut ZewifTop) {
+        for note in &self.notes {
+            let (pool, Some(spent), Some((nullifier, _))) = (note.pool, note.spent, note.nullifier)
+            else {
+                continue;
+            };
+            let pool = match pool {
+                ValuePool::Sapling => Pool::Sapling,
+                ValuePool::Orchard => Pool::Orchard,
+                ValuePool::Transparent => continue,
+            };
+            let envelope = Envelope::new("NoteStatus")
+                .add_assertion("spent", spent)
+                .add_assertion("nullifier", hex::encode(nullifier));
+            attach_to_output(zewif, &note.txid, pool, note.index, envelope);
+        }
+    }
+
+    /// Append the unspent balances to a migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Balances (from wallet data only):")?;
+        for pool in [ValuePool::Transparent, ValuePool::Sapling, ValuePool::Orchard] {
+            let unspent = self.unspent_notes().filter(|n| n.pool == pool).count();
+            writeln!(
+                report,
+                "  * {}: {} in {} unspent notes",
+                pool,
+                format_zec(self.pool_balance(pool)),
+                unspent
+            )?;
+        }
+        for (account, pools) in self.account_balances() {
+            let total: u64 = pools.values().sum();
+            writeln!(report, "  * Account {}: {}", account, format_zec(total))?;
+            for (pool, value) in pools {
+                writeln!(report, "    - {}: {}", pool, format_zec(value))?;
+            }
+        }
+        let unknown: Vec<_> = self.notes.iter().filter(|n| n.spent.is_none()).collect();
+        if !unknown.is_empty() {
+            let value: u64 = unknown.iter().map(|n| n.value).sum();
+            writeln!(
+                report,
+                "  * ⚠️ Spentness unknown (no nullifier): {} notes, {}",
+                unknown.len(),
+                format_zec(value)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of the first migrated account matching `predicate`.
+/// The transparent addresses whose outputs belong to the wallet: those of its
+/// keys, and address book entries with the "receive" purpose. Keypool and
+/// change addresses have keys but no address book entry, and watch-only
+/// scripts imported with `importaddress` have a "receive" entry but no key.
+/// Entries with the "send" purpose are addresses the wallet paid, and are
+/// left out.
+pub fn receiving_addresses<'a>(
+    key_addresses: impl IntoIterator<Item = String>,
+    address_purposes: impl IntoIterator<Item = (String, &'a str)>,
+) -> HashSet<String> {
+    let mut addresses: HashSet<String> = key_addresses.into_iter().collect();
+    addresses.extend(
+        address_purposes
+            .into_iter()
+            .filter(|(_, purpose)| *purpose == "receive")
+            .map(|(address, _)| address),
+    );
+    addresses
+}
+
+fn account_name(zewif: &ZewifTop, predicate: impl Fn(&Account) -> bool) -> String {
+    zewif
+        .wallets()
+        .values()
+        .flat_map(|wallet| wallet.accounts().values())
+        .find(|account| predicate(account))
+        .map_or_else(|| "(unassigned)".to_string(), |account| account.name().to_string())
+}
+
+/// The Sapling nullifier deriving key for each of the wallet's Sapling IVK sources.
+fn sapling_nullifier_keys(
+    zcashd_wallet: &ZcashdWallet,
+    fvks: &[UnifiedAccountFvks],
+) -> Result<HashMap<KeySource, NullifierDerivingKey>> {
+    let mut nks = HashMap::new();
+
+    for (address, ivk) in zcashd_wallet.sapling_z_addresses() {
+        let Some(key) = zcashd_wallet.sapling_keys().get(ivk) else {
+            continue;
+        };
+        let expsk = key.key().expsk();
+        let mut bytes = Vec::with_capacity(96);
+        bytes.extend_from_slice(expsk.ask().as_ref());
+        bytes.extend_from_slice(expsk.nsk().as_ref());
+        bytes.extend_from_slice(expsk.ovk().as_ref());
+        let Ok(expsk) = ExpandedSpendingKey::from_bytes(&bytes) else {
+            bail!("Invalid Sapling spending key for {}", address.to_string(zcashd_wallet.network()));
+        };
+        nks.insert(
+            KeySource::SaplingAddress(address.to_string(zcashd_wallet.network())),
+            expsk.proof_generation_key().to_viewing_key().nk,
+        );
+    }
+
+    for account in fvks {
+        let Some(dfvk) = account.sapling() else {
+            continue;
+        };
+        for (scope, name) in [(Scope::External, "external"), (Scope::Internal, "internal")] {
+            nks.insert(
+                KeySource::UnifiedAccount {
+                    ufvk_id: account.ufvk_id().to_string(),
+                    account_id: account.account_id(),
+                    scope: name,
+                },
+                dfvk.to_nk(scope),
+            );
+        }
+    }
+
+    Ok(nks)
+}
+
+/// The nullifier zcashd stored for a Sapling note, if any.
+fn stored_sapling_nullifier(wallet: &ZcashdWallet, txid: &TxId, index: u32) -> Option<[u8; 32]> {
+    let note_data = wallet.transactions().get(txid)?.sapling_note_data()?;
+    let (_, data) = note_data.iter().find(|(outpoint, _)| outpoint.vout() == index)?;
+    let nullifier = data.nullifier()?;
+    <[u8; 32]>::try_from(nullifier.as_ref()).ok()
+}
+
+/// The note commitment tree position of a migrated Sapling output. zcashd only
+/// records positions for notes it holds witnesses for.
+fn sapling_note_position(zewif: &ZewifTop, txid: &TxId, index: u32) -> Option<u64> {
+    let output = zewif
+        .transactions()
+        .get(txid)?
+        .sapling_outputs()?
+        .iter()
+        .find(|output| output.index() == index)?;
+    output.witness()?;
+    Some(u32::from(output.note_commitment_tree_position()).into())
+}
@@ -0,0 +1,79 @@
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches, Subcommand};
+
+use crate::{
+    exec::{ColorChoice, Exec},
+    export_cmd,
+    formats::Registry,
+    from_seed_cmd, list_formats_cmd, migrate_cmd, styles,
+};
+
+/// A tool for migrating Zcash wallets
+#[derive(Debug, clap::Parser)]
+#[command(author, version)]
+#[command(propagate_version = true)]
+#[command(styles=styles::get_styles())]
+struct Cli {
+    #[command(subcommand)]
+    command: MainCommands,
+
+    /// When to color the highlighted lines of reports.
+    #[arg(long, global = true, value_enum, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// The commands that work with every registered format. Each front-end
+/// with a subcommand of its own adds it alongside these.
+#[derive(Debug, Subcommand)]
+enum MainCommands {
+    ListFormats(list_formats_cmd::CommandArgs),
+    Migrate(migrate_cmd::CommandArgs),
+    Export(export_cmd::CommandArgs),
+    FromSeed(from_seed_cmd::CommandArgs),
+}
+
+/// The zmigrate command line: the generic commands, and a subcommand for
+/// each front-end in `registry` that has one.
+pub fn command(registry: &Registry) -> clap::Command {
+    let mut command = Cli::command();
+    for frontend in registry.frontends() {
+        if let Some(subcommand) = frontend.command() {
+            command = command.subcommand(subcommand);
+        }
+    }
+    command
+}
+
+/// Run the zmigrate command line with the formats in `registry`, writing
+/// to standard output.
+///
+/// A binary built with additional formats registers them on top of
+/// [`Registry::with_builtin`] and calls this from its `main`. Their names must
+/// not clash with the generic commands.
+pub fn run(registry: &Registry) -> Result<()> {
+    let matches = command(registry).get_matches();
+    matches.get_one::<ColorChoice>("color").copied().unwrap_or_default().apply();
+
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let frontend = matches.subcommand().and_then(|(name, submatches)| {
+        registry
+            .frontends()
+            .find(|frontend| frontend.name() == name && frontend.command().is_some())
+            .map(|frontend| (frontend, submatches))
+    });
+    let result = match frontend {
+        Some((frontend, submatches)) => frontend.exec_command(submatches, &mut out),
+        None => match Cli::from_arg_matches(&matches)?.command {
+            MainCommands::ListFormats(args) => args.exec_with(registry, &mut out),
+            MainCommands::Migrate(args) => args.exec_with(registry, &mut out),
+            MainCommands::Export(args) => args.exec_with(registry, &mut out),
+            MainCommands::FromSeed(args) => args.exec(&mut out),
+        },
+    };
+    // Whatever was written before an error is shown ahead of it.
+    out.flush()?;
+    result
+}
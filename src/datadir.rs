@@ -0,0 +1,141 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use zewif::Network;
+
+/// The magic number of a Berkeley DB btree file, stored little-endian at
+/// offset 12 of its metadata page.
+const BDB_BTREE_MAGIC: [u8; 4] = [0x62, 0x31, 0x05, 0x00];
+
+/// A wallet file found in a zcashd data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredWallet {
+    path: PathBuf,
+    datadir: PathBuf,
+    network: Network,
+    chain_cache: Option<PathBuf>,
+}
+
+impl DiscoveredWallet {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The network's data directory the wallet belongs to, which holds the
+    /// `.lock` and `database/` environment of the zcashd that uses it.
+    pub fn datadir(&self) -> &Path {
+        &self.datadir
+    }
+
+    /// The network implied by the directory the wallet was found in.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// The directory holding the chain's `blocks/index`, if one was found
+    /// alongside the wallet.
+    pub fn chain_cache(&self) -> Option<&Path> {
+        self.chain_cache.as_deref()
+    }
+}
+
+/// Find every wallet in a zcashd data directory.
+///
+/// `datadir` is searched along with its `testnet3` and `regtest`
+/// subdirectories, which zcashd uses for those networks. In each, the
+/// candidates are `wallet.dat`, files under `wallets/` (where `-wallet=`
+/// files are kept) and other `*wallet*.dat` files, such as the
+/// `nodeN_wallet.dat` files of a test chain cache; only files with a Berkeley
+/// DB btree header are returned. A chain cache next to the wallets is picked
+/// up with them.
+pub fn discover_wallets(datadir: &Path) -> Result<Vec<DiscoveredWallet>> {
+    if !datadir.is_dir() {
+        bail!("Not a directory: {}", datadir.display());
+    }
+
+    let mut result = Vec::new();
+    let network_dirs = [
+        (datadir.to_path_buf(), network_of_dir(datadir)),
+        (datadir.join("testnet3"), Network::Test),
+        (datadir.join("regtest"), Network::Regtest),
+    ];
+    for (dir, network) in network_dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        let chain_cache = find_chain_cache(&dir);
+
+        let mut candidates = vec![dir.join("wallet.dat")];
+        let wallets_dir = dir.join("wallets");
+        if wallets_dir.is_dir() {
+            candidates.extend(sorted_files(&wallets_dir)?);
+        }
+        candidates.extend(sorted_files(&dir)?.into_iter().filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name != "wallet.dat" && name.contains("wallet") && name.ends_with(".dat"))
+        }));
+
+        for path in candidates {
+            if is_bdb_file(&path)? {
+                result.push(DiscoveredWallet {
+                    path,
+                    datadir: dir.clone(),
+                    network,
+                    chain_cache: chain_cache.clone(),
+                });
+            }
+        }
+    }
+
+    if result.is_empty() {
+        bail!("No zcashd wallet files found in {}", datadir.display());
+    }
+    Ok(result)
+}
+
+/// The network of a data directory, from its name.
+fn network_of_dir(dir: &Path) -> Network {
+    match dir.file_name().and_then(|name| name.to_str()) {
+        Some("testnet3") => Network::Test,
+        Some("regtest") => Network::Regtest,
+        _ => Network::Main,
+    }
+}
+
+/// The directory holding `blocks/index`: either `dir` itself, as in a
+/// zcashd data directory, or a sibling `chain_cache` directory, as in the
+/// test fixtures.
+fn find_chain_cache(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("chain_cache")]
+        .into_iter()
+        .find(|candidate| candidate.join("blocks").join("index").is_dir())
+}
+
+fn sorted_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_bdb_file(path: &Path) -> Result<bool> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let mut header = [0u8; 16];
+    let mut file = File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    if file.read_exact(&mut header).is_err() {
+        return Ok(false);
+    }
+    Ok(header[12..16] == BDB_BTREE_MAGIC)
+}
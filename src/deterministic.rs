@@ -0,0 +1,231 @@
+use std::{
+    fmt::Debug,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use bc_envelope::prelude::*;
+use blake2b_simd::Params as Blake2bParams;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use zewif::{Account, ZewifTop, ZewifWallet};
+
+use crate::sqlite_export::wallet_seed;
+
+/// The environment variable that fixes the time written into output, as
+/// defined by the reproducible builds project.
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// The time given by `SOURCE_DATE_EPOCH`, in seconds since the Unix epoch, if
+/// it is set.
+pub fn source_date_epoch() -> Result<Option<u64>> {
+    match std::env::var(SOURCE_DATE_EPOCH) {
+        Ok(value) => parse_source_date_epoch(&value).map(Some),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Reading {}", SOURCE_DATE_EPOCH)),
+    }
+}
+
+/// Parse a value of `SOURCE_DATE_EPOCH`.
+pub fn parse_source_date_epoch(value: &str) -> Result<u64> {
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("{} is not a number of seconds: {}", SOURCE_DATE_EPOCH, value))
+}
+
+/// The time to record as an output's creation time: `SOURCE_DATE_EPOCH` if
+/// it is set, and otherwise the current time.
+pub fn creation_time() -> Result<u64> {
+    match source_date_epoch()? {
+        Some(time) => Ok(time),
+        None => Ok(SystemTime::now().duration_since(UNIX_EPOCH).context("Reading the clock")?.as_secs()),
+    }
+}
+
+/// Format seconds since the Unix epoch as an ISO 8601 UTC time, as zcashd
+/// writes it: `2024-01-01T00:00:00Z`.
+pub fn format_utc(time: u64) -> String {
+    let days = (time / 86_400) as i64;
+    let seconds = time % 86_400;
+
+    // The days-to-civil-date conversion of Howard Hinnant's `chrono`
+    // algorithms, over 400-year eras starting on 0000-03-01.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Rebuild a migrated wallet with identifiers derived from its contents
+/// rather than drawn at random, so that migrating the same wallet twice gives
+/// the same ZeWIF.
+///
+/// A wallet's ARID is derived from the ZIP-32 fingerprint of its seed, or if
+/// it has none from the addresses of its accounts. An account's is derived
+/// from its wallet's and its ZIP-32 account index, or if it has none from its
+/// own addresses, which stand in for the keys they were derived from. The
+/// ZeWIF's own ARID is derived from those of its wallets.
+pub fn canonicalize(zewif: &ZewifTop) -> Result<ZewifTop> {
+    let wallets = zewif.wallets().values().map(canonical_wallet).collect::<Result<Vec<_>>>()?;
+    let mut wallet_ids: Vec<Vec<u8>> = wallets.iter().map(|wallet| wallet.id().data().to_vec()).collect();
+    wallet_ids.sort();
+    let wallet_ids: Vec<&[u8]> = wallet_ids.iter().map(Vec::as_slice).collect();
+
+    // Cloning keeps the transactions and attachments; only the IDs change.
+    let mut canonical = zewif.clone();
+    canonical.set_id(derive_arid("zewif", &wallet_ids));
+    canonical.wallets_mut().clear();
+    for wallet in wallets {
+        canonical.add_wallet(wallet);
+    }
+    Ok(canonical)
+}
+
+fn canonical_wallet(wallet: &ZewifWallet) -> Result<ZewifWallet> {
+    let seed = wallet_seed(wallet).context("Fingerprinting the wallet's seed")?;
+    let wallet_id = match &seed {
+        Some(seed) => derive_arid("wallet", &[seed_fingerprint(seed.expose_secret()).as_slice()]),
+        None => {
+            let mut addresses: Vec<String> =
+                wallet.accounts().values().flat_map(account_addresses).collect();
+            addresses.sort();
+            derive_arid("wallet", &[addresses.join("\n").as_bytes()])
+        }
+    };
+
+    let mut canonical = wallet.clone();
+    canonical.set_id(wallet_id.clone());
+    canonical.accounts_mut().clear();
+    for account in wallet.accounts().values() {
+        let account_id = match (&seed, account.zip32_account_id()) {
+            (Some(_), Some(index)) => {
+                derive_arid("zip32-account", &[wallet_id.data().as_slice(), &index.to_le_bytes()])
+            }
+            _ => {
+                let mut addresses = account_addresses(account);
+                addresses.sort();
+                derive_arid(
+                    "account",
+                    &[wallet_id.data().as_slice(), account.name().as_bytes(), addresses.join("\n").as_bytes()],
+                )
+            }
+        };
+        let mut account = account.clone();
+        account.set_id(account_id);
+        canonical.add_account(account);
+    }
+    Ok(canonical)
+}
+
+fn account_addresses(account: &Account) -> Vec<String> {
+    account.addresses().values().map(|address| address.as_string()).collect()
+}
+
+/// The ZIP-32 seed fingerprint.
+pub(crate) fn seed_fingerprint(seed: &[u8]) -> [u8; 32] {
+    let hash = Blake2bParams::new()
+        .hash_length(32)
+        .personal(b"Zcash_HD_Seed_FP")
+        .to_state()
+        .update(&[seed.len() as u8])
+        .update(seed)
+        .finalize();
+    hash.as_bytes().try_into().expect("hash length is 32")
+}
+
+/// An ARID from SHA-256 over a domain and length-prefixed inputs, so that
+/// different inputs cannot run together into the same ID.
+fn derive_arid(domain: &str, inputs: &[&[u8]]) -> ARID {
+    let mut hasher = Sha256::new();
+    hasher.update(b"zmigrate-arid:");
+    hasher.update(domain.as_bytes());
+    for input in inputs {
+        hasher.update((input.len() as u64).to_le_bytes());
+        hasher.update(input);
+    }
+    ARID::from_data(hasher.finalize().into())
+}
+
+/// Pretty-print a value as `{:#?}` does, but with the entries of its maps and
+/// sets sorted, so that the same value always prints the same way.
+///
+/// `HashMap` and `HashSet` print in an order that differs from run to run.
+/// Their entries are told apart from struct fields by the line that opens
+/// them: a struct is opened by its name, a map or set by a bare `{`.
+pub fn sorted_debug(value: &dyn Debug) -> String {
+    let text = format!("{:#?}", value);
+    let lines: Vec<&str> = text.lines().collect();
+    sort_groups(&lines).join("\n")
+}
+
+/// Write a value's pretty `Debug` form and a line break to `out`, with its
+/// maps and sets sorted as by [`sorted_debug`] if `sorted` is set. Unsorted,
+/// it is written straight to `out` rather than built in memory first.
+pub fn write_debug(out: &mut impl std::io::Write, value: &dyn Debug, sorted: bool) -> std::io::Result<()> {
+    if sorted { writeln!(out, "{}", sorted_debug(value)) } else { writeln!(out, "{:#?}", value) }
+}
+
+fn sort_groups(lines: &[&str]) -> Vec<String> {
+    let mut sorted = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        sorted.push(line.to_string());
+        i += 1;
+        if !opens_map_or_set(line) {
+            continue;
+        }
+
+        // The group's entries are indented one level deeper than the line
+        // that opens it, and it is closed at that line's own indentation.
+        let indent = indentation(line);
+        let end = (i..lines.len())
+            .find(|&j| indentation(lines[j]) == indent && lines[j].trim_start().starts_with('}'))
+            .unwrap_or(lines.len());
+        let mut entries = Vec::new();
+        let mut start = i;
+        for j in i + 1..=end {
+            let starts_entry = j < end
+                && indentation(lines[j]) == indent + 4
+                && !lines[j].trim_start().starts_with(['}', ']', ')']);
+            if j == end || starts_entry {
+                entries.push(sort_groups(&lines[start..j]).join("\n"));
+                start = j;
+            }
+        }
+        entries.sort();
+        sorted.extend(entries);
+        i = end;
+    }
+    sorted
+}
+
+fn opens_map_or_set(line: &str) -> bool {
+    match line.trim_end().strip_suffix('{') {
+        Some(head) => {
+            let head = head.trim();
+            head.is_empty() || head.ends_with(':')
+        }
+        None => false,
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
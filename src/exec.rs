@@ -1,5 +1,47 @@
+use std::io::Write;
+
 use anyhow::Result;
+use clap::ValueEnum;
 
 pub trait Exec {
-    fn exec(&self) -> Result<String>;
+    /// Run the command, writing its output to `out` section by section as
+    /// it is produced. Output written before an error is left in `out`.
+    fn exec(&self, out: &mut impl Write) -> Result<()>;
+}
+
+/// End an output section and flush it, so that it is seen while the next
+/// one is being produced.
+pub fn end_section(out: &mut impl Write) -> Result<()> {
+    writeln!(out, "---")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Collect the output of a writer into a string, for callers that want the
+/// whole output at once.
+pub fn collect_output(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> Result<String> {
+    let mut buffer = Vec::new();
+    write(&mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// When to color the highlighted lines of reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when standard output is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Make this choice for all output written from now on.
+    pub fn apply(self) {
+        match self {
+            ColorChoice::Auto => owo_colors::unset_override(),
+            ColorChoice::Always => owo_colors::set_override(true),
+            ColorChoice::Never => owo_colors::set_override(false),
+        }
+    }
 }
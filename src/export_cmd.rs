@@ -0,0 +1,104 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use zewif::Network;
+
+use crate::{
+    deterministic::canonicalize,
+    file_args::{FileArgs, FileArgsLike},
+    formats::{BackendOptions, FrontendOptions, Registry},
+    from_seed_cmd::parse_network,
+    zcashd_cmd::{MigrationArgs, MigrationOptions},
+};
+
+/// Export a migrated wallet to another wallet's format
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    /// The format to write, as given by `list-formats`: `keydump` writes a
+    /// `z_exportwallet`-compatible key dump that zcashd can import with
+    /// `z_importwallet`, and `sqlite` a new `zcash_client_sqlite` wallet
+    /// database, as used by light wallets.
+    to: String,
+
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// The format of the source wallet file, as given by `list-formats`, or
+    /// `auto` to detect it.
+    #[arg(long, default_value = "auto")]
+    from: String,
+
+    /// The network the wallet is used on, for formats that do not record it.
+    #[arg(long, value_parser = parse_network)]
+    network: Option<Network>,
+
+    /// Read the password of an encrypted wallet from this file instead of
+    /// prompting for it.
+    #[arg(long)]
+    password_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    zcashd: MigrationArgs,
+
+    /// Write the exported wallet to this file. Formats that can be written
+    /// to standard output are, if it is not given.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Write the wallet's mnemonic and HD seed rather than redacting them.
+    #[arg(long)]
+    include_seed: bool,
+
+    /// lightwalletd `GetTreeState` JSON for a block at or below the wallet's
+    /// birthday, from which a new light wallet scans. Without it the wallet
+    /// scans from Sapling activation.
+    #[arg(long)]
+    tree_state: Option<PathBuf>,
+
+    /// Derive the migrated wallet's IDs from its contents rather than at
+    /// random, so that the same wallet always exports the same way. The
+    /// creation time written into a key dump honors `SOURCE_DATE_EPOCH`.
+    #[arg(long)]
+    deterministic: bool,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        &self.file_args.file
+    }
+}
+
+impl CommandArgs {
+    pub fn exec_with(&self, registry: &Registry, out: &mut impl Write) -> Result<()> {
+        // Look up the back-end first, so an unknown one fails before the
+        // wallet is read.
+        let backend = registry.backend(&self.to)?;
+        let frontend = registry.resolve(&self.from, self.file())?;
+        let frontend_options = FrontendOptions {
+            network: self.network,
+            password_file: self.password_file.clone(),
+            zcashd: MigrationOptions { deterministic: self.deterministic, ..self.zcashd.options() },
+        };
+        let mut zewif = frontend
+            .migrate(self.file(), &frontend_options)
+            .with_context(|| format!("Migrating {} as {}", self.file().display(), frontend.name()))?;
+        if self.deterministic {
+            zewif = canonicalize(&zewif)?;
+        }
+        let backend_options = BackendOptions {
+            include_seed: self.include_seed,
+            tree_state: self.tree_state.clone(),
+        };
+        let written = backend.write(&zewif, self.output.as_deref(), &backend_options)?;
+        writeln!(out, "{}", written.trim_end())?;
+        Ok(())
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        self.exec_with(&Registry::with_builtin(), out)
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use zewif::{
+    Account, Address, Bip39Mnemonic, Blob, Network, ProtocolAddress, SeedMaterial,
+    ShieldedAddress, SpendingKey, TransparentAddress, TransparentSpendAuthority,
+    TransparentSpendingKey, ZewifTop, ZewifWallet, sprout::SproutSpendingKey, u252,
+};
+
+use crate::{
+    deterministic::seed_fingerprint,
+    keydump::{decode_sapling_extsk, decode_sprout_sk, decode_wif},
+    note_decryption::ZMIGRATE_VENDOR,
+    seed_wallet::validate_phrase,
+};
+
+/// The account index under which zcashd derives its legacy transparent and
+/// Sapling keys from the mnemonic seed, outside any unified account.
+const LEGACY_ACCOUNT: u32 = 0x7FFF_FFFF;
+
+/// The protocol of a key in a `z_exportwallet` or `dumpwallet` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportedKeyKind {
+    /// A WIF-encoded secp256k1 private key.
+    Transparent,
+    /// A Sprout spending key (`SK…`/`ST…`).
+    Sprout,
+    /// A Sapling extended spending key (`secret-extended-key-…`).
+    Sapling,
+}
+
+/// One key line of an exported wallet.
+#[derive(Debug, Clone)]
+pub struct ExportedKey {
+    kind: ExportedKeyKind,
+    key: String,
+    created: String,
+    address: String,
+    label: Option<String>,
+    purpose: Option<String>,
+    hd_keypath: Option<String>,
+    seed_fp: Option<String>,
+}
+
+impl ExportedKey {
+    pub fn kind(&self) -> ExportedKeyKind {
+        self.kind
+    }
+
+    /// The encoded private key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The key's creation time, as written by zcashd (ISO 8601). It is kept
+    /// through migration, and written back by the `keydump` back-end.
+    pub fn created(&self) -> &str {
+        &self.created
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// `change` or `reserve` for transparent keys that zcashd marked so.
+    pub fn purpose(&self) -> Option<&str> {
+        self.purpose.as_deref()
+    }
+
+    pub fn hd_keypath(&self) -> Option<&str> {
+        self.hd_keypath.as_deref()
+    }
+
+    /// The fingerprint of the seed the key was derived from, as zcashd
+    /// writes it for Sapling keys (byte-reversed hex).
+    pub fn seed_fp(&self) -> Option<&str> {
+        self.seed_fp.as_deref()
+    }
+
+    /// The account the key belongs to: its ZIP-32 or BIP-44 account index
+    /// for keys derived at `m/32'/coin'/account'…` or `m/44'/coin'/account'…`.
+    ///
+    /// zcashd's legacy account `0x7FFFFFFF` is not a ZIP-32 account. Nor is
+    /// a Sapling key from a seed other than the mnemonic: before zcashd had
+    /// a mnemonic, it derived each Sapling key from the legacy HD seed at a
+    /// `m/32'/coin'/n'` path of its own.
+    fn account_group(&self, mnemonic_fingerprint: Option<&[u8; 32]>) -> AccountGroup {
+        let Some(path) = &self.hd_keypath else {
+            return AccountGroup::Imported;
+        };
+        let components: Vec<&str> = path.split('/').collect();
+        let account = match components.as_slice() {
+            ["m", "32'" | "44'", _coin, account, ..] => {
+                account.trim_end_matches(['\'', 'h']).parse::<u32>().ok()
+            }
+            _ => None,
+        };
+        match account {
+            None | Some(LEGACY_ACCOUNT) => AccountGroup::LegacyHd,
+            Some(_)
+                if self.kind == ExportedKeyKind::Sapling
+                    && !self.is_from_seed(mnemonic_fingerprint) =>
+            {
+                AccountGroup::LegacyHd
+            }
+            Some(account) => AccountGroup::Zip32(account),
+        }
+    }
+
+    /// Whether the key was derived from the seed with `fingerprint`. A key
+    /// whose export records no seed fingerprint is taken to be from the
+    /// mnemonic seed, if the wallet has one.
+    fn is_from_seed(&self, fingerprint: Option<&[u8; 32]>) -> bool {
+        let Some(fingerprint) = fingerprint else {
+            return false;
+        };
+        match &self.seed_fp {
+            None => true,
+            Some(seed_fp) => {
+                let mut reversed = *fingerprint;
+                reversed.reverse();
+                seed_fp.eq_ignore_ascii_case(&hex::encode(reversed))
+            }
+        }
+    }
+}
+
+/// How keys are grouped into rebuilt accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AccountGroup {
+    Zip32(u32),
+    /// Keys derived from the legacy HD seed, and those zcashd derives from
+    /// the mnemonic seed for its legacy account.
+    LegacyHd,
+    /// Keys with no HD path, which were imported or randomly generated.
+    Imported,
+}
+
+impl AccountGroup {
+    fn name(&self) -> String {
+        match self {
+            AccountGroup::Zip32(account) => format!("Account #{}", account),
+            AccountGroup::LegacyHd => "Legacy HD keys".to_string(),
+            AccountGroup::Imported => "Imported keys".to_string(),
+        }
+    }
+}
+
+/// The contents of a text wallet export written by zcashd's `z_exportwallet`
+/// or `dumpwallet`.
+#[derive(Debug, Clone, Default)]
+pub struct ExportedWallet {
+    created_by: Option<String>,
+    created_on: Option<String>,
+    best_block: Option<String>,
+    recovery_phrase: Option<String>,
+    language: Option<String>,
+    hd_seed: Option<String>,
+    keys: Vec<ExportedKey>,
+}
+
+impl ExportedWallet {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut wallet = Self::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                wallet.parse_comment(comment.trim());
+                continue;
+            }
+            let key = parse_key_line(line)
+                .with_context(|| format!("Parsing line {} of the wallet export", line_number + 1))?;
+            wallet.keys.push(key);
+        }
+        if wallet.keys.is_empty() && wallet.recovery_phrase.is_none() && wallet.hd_seed.is_none() {
+            bail!("No keys or seeds found; is this a z_exportwallet file?");
+        }
+        Ok(wallet)
+    }
+
+    fn parse_comment(&mut self, comment: &str) {
+        if let Some(version) = comment.strip_prefix("Wallet dump created by ") {
+            self.created_by = Some(version.to_string());
+        } else if let Some(created) = comment.strip_prefix("* Created on ") {
+            self.created_on = Some(created.to_string());
+        } else if let Some(best_block) = comment.strip_prefix("* Best block at time of backup was ") {
+            self.best_block = Some(best_block.trim_end_matches(',').to_string());
+        } else if let Some(phrase) = comment.strip_prefix("- recovery_phrase=") {
+            self.recovery_phrase = Some(phrase.trim_matches('"').to_string());
+        } else if let Some(language) = comment.strip_prefix("- language=") {
+            self.language = Some(language.to_string());
+        } else if let Some(seed) = comment.strip_prefix("HDSeed=") {
+            self.hd_seed = seed.split_whitespace().next().map(str::to_string);
+        }
+    }
+
+    /// The zcashd version that wrote the export.
+    pub fn created_by(&self) -> Option<&str> {
+        self.created_by.as_deref()
+    }
+
+    pub fn created_on(&self) -> Option<&str> {
+        self.created_on.as_deref()
+    }
+
+    /// The height and hash of the best block when the export was written.
+    pub fn best_block(&self) -> Option<&str> {
+        self.best_block.as_deref()
+    }
+
+    /// The BIP-39 recovery phrase of the wallet's mnemonic seed.
+    pub fn recovery_phrase(&self) -> Option<&str> {
+        self.recovery_phrase.as_deref()
+    }
+
+    /// The hex-encoded legacy HD seed.
+    pub fn hd_seed(&self) -> Option<&str> {
+        self.hd_seed.as_deref()
+    }
+
+    pub fn keys(&self) -> &[ExportedKey] {
+        &self.keys
+    }
+
+    /// The network the keys belong to, from the encoding of their addresses.
+    /// Regtest transparent addresses share the testnet prefixes, so any
+    /// regtest Sapling address decides it.
+    pub fn network(&self) -> Network {
+        let networks: Vec<Network> =
+            self.keys.iter().filter_map(|key| network_of(&key.address)).collect();
+        if networks.contains(&Network::Regtest) {
+            return Network::Regtest;
+        }
+        networks.first().copied().unwrap_or(Network::Main)
+    }
+
+    /// Rebuild the wallet as ZeWIF. Keys are grouped into accounts by the
+    /// account index in their HD path; keys from the legacy HD seed or
+    /// zcashd's legacy account, and keys without an HD path, each get their
+    /// own account.
+    ///
+    /// The recovery phrase, or failing that the legacy HD seed, becomes the
+    /// wallet's seed material, and each key becomes the spending key of its
+    /// address. ZeWIF holds one seed per wallet, so a legacy HD seed exported
+    /// alongside a recovery phrase is kept as an attachment, as are the key
+    /// creation times and HD paths, which it has no fields for.
+    pub fn to_zewif(&self) -> Result<ZewifTop> {
+        let network = self.network();
+        let mut zewif_wallet = ZewifWallet::new(network);
+
+        let hd_seed = self
+            .hd_seed
+            .as_deref()
+            .map(|hd_seed| -> Result<[u8; 32]> {
+                hex::decode(hd_seed)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .context("The HDSeed is not 32 hex-encoded bytes")
+            })
+            .transpose()?;
+        match (&self.recovery_phrase, hd_seed) {
+            (Some(phrase), hd_seed) => {
+                zewif_wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+                    phrase.clone(),
+                )));
+                if let Some(hd_seed) = hd_seed {
+                    let envelope = Envelope::new("LegacyHdSeed")
+                        .add_assertion("seed", hex::encode(hd_seed));
+                    zewif_wallet.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+                }
+            }
+            (None, Some(hd_seed)) => {
+                zewif_wallet.set_seed_material(SeedMaterial::PreBIP39Seed(Blob::new(hd_seed)));
+            }
+            (None, None) => {}
+        }
+
+        let mnemonic_fingerprint = self
+            .recovery_phrase
+            .as_deref()
+            .map(|phrase| -> Result<[u8; 32]> {
+                Ok(seed_fingerprint(&validate_phrase(phrase)?.to_seed("")))
+            })
+            .transpose()?;
+        let mut groups: BTreeMap<AccountGroup, Vec<&ExportedKey>> = BTreeMap::new();
+        for key in &self.keys {
+            groups.entry(key.account_group(mnemonic_fingerprint.as_ref())).or_default().push(key);
+        }
+
+        for (group, keys) in groups {
+            let mut account = Account::new();
+            account.set_name(group.name());
+            if let AccountGroup::Zip32(account_id) = group {
+                account.set_zip32_account_id(account_id);
+            }
+            for key in keys {
+                let protocol_address = match key.kind {
+                    ExportedKeyKind::Transparent => {
+                        let mut address = TransparentAddress::new(key.address.clone());
+                        address.set_spend_authority(TransparentSpendAuthority::SpendingKey(
+                            TransparentSpendingKey::new(decode_wif(&key.key)?),
+                        ));
+                        ProtocolAddress::Transparent(address)
+                    }
+                    ExportedKeyKind::Sprout => {
+                        let mut address = ShieldedAddress::new(key.address.clone());
+                        address.set_spending_key(SpendingKey::Sprout(SproutSpendingKey::new(
+                            u252::from_slice(&decode_sprout_sk(&key.key)?)?,
+                        )));
+                        ProtocolAddress::Shielded(address)
+                    }
+                    ExportedKeyKind::Sapling => {
+                        let mut address = ShieldedAddress::new(key.address.clone());
+                        address.set_spending_key(SpendingKey::Sapling(decode_sapling_extsk(
+                            &key.key,
+                        )?));
+                        ProtocolAddress::Shielded(address)
+                    }
+                };
+                let mut address = Address::new(protocol_address);
+                address.set_name(key.label.clone().unwrap_or_default());
+                if let Some(purpose) = &key.purpose {
+                    address.set_purpose(purpose.clone());
+                }
+
+                let mut metadata =
+                    Envelope::new("KeyMetadata").add_assertion("created", key.created.clone());
+                if let Some(hd_keypath) = &key.hd_keypath {
+                    metadata = metadata.add_assertion("hdKeypath", hd_keypath.clone());
+                }
+                address.add_attachment(metadata, ZMIGRATE_VENDOR, None);
+                account.add_address(address);
+            }
+            zewif_wallet.add_account(account);
+        }
+
+        let mut zewif = ZewifTop::new();
+        zewif.add_wallet(zewif_wallet);
+        Ok(zewif)
+    }
+}
+
+/// Parse a key line: `<key> <time> [label=…|change=1|reserve=1|hdkeypath=…]
+/// # addr=… [hdkeypath=…] [seedFp=…]`, where Sprout and Sapling keys use
+/// `zaddr=`.
+fn parse_key_line(line: &str) -> Result<ExportedKey> {
+    let (fields, comment) = line.split_once('#').unwrap_or((line, ""));
+    let mut fields = fields.split_whitespace();
+    let key = fields.next().context("Missing key")?.to_string();
+    let created = fields.next().context("Missing key creation time")?.to_string();
+
+    let kind = if key.starts_with("secret-extended-key-") {
+        ExportedKeyKind::Sapling
+    } else if key.starts_with("SK") || key.starts_with("ST") {
+        ExportedKeyKind::Sprout
+    } else {
+        ExportedKeyKind::Transparent
+    };
+
+    let mut label = None;
+    let mut purpose = None;
+    let mut hd_keypath = None;
+    let mut seed_fp = None;
+    let mut address = None;
+    for field in fields.chain(comment.split_whitespace()) {
+        match field.split_once('=') {
+            Some(("label", value)) => label = Some(percent_decode(value)),
+            Some(("change", "1")) => purpose = Some("change".to_string()),
+            Some(("reserve", "1")) => purpose = Some("reserve".to_string()),
+            Some(("hdkeypath", value)) => hd_keypath = Some(value.to_string()),
+            Some(("seedFp", value)) => seed_fp = Some(value.to_string()),
+            Some(("addr" | "zaddr", value)) => address = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    let address = address.with_context(|| format!("Missing address for key created {}", created))?;
+
+    Ok(ExportedKey { kind, key, created, address, label, purpose, hd_keypath, seed_fp })
+}
+
+/// zcashd percent-encodes labels so that they contain no spaces.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The network of an encoded address, from its prefix.
+fn network_of(address: &str) -> Option<Network> {
+    const PREFIXES: [(&str, Network); 9] = [
+        ("zregtestsapling", Network::Regtest),
+        ("ztestsapling", Network::Test),
+        ("zt", Network::Test),
+        ("tm", Network::Test),
+        ("t2", Network::Test),
+        ("zs", Network::Main),
+        ("zc", Network::Main),
+        ("t1", Network::Main),
+        ("t3", Network::Main),
+    ];
+    PREFIXES
+        .iter()
+        .find(|(prefix, _)| address.starts_with(prefix))
+        .map(|(_, network)| *network)
+}
@@ -0,0 +1,49 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    exec::collect_output,
+    file_args::{FileArgs, FileArgsLike},
+    formats::{ExportwalletFrontend, FrontendOptions, WalletFrontend, dump_source_wallet},
+    json_output::OutputFormat,
+};
+
+/// Process a text wallet export from zcashd's `z_exportwallet` or `dumpwallet`
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// How to render the source and migrated wallets.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        &self.file_args.file
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        dump_wallet_to(self.file(), self.format, out)
+    }
+}
+
+pub fn dump_wallet(file: &Path) -> Result<String> {
+    collect_output(|out| dump_wallet_to(file, OutputFormat::Text, out))
+}
+
+/// Dump and migrate a wallet export, writing each section to `out` as soon
+/// as it is ready.
+pub fn dump_wallet_to(file: &Path, format: OutputFormat, out: &mut impl Write) -> Result<()> {
+    let wallet = ExportwalletFrontend.parse(file, &FrontendOptions::default())?;
+    dump_source_wallet(wallet.as_ref(), None, format, false, out)
+}
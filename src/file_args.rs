@@ -1,19 +1,32 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
-use std::{io::Read, path::PathBuf};
+use std::path::{Path, PathBuf};
 
 use zewif::Data;
 
+use crate::mapped_file::MappedFile;
+
 pub trait FileArgsLike {
     fn file(&self) -> &PathBuf;
 
+    /// Map the file into memory, to be read in place.
+    fn map_file(&self) -> Result<MappedFile> {
+        MappedFile::open(self.file())
+    }
+
+    /// Read the whole file into memory, for parsers that need an owned
+    /// `Data`.
     fn read_file(&self) -> Result<Data> {
-        let mut vec = Vec::new();
-        std::fs::File::open(self.file())?.read_to_end(&mut vec)?;
-        Ok(vec.into())
+        read_data(self.file())
     }
 }
 
+/// Read a whole file into an owned `Data`, in a single buffer of its size.
+pub fn read_data(path: &Path) -> Result<Data> {
+    let bytes = std::fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+    Ok(bytes.into())
+}
+
 #[derive(Debug, Args)]
 #[group(skip)]
 pub struct FileArgs {
@@ -0,0 +1,768 @@
+use std::{
+    fmt::{Debug, Write},
+    io::{self, Read, Write as _},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use zeroize::Zeroizing;
+use zewif::{Network, ZewifTop};
+use zewif_zcashd::ZcashdWallet;
+use zewif_zingo::{ZingoParser, ZingoWallet};
+
+use crate::{
+    deterministic::{canonicalize, write_debug},
+    exec::{Exec, end_section},
+    exported_wallet::ExportedWallet,
+    exportwallet_cmd,
+    file_args::read_data,
+    json_output::{JsonObjectWriter, OutputFormat, debug_to_json, report_to_json, zewif_diagnostic, zewif_to_json},
+    keydump::write_keydump,
+    mapped_file::MappedFile,
+    salvage::SalvageReport,
+    sqlite_cmd,
+    sqlite_export::{parse_tree_state, write_sqlite_wallet},
+    sqlite_wallet::SqliteWallet,
+    ywallet::YwalletDatabase,
+    ywallet_cmd,
+    zcashd_cmd::{self, MigrationOptions},
+    zecwallet::{self, ZecwalletWallet},
+    zecwallet_cmd, zingo_cmd,
+};
+
+/// A wallet read by a front-end, ready to be migrated to ZeWIF.
+pub trait SourceWallet: Debug {
+    /// Migrate the wallet, returning the migrated ZeWIF and its migration
+    /// quality report.
+    fn migrate(&self) -> Result<(ZewifTop, String)>;
+}
+
+/// Options that front-ends may need to read a wallet. Each front-end uses
+/// only those that apply to its format.
+#[derive(Debug, Clone, Default)]
+pub struct FrontendOptions {
+    /// The network of a wallet that does not record it.
+    pub network: Option<Network>,
+    /// A file holding the password of an encrypted wallet. Without it, the
+    /// password is prompted for.
+    pub password_file: Option<PathBuf>,
+    /// The optional passes to run while migrating a zcashd wallet.
+    pub zcashd: MigrationOptions,
+}
+
+/// A wallet format that zmigrate can read and migrate to ZeWIF.
+pub trait WalletFrontend: Send + Sync {
+    /// The name by which the format is chosen on the command line.
+    fn name(&self) -> &str;
+
+    /// A one-line description of the format, for `list-formats`.
+    fn description(&self) -> &str;
+
+    /// Whether the file at `path` is in this format. Files in other formats
+    /// give `Ok(false)`; only errors reading the file are returned.
+    fn detect(&self, path: &Path) -> Result<bool>;
+
+    /// Read a wallet in this format.
+    fn parse(&self, path: &Path, options: &FrontendOptions) -> Result<Box<dyn SourceWallet>>;
+
+    /// Read a wallet in this format and migrate it to ZeWIF.
+    fn migrate(&self, path: &Path, options: &FrontendOptions) -> Result<ZewifTop> {
+        let (zewif, _) = self.parse(path, options)?.migrate()?;
+        Ok(zewif)
+    }
+
+    /// The format's own subcommand, named after it, which offers what the
+    /// generic `migrate` command does not. Formats without one are read with
+    /// `migrate --from`.
+    fn command(&self) -> Option<Command> {
+        None
+    }
+
+    /// Run the subcommand returned by [`command`](Self::command) with the
+    /// arguments it was given.
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        let _ = (matches, out);
+        bail!("The {} format has no subcommand", self.name())
+    }
+}
+
+/// A front-end subcommand whose arguments are `A`.
+pub fn frontend_command<A: Args>(name: &str) -> Command {
+    A::augment_args(Command::new(name.to_string()))
+}
+
+/// Run a front-end subcommand whose arguments are `A`.
+pub fn exec_frontend_command<A: FromArgMatches + Exec>(
+    matches: &ArgMatches,
+    mut out: &mut dyn io::Write,
+) -> Result<()> {
+    A::from_arg_matches(matches)?.exec(&mut out)
+}
+
+/// What a back-end keeps of a migrated wallet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    pub seeds: bool,
+    pub spending_keys: bool,
+    pub viewing_keys: bool,
+    pub transactions: bool,
+    pub note_commitment_trees: bool,
+}
+
+impl BackendCapabilities {
+    /// The names of the kinds of data that are kept.
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.seeds, "seeds"),
+            (self.spending_keys, "spending keys"),
+            (self.viewing_keys, "viewing keys"),
+            (self.transactions, "transactions"),
+            (self.note_commitment_trees, "note commitment trees"),
+        ]
+        .into_iter()
+        .filter_map(|(kept, name)| kept.then_some(name))
+        .collect()
+    }
+}
+
+/// Options that back-ends may use when writing a wallet. Each back-end uses
+/// only those that apply to its format.
+#[derive(Debug, Clone, Default)]
+pub struct BackendOptions {
+    /// Write seed material rather than redacting it.
+    pub include_seed: bool,
+    /// A lightwalletd `GetTreeState` JSON file for the block from which a
+    /// new light wallet scans.
+    pub tree_state: Option<PathBuf>,
+}
+
+/// A wallet format that zmigrate can write from ZeWIF.
+pub trait WalletBackend: Send + Sync {
+    /// The name by which the format is chosen on the command line.
+    fn name(&self) -> &str;
+
+    /// A one-line description of the format, for `list-formats`.
+    fn description(&self) -> &str;
+
+    /// What this format keeps of a migrated wallet.
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Write `zewif` to `output`, returning a summary of what was written.
+    /// Back-ends that can write to standard output return the written wallet
+    /// itself when `output` is `None`.
+    fn write(&self, zewif: &ZewifTop, output: Option<&Path>, options: &BackendOptions) -> Result<String>;
+}
+
+/// The wallet formats available to the command line.
+///
+/// A binary built with its own front-ends or back-ends registers them on
+/// top of the built-in ones, and the generic `list-formats`, `migrate` and
+/// `export` commands then offer them alongside the rest.
+#[derive(Default)]
+pub struct Registry {
+    frontends: Vec<Box<dyn WalletFrontend>>,
+    backends: Vec<Box<dyn WalletBackend>>,
+}
+
+impl Registry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry of the formats built into zmigrate.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_frontend(ZcashdFrontend)
+            .register_frontend(ZingoFrontend)
+            .register_frontend(ExportwalletFrontend)
+            .register_frontend(ZecwalletFrontend)
+            .register_frontend(SqliteFrontend)
+            .register_frontend(YwalletFrontend)
+            .register_backend(KeydumpBackend)
+            .register_backend(SqliteBackend);
+        registry
+    }
+
+    /// Add a front-end, replacing any registered under the same name.
+    pub fn register_frontend(&mut self, frontend: impl WalletFrontend + 'static) -> &mut Self {
+        self.frontends.retain(|f| f.name() != frontend.name());
+        self.frontends.push(Box::new(frontend));
+        self
+    }
+
+    /// Add a back-end, replacing any registered under the same name.
+    pub fn register_backend(&mut self, backend: impl WalletBackend + 'static) -> &mut Self {
+        self.backends.retain(|b| b.name() != backend.name());
+        self.backends.push(Box::new(backend));
+        self
+    }
+
+    pub fn frontends(&self) -> impl Iterator<Item = &dyn WalletFrontend> {
+        self.frontends.iter().map(|f| f.as_ref())
+    }
+
+    pub fn backends(&self) -> impl Iterator<Item = &dyn WalletBackend> {
+        self.backends.iter().map(|b| b.as_ref())
+    }
+
+    pub fn frontend(&self, name: &str) -> Result<&dyn WalletFrontend> {
+        self.frontends().find(|f| f.name() == name).with_context(|| {
+            let names: Vec<_> = self.frontends().map(|f| f.name()).collect();
+            format!("Unknown source format {}; expected one of: {}", name, names.join(", "))
+        })
+    }
+
+    pub fn backend(&self, name: &str) -> Result<&dyn WalletBackend> {
+        self.backends().find(|b| b.name() == name).with_context(|| {
+            let names: Vec<_> = self.backends().map(|b| b.name()).collect();
+            format!("Unknown export format {}; expected one of: {}", name, names.join(", "))
+        })
+    }
+
+    /// The one front-end that recognizes the file at `path`.
+    pub fn detect(&self, path: &Path) -> Result<&dyn WalletFrontend> {
+        let mut matches = Vec::new();
+        for frontend in self.frontends() {
+            if frontend
+                .detect(path)
+                .with_context(|| format!("Detecting the format of {}", path.display()))?
+            {
+                matches.push(frontend);
+            }
+        }
+        match matches.as_slice() {
+            [frontend] => Ok(*frontend),
+            [] => bail!("{} is not in any known wallet format", path.display()),
+            _ => {
+                let names: Vec<_> = matches.iter().map(|f| f.name()).collect();
+                bail!(
+                    "{} could be in any of these formats: {}; choose one with --from",
+                    path.display(),
+                    names.join(", ")
+                )
+            }
+        }
+    }
+
+    /// The front-end named `from`, or the one that recognizes the file at
+    /// `path` if `from` is `auto`.
+    pub fn resolve(&self, from: &str, path: &Path) -> Result<&dyn WalletFrontend> {
+        if from == "auto" { self.detect(path) } else { self.frontend(from) }
+    }
+}
+
+/// Dump a source wallet and the ZeWIF it migrates to, followed by the
+/// migration quality report, writing each section to `out` as soon as it is
+/// ready. `source_format` names the front-end that read the wallet, when it
+/// was not chosen by the command itself. If `deterministic` is set, the
+/// migrated wallet's IDs are derived from its contents and its maps and sets
+/// are written sorted, so that the same wallet always gives the same dump.
+pub fn dump_source_wallet(
+    wallet: &dyn SourceWallet,
+    source_format: Option<&str>,
+    format: OutputFormat,
+    deterministic: bool,
+    out: &mut impl io::Write,
+) -> Result<()> {
+    let migrate = || -> Result<(ZewifTop, String)> {
+        let (zewif_wallet, report) = wallet.migrate().context("Migrating to Zewif")?;
+        if deterministic {
+            return Ok((canonicalize(&zewif_wallet)?, report));
+        }
+        Ok((zewif_wallet, report))
+    };
+
+    if format == OutputFormat::Json {
+        // Everything is computed before the object is opened, so that an
+        // error cannot cut it short; a failed migration is recorded in it.
+        let source_wallet = debug_to_json(&wallet).context("Rendering source wallet")?;
+        let migrated =
+            migrate().map(|(zewif_wallet, report)| (zewif_to_json(&zewif_wallet), report));
+
+        let mut object = JsonObjectWriter::new(out)?;
+        if let Some(source_format) = source_format {
+            object.field("source_format", &source_format.into())?;
+        }
+        object.field("source_wallet", &source_wallet)?;
+        return match migrated {
+            Ok((migrated_wallet, report)) => {
+                object.field("migrated_wallet", &migrated_wallet)?;
+                object.field("report", &report_to_json(&report))?;
+                object.field("success", &true.into())?;
+                object.finish()
+            }
+            Err(error) => {
+                object.field("error", &format!("{:#}", error).into())?;
+                object.field("success", &false.into())?;
+                object.finish()?;
+                Err(error)
+            }
+        };
+    }
+
+    if let Some(source_format) = source_format {
+        writeln!(out, "Source format: {}", source_format)?;
+        end_section(out)?;
+    }
+    writeln!(out, "Source wallet:")?;
+    write_debug(out, &wallet, deterministic)?;
+    end_section(out)?;
+
+    let (zewif_wallet, report) = migrate()?;
+    if format == OutputFormat::CborDiag {
+        writeln!(out, "Migrated wallet:\n{}", zewif_diagnostic(&zewif_wallet))?;
+    } else {
+        writeln!(out, "Migrated wallet:")?;
+        write_debug(out, &zewif_wallet, deterministic)?;
+    }
+    end_section(out)?;
+    writeln!(out, "{}", report)?;
+    end_section(out)?;
+    writeln!(out, "✅ Success")?;
+    Ok(())
+}
+
+/// Read up to `len` bytes from the start of a file.
+fn read_header(path: &Path, len: usize) -> Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(len);
+    std::fs::File::open(path)
+        .with_context(|| format!("Opening {}", path.display()))?
+        .take(len as u64)
+        .read_to_end(&mut header)
+        .with_context(|| format!("Reading {}", path.display()))?;
+    Ok(header)
+}
+
+/// The version number that Zecwallet Lite and zingo wallets begin with.
+fn leading_version(path: &Path) -> Result<Option<u64>> {
+    let header = read_header(path, 8)?;
+    Ok(header.try_into().ok().map(u64::from_le_bytes))
+}
+
+fn is_sqlite(path: &Path) -> Result<bool> {
+    Ok(read_header(path, 16)? == b"SQLite format 3\0")
+}
+
+pub struct ZcashdFrontend;
+
+impl WalletFrontend for ZcashdFrontend {
+    fn name(&self) -> &str {
+        "zcashd"
+    }
+
+    fn description(&self) -> &str {
+        "zcashd wallet.dat (Berkeley DB)"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        // The Berkeley DB btree magic number, in either byte order.
+        let header = read_header(path, 16)?;
+        Ok(header.get(12..16).is_some_and(|magic| {
+            magic == [0x62, 0x31, 0x05, 0x00] || magic == [0x00, 0x05, 0x31, 0x62]
+        }))
+    }
+
+    fn parse(&self, path: &Path, options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let (wallet, salvage_report) = zcashd_cmd::parse_wallet(path, &options.zcashd)?;
+        Ok(Box::new(ZcashdSource { wallet, salvage_report, options: options.zcashd.clone() }))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<zcashd_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<zcashd_cmd::CommandArgs>(matches, out)
+    }
+}
+
+/// A zcashd wallet and the passes it is to be migrated with.
+pub struct ZcashdSource {
+    wallet: ZcashdWallet,
+    salvage_report: Option<SalvageReport>,
+    options: MigrationOptions,
+}
+
+impl Debug for ZcashdSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.wallet, f)
+    }
+}
+
+impl SourceWallet for ZcashdSource {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        let (zewif, mut report) = zcashd_cmd::migrate_with_options(&self.wallet, &self.options)?;
+        if let Some(salvage_report) = &self.salvage_report {
+            salvage_report.write_report(&mut report)?;
+        }
+        Ok((zewif, report))
+    }
+}
+
+pub struct ZingoFrontend;
+
+/// The earliest zingo wallet version, which follows the last Zecwallet Lite
+/// version.
+const MIN_ZINGO_VERSION: u64 = 26;
+
+/// A bound on zingo wallet versions, well above any released, to avoid
+/// parsing other files that happen to begin with a small number.
+const MAX_ZINGO_VERSION: u64 = 255;
+
+impl WalletFrontend for ZingoFrontend {
+    fn name(&self) -> &str {
+        "zingo"
+    }
+
+    fn description(&self) -> &str {
+        "zingo wallet file (parsed only; not yet migrated)"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        if !leading_version(path)?
+            .is_some_and(|version| (MIN_ZINGO_VERSION..=MAX_ZINGO_VERSION).contains(&version))
+        {
+            return Ok(false);
+        }
+        let file_data = read_data(path)?;
+        Ok(ZingoParser::new(&file_data).parse().is_ok())
+    }
+
+    fn parse(&self, path: &Path, _options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let file_data = read_data(path)?;
+        let wallet = ZingoParser::new(&file_data).parse().context("Parsing zingo wallet")?;
+        Ok(Box::new(wallet))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<zingo_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<zingo_cmd::CommandArgs>(matches, out)
+    }
+}
+
+impl SourceWallet for ZingoWallet {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        bail!("Migrating zingo wallets to ZeWIF is not yet supported; use `zmigrate zingo` to dump the parsed wallet")
+    }
+}
+
+pub struct ExportwalletFrontend;
+
+impl WalletFrontend for ExportwalletFrontend {
+    fn name(&self) -> &str {
+        "exportwallet"
+    }
+
+    fn description(&self) -> &str {
+        "zcashd z_exportwallet or dumpwallet text file"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        let header = read_header(path, 64)?;
+        Ok(header.starts_with(b"# Wallet dump created by "))
+    }
+
+    fn parse(&self, path: &Path, _options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let wallet = ExportedWallet::parse(&text).context("Parsing wallet export")?;
+        Ok(Box::new(wallet))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<exportwallet_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<exportwallet_cmd::CommandArgs>(matches, out)
+    }
+}
+
+impl SourceWallet for ExportedWallet {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        let zewif_wallet = self.to_zewif()?;
+
+        let mut report = String::new();
+        writeln!(report, "Migration Quality Report")?;
+        let zewif_address_count = zewif_wallet
+            .wallets()
+            .values()
+            .flat_map(|w| w.accounts().values())
+            .flat_map(|a| a.addresses())
+            .count();
+        writeln!(
+            report,
+            "- Addresses: {}/{} preserved",
+            zewif_address_count,
+            self.keys().len()
+        )?;
+        let seeds: Vec<_> = [
+            self.recovery_phrase().map(|_| "recovery phrase"),
+            self.hd_seed().map(|_| "legacy HD seed"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if seeds.is_empty() {
+            writeln!(report, "- Seeds: none in export")?;
+        } else {
+            writeln!(report, "- Seeds: {}", seeds.join(", "))?;
+        }
+        Ok((zewif_wallet, report))
+    }
+}
+
+pub struct ZecwalletFrontend;
+
+/// A decrypted Zecwallet Lite wallet and the network it is used on, which
+/// the wallet file does not record.
+#[derive(Debug)]
+pub struct ZecwalletSource {
+    wallet: ZecwalletWallet,
+    network: Network,
+}
+
+impl WalletFrontend for ZecwalletFrontend {
+    fn name(&self) -> &str {
+        "zecwallet"
+    }
+
+    fn description(&self) -> &str {
+        "Zecwallet Lite zecwallet-light-wallet.dat"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        if !leading_version(path)?.is_some_and(zecwallet::supports_version) {
+            return Ok(false);
+        }
+        Ok(ZecwalletWallet::parse(&MappedFile::open(path)?).is_ok())
+    }
+
+    fn parse(&self, path: &Path, options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let mut wallet = ZecwalletWallet::parse(&MappedFile::open(path)?).context("Parsing Zecwallet wallet")?;
+        if wallet.is_encrypted() {
+            let password = match &options.password_file {
+                Some(path) => Zeroizing::new(
+                    std::fs::read_to_string(path)
+                        .with_context(|| format!("Reading {}", path.display()))?
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string(),
+                ),
+                None => Zeroizing::new(
+                    rpassword::prompt_password("Wallet password: ").context("Reading password")?,
+                ),
+            };
+            wallet.decrypt(&password)?;
+        }
+        let network = options
+            .network
+            .or_else(|| wallet.network_hint())
+            .context("The wallet has no transparent addresses to infer its network from; pass --network")?;
+        Ok(Box::new(ZecwalletSource { wallet, network }))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<zecwallet_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<zecwallet_cmd::CommandArgs>(matches, out)
+    }
+}
+
+impl SourceWallet for ZecwalletSource {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        let wallet = &self.wallet;
+        let zewif_wallet = wallet.to_zewif(self.network)?;
+
+        let mut report = String::new();
+        writeln!(report, "Migration Quality Report")?;
+        writeln!(report, "- Wallet version: {}", wallet.version())?;
+        writeln!(
+            report,
+            "- Keys: {} Sapling, {} transparent",
+            wallet.zkeys().len(),
+            wallet.tkeys().len()
+        )?;
+        match wallet.check_derivation(self.network)? {
+            Some(check) => check.write_report(&mut report)?,
+            None => writeln!(report, "- HD Path Verification: no seed found")?,
+        }
+        writeln!(
+            report,
+            "- Not migrated: {} bytes of block and transaction data, which a light wallet rebuilds by rescanning",
+            wallet.unparsed_len()
+        )?;
+        Ok((zewif_wallet, report))
+    }
+}
+
+pub struct SqliteFrontend;
+
+impl WalletFrontend for SqliteFrontend {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    fn description(&self) -> &str {
+        "zcash_client_sqlite wallet database, as used by light wallets"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        Ok(is_sqlite(path)? && SqliteWallet::open(path).is_ok())
+    }
+
+    fn parse(&self, path: &Path, _options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let wallet = SqliteWallet::open(path).context("Reading wallet database")?;
+        Ok(Box::new(wallet))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<sqlite_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<sqlite_cmd::CommandArgs>(matches, out)
+    }
+}
+
+impl SourceWallet for SqliteWallet {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        let zewif_wallet = self.to_zewif()?;
+
+        let mut report = String::new();
+        writeln!(report, "Migration Quality Report")?;
+        self.write_report(&mut report)?;
+        writeln!(
+            report,
+            "- Transactions: {}/{} preserved",
+            zewif_wallet.transactions().len(),
+            self.transactions().len()
+        )?;
+        Ok((zewif_wallet, report))
+    }
+}
+
+pub struct YwalletFrontend;
+
+impl WalletFrontend for YwalletFrontend {
+    fn name(&self) -> &str {
+        "ywallet"
+    }
+
+    fn description(&self) -> &str {
+        "YWallet database"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        Ok(is_sqlite(path)? && YwalletDatabase::open(path).is_ok())
+    }
+
+    fn parse(&self, path: &Path, _options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        let wallet = YwalletDatabase::open(path).context("Reading YWallet database")?;
+        Ok(Box::new(wallet))
+    }
+
+    fn command(&self) -> Option<Command> {
+        Some(frontend_command::<ywallet_cmd::CommandArgs>(self.name()))
+    }
+
+    fn exec_command(&self, matches: &ArgMatches, out: &mut dyn io::Write) -> Result<()> {
+        exec_frontend_command::<ywallet_cmd::CommandArgs>(matches, out)
+    }
+}
+
+impl SourceWallet for YwalletDatabase {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        let zewif_wallet = self.to_zewif()?;
+
+        let mut report = String::new();
+        writeln!(report, "Migration Quality Report")?;
+        self.write_report(&mut report)?;
+        writeln!(
+            report,
+            "- Transactions: {}/{} preserved",
+            zewif_wallet.transactions().len(),
+            self.transactions().len()
+        )?;
+        Ok((zewif_wallet, report))
+    }
+}
+
+pub struct KeydumpBackend;
+
+impl WalletBackend for KeydumpBackend {
+    fn name(&self) -> &str {
+        "keydump"
+    }
+
+    fn description(&self) -> &str {
+        "z_exportwallet-compatible key dump that zcashd can import with z_importwallet"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { seeds: true, spending_keys: true, ..Default::default() }
+    }
+
+    fn write(&self, zewif: &ZewifTop, output: Option<&Path>, options: &BackendOptions) -> Result<String> {
+        let dump = write_keydump(zewif, options.include_seed)?;
+        match output {
+            Some(output) => {
+                write_private_file(output, &dump)?;
+                Ok(format!("Wrote key dump to {}", output.display()))
+            }
+            None => Ok(dump),
+        }
+    }
+}
+
+pub struct SqliteBackend;
+
+impl WalletBackend for SqliteBackend {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    fn description(&self) -> &str {
+        "new zcash_client_sqlite wallet database, as used by light wallets"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { viewing_keys: true, transactions: true, ..Default::default() }
+    }
+
+    fn write(&self, zewif: &ZewifTop, output: Option<&Path>, options: &BackendOptions) -> Result<String> {
+        let output = output.context("A wallet database can't be written to standard output; pass --output")?;
+        let tree_state = match &options.tree_state {
+            Some(path) => Some(parse_tree_state(
+                &std::fs::read_to_string(path)
+                    .with_context(|| format!("Reading {}", path.display()))?,
+            )?),
+            None => None,
+        };
+        let report = write_sqlite_wallet(zewif, output, tree_state.as_ref())?;
+        let mut summary = format!("Wrote wallet database to {}\n", output.display());
+        report.write_report(&mut summary)?;
+        Ok(summary)
+    }
+}
+
+/// Write a file holding private keys so that only its owner can read it.
+fn write_private_file(path: &Path, contents: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).with_context(|| format!("Creating {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("Writing {}", path.display()))
+}
@@ -0,0 +1,89 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use zeroize::Zeroizing;
+use zewif::Network;
+
+use crate::{
+    exec::end_section,
+    json_output::{JsonObjectWriter, OutputFormat, zewif_diagnostic, zewif_to_json},
+    seed_wallet::{SeedWalletOptions, build_seed_wallet},
+};
+
+/// Build a fresh wallet from a BIP-39 recovery phrase
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    /// The network the wallet is used on: main, test or regtest.
+    #[arg(long, value_parser = parse_network)]
+    network: Network,
+
+    /// The block height from which the restored wallet should scan.
+    #[arg(long)]
+    birthday: u32,
+
+    /// The number of ZIP-32 accounts to derive.
+    #[arg(long, default_value_t = 1)]
+    accounts: u32,
+
+    /// The number of external transparent addresses to derive per account.
+    #[arg(long, default_value_t = 0)]
+    transparent_gap_limit: u32,
+
+    /// Read the recovery phrase from this file instead of prompting for it.
+    /// The phrase is never accepted as an argument, where it would be visible
+    /// to other processes and saved in shell history.
+    #[arg(long)]
+    phrase_file: Option<PathBuf>,
+
+    /// How to render the new wallet.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+pub fn parse_network(network: &str) -> Result<Network> {
+    match network {
+        "main" | "mainnet" => Ok(Network::Main),
+        "test" | "testnet" => Ok(Network::Test),
+        "regtest" => Ok(Network::Regtest),
+        _ => bail!("Unknown network {}; expected main, test or regtest", network),
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        let phrase = match &self.phrase_file {
+            Some(path) => Zeroizing::new(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Reading {}", path.display()))?,
+            ),
+            None => Zeroizing::new(
+                rpassword::prompt_password("Recovery phrase: ")
+                    .context("Reading recovery phrase")?,
+            ),
+        };
+        let options = SeedWalletOptions {
+            network: self.network,
+            birthday: self.birthday,
+            accounts: self.accounts,
+            transparent_gap_limit: self.transparent_gap_limit,
+        };
+        let zewif_wallet = build_seed_wallet(&phrase, &options)?;
+
+        if self.format == OutputFormat::Json {
+            let mut object = JsonObjectWriter::new(out)?;
+            object.field("migrated_wallet", &zewif_to_json(&zewif_wallet))?;
+            object.field("success", &true.into())?;
+            return object.finish();
+        }
+        if self.format == OutputFormat::CborDiag {
+            writeln!(out, "Migrated wallet:\n{}", zewif_diagnostic(&zewif_wallet))?;
+        } else {
+            writeln!(out, "Migrated wallet:\n{:#?}", zewif_wallet)?;
+        }
+        end_section(out)?;
+        writeln!(out, "✅ Success")?;
+        Ok(())
+    }
+}
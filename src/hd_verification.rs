@@ -0,0 +1,313 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result, bail};
+use bip0039::{English, Mnemonic};
+use bip32::{DerivationPath, XPrv};
+use orchard::keys::{FullViewingKey as OrchardFullViewingKey, SpendingKey as OrchardSpendingKey};
+use owo_colors::{OwoColorize, Stream};
+use sapling::zip32::ExtendedSpendingKey;
+use zewif::Network;
+use zewif_zcashd::ZcashdWallet;
+use zip32::{AccountId, ChildIndex};
+
+use crate::{address_encoding::decode_sapling, viewing_keys::UnifiedAccountFvks};
+
+/// The kind of key checked against the wallet's seeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckedKey {
+    Transparent,
+    Sapling,
+    UnifiedSapling,
+    UnifiedOrchard,
+}
+
+impl std::fmt::Display for CheckedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckedKey::Transparent => write!(f, "transparent key"),
+            CheckedKey::Sapling => write!(f, "Sapling key"),
+            CheckedKey::UnifiedSapling => write!(f, "unified account Sapling key"),
+            CheckedKey::UnifiedOrchard => write!(f, "unified account Orchard key"),
+        }
+    }
+}
+
+/// The outcome of re-deriving one stored key from the wallet's seeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationStatus {
+    /// A seed reproduces the stored key at its recorded path.
+    Reproduced { seed: &'static str },
+    /// The key has an HD path, but no seed reproduces it there.
+    Mismatch,
+    /// The key has no HD path (it was imported or randomly generated).
+    NotHd,
+}
+
+/// A stored key and whether the wallet's seeds can reproduce it.
+#[derive(Debug, Clone)]
+pub struct KeyDerivationCheck {
+    kind: CheckedKey,
+    key: String,
+    path: Option<String>,
+    status: DerivationStatus,
+}
+
+impl KeyDerivationCheck {
+    pub fn kind(&self) -> CheckedKey {
+        self.kind
+    }
+
+    /// An identifier for the key: its address, or its account.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn status(&self) -> &DerivationStatus {
+        &self.status
+    }
+
+    /// Whether the key's funds are only recoverable from this key itself, so
+    /// it must be swept or exported individually.
+    pub fn needs_individual_export(&self) -> bool {
+        !matches!(self.status, DerivationStatus::Reproduced { .. })
+    }
+}
+
+/// Re-derivation of every stored key from the wallet's mnemonic and legacy HD
+/// seeds, to find keys that a seed-only restore would lose.
+#[derive(Debug, Default)]
+pub struct HdVerification {
+    seeds: Vec<&'static str>,
+    checks: Vec<KeyDerivationCheck>,
+}
+
+impl HdVerification {
+    pub fn run(wallet: &ZcashdWallet) -> Result<Self> {
+        let mut seeds: Vec<(&'static str, Vec<u8>)> = Vec::new();
+        if let Some(mnemonic) = wallet.bip39_mnemonic() {
+            let phrase = mnemonic.mnemonic();
+            if !phrase.is_empty() {
+                let mnemonic = Mnemonic::<English>::from_phrase(phrase)
+                    .context("Parsing the wallet's BIP-39 mnemonic")?;
+                seeds.push(("mnemonic seed", mnemonic.to_seed("").to_vec()));
+            }
+        }
+        if let Some(legacy_seed) = wallet.legacy_hd_seed() {
+            seeds.push(("legacy HD seed", legacy_seed.as_ref().to_vec()));
+        }
+
+        let coin_type = coin_type(wallet.network());
+        let mut checks = Vec::new();
+
+        for keypair in wallet.keys().keypairs() {
+            let path = keypair.metadata().hd_keypath().map(str::to_string);
+            let pubkey = keypair.pubkey().as_ref().to_vec();
+            let status = match &path {
+                None => DerivationStatus::NotHd,
+                Some(path) => {
+                    let path: DerivationPath = path
+                        .parse()
+                        .with_context(|| format!("Parsing transparent key path {}", path))?;
+                    reproduce(&seeds, |seed| {
+                        let xprv = XPrv::derive_from_path(seed, &path)?;
+                        Ok(xprv.public_key().to_bytes().as_slice() == pubkey.as_slice())
+                    })?
+                }
+            };
+            checks.push(KeyDerivationCheck {
+                kind: CheckedKey::Transparent,
+                key: hex::encode(&pubkey),
+                path,
+                status,
+            });
+        }
+
+        // zcashd records no path in the key metadata of Sapling addresses
+        // derived from a unified account, so those are checked against the
+        // account's key instead.
+        let unified_accounts = UnifiedAccountFvks::from_zcashd_wallet(wallet)?;
+        for (address, ivk) in wallet.sapling_z_addresses() {
+            let Some(key) = wallet.sapling_keys().get(ivk) else {
+                continue;
+            };
+            let encoded = address.to_string(wallet.network());
+            let mut path = key.metadata().hd_keypath().map(str::to_string);
+            let mut account_key = None;
+            if path.is_none() {
+                let payment_address = decode_sapling(&encoded)?;
+                let owner = unified_accounts.iter().find(|fvks| fvks.owns_sapling(&payment_address));
+                if let Some((account_id, dfvk)) =
+                    owner.and_then(|fvks| Some((fvks.account_id()?, fvks.sapling()?)))
+                {
+                    path = Some(format!("m/32'/{}'/{}'", coin_type, account_id));
+                    account_key = Some(dfvk.to_bytes());
+                }
+            }
+            let status = match (&path, account_key) {
+                (None, _) => DerivationStatus::NotHd,
+                (Some(path), Some(stored)) => {
+                    let indices = parse_zip32_path(path)?;
+                    reproduce(&seeds, |seed| {
+                        let master = ExtendedSpendingKey::master(seed);
+                        let extsk = ExtendedSpendingKey::from_path(&master, &indices);
+                        Ok(extsk.to_diversifiable_full_viewing_key().to_bytes() == stored)
+                    })?
+                }
+                (Some(path), None) => {
+                    let indices = parse_zip32_path(path)?;
+                    let stored_ivk = ivk.as_ref().to_vec();
+                    reproduce(&seeds, |seed| {
+                        let master = ExtendedSpendingKey::master(seed);
+                        let extsk = ExtendedSpendingKey::from_path(&master, &indices);
+                        let derived = extsk.to_diversifiable_full_viewing_key().fvk().vk.ivk();
+                        Ok(derived.to_repr().as_slice() == stored_ivk.as_slice())
+                    })?
+                }
+            };
+            checks.push(KeyDerivationCheck {
+                kind: CheckedKey::Sapling,
+                key: encoded,
+                path,
+                status,
+            });
+        }
+
+        for fvks in &unified_accounts {
+            let Some(account_id) = fvks.account_id() else {
+                continue;
+            };
+            let account = AccountId::try_from(account_id)
+                .map_err(|_| anyhow::anyhow!("Invalid ZIP-32 account index {}", account_id))?;
+            let key = format!("account #{} ({})", account_id, fvks.ufvk_id());
+
+            if let Some(stored) = fvks.sapling() {
+                let path = format!("m/32'/{}'/{}'", coin_type, account_id);
+                let indices = parse_zip32_path(&path)?;
+                let status = reproduce(&seeds, |seed| {
+                    let master = ExtendedSpendingKey::master(seed);
+                    let extsk = ExtendedSpendingKey::from_path(&master, &indices);
+                    Ok(extsk.to_diversifiable_full_viewing_key().to_bytes() == stored.to_bytes())
+                })?;
+                checks.push(KeyDerivationCheck {
+                    kind: CheckedKey::UnifiedSapling,
+                    key: key.clone(),
+                    path: Some(path),
+                    status,
+                });
+            }
+
+            if let Some(stored) = fvks.orchard() {
+                let path = format!("m/32'/{}'/{}'", coin_type, account_id);
+                let status = reproduce(&seeds, |seed| {
+                    let Ok(sk) = OrchardSpendingKey::from_zip32_seed(seed, coin_type, account) else {
+                        return Ok(false);
+                    };
+                    Ok(OrchardFullViewingKey::from(&sk).to_bytes() == stored.to_bytes())
+                })?;
+                checks.push(KeyDerivationCheck {
+                    kind: CheckedKey::UnifiedOrchard,
+                    key,
+                    path: Some(path),
+                    status,
+                });
+            }
+        }
+
+        Ok(Self {
+            seeds: seeds.iter().map(|(name, _)| *name).collect(),
+            checks,
+        })
+    }
+
+    pub fn checks(&self) -> &[KeyDerivationCheck] {
+        &self.checks
+    }
+
+    /// The keys that cannot be reproduced from any of the wallet's seeds.
+    pub fn unreproducible(&self) -> impl Iterator<Item = &KeyDerivationCheck> {
+        self.checks.iter().filter(|c| c.needs_individual_export())
+    }
+
+    /// Append the verification results to a migration report. Keys that
+    /// cannot be reproduced from a seed are highlighted in red.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- HD Path Verification:")?;
+        if self.seeds.is_empty() {
+            let warning = "No seed found: no key can be restored from a seed";
+            writeln!(
+                report,
+                "  * {}",
+                warning.if_supports_color(Stream::Stdout, |text| text.red().bold())
+            )?;
+        } else {
+            writeln!(report, "  * Seeds checked: {}", self.seeds.join(", "))?;
+        }
+        let reproduced = self.checks.len() - self.unreproducible().count();
+        writeln!(report, "  * Keys reproduced from seed: {}/{}", reproduced, self.checks.len())?;
+        for check in &self.checks {
+            let reason = match check.status {
+                DerivationStatus::Reproduced { .. } => continue,
+                DerivationStatus::Mismatch => format!(
+                    "does not match derivation at {}",
+                    check.path.as_deref().unwrap_or_default()
+                ),
+                DerivationStatus::NotHd => "has no HD path".to_string(),
+            };
+            let line = format!(
+                "🔴 {} {} {}; sweep or export it individually",
+                check.kind, check.key, reason
+            );
+            writeln!(report, "    {}", line.if_supports_color(Stream::Stdout, |text| text.red()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The BIP-44 coin type of `network`.
+pub fn coin_type(network: Network) -> u32 {
+    match network {
+        Network::Main => 133,
+        Network::Test | Network::Regtest => 1,
+    }
+}
+
+/// Parse a ZIP-32 path such as `m/32'/133'/0'`. Sapling derivation only
+/// permits hardened children.
+fn parse_zip32_path(path: &str) -> Result<Vec<ChildIndex>> {
+    let mut components = path.split('/');
+    if components.next() != Some("m") {
+        bail!("ZIP-32 path must start with m: {}", path);
+    }
+    components
+        .map(|component| {
+            let Some(index) = component.strip_suffix('\'').or_else(|| component.strip_suffix('h'))
+            else {
+                bail!("Non-hardened ZIP-32 path component {} in {}", component, path);
+            };
+            let index: u32 = index
+                .parse()
+                .with_context(|| format!("Invalid ZIP-32 path component {} in {}", component, path))?;
+            if index >= 1 << 31 {
+                bail!("ZIP-32 path component out of range in {}", path);
+            }
+            Ok(ChildIndex::hardened(index))
+        })
+        .collect()
+}
+
+/// Try `matches` with each seed, reporting the first that reproduces the key.
+fn reproduce(
+    seeds: &[(&'static str, Vec<u8>)],
+    matches: impl Fn(&[u8]) -> Result<bool>,
+) -> Result<DerivationStatus> {
+    for (name, seed) in seeds {
+        if matches(seed)? {
+            return Ok(DerivationStatus::Reproduced { seed: name });
+        }
+    }
+    Ok(DerivationStatus::Mismatch)
+}
@@ -0,0 +1,475 @@
+use std::{fmt::Debug, io::Write};
+
+use anyhow::{Result, bail};
+use bc_envelope::prelude::*;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use zewif::ZewifTop;
+
+/// How a command renders the wallets it reads and migrates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Rust debug text, in sections separated by `---`.
+    #[default]
+    Text,
+    /// A single JSON object, as described in `docs/json-output.md`.
+    Json,
+    /// Debug text, with the migrated wallet written as its ZeWIF envelope in
+    /// CBOR diagnostic notation.
+    CborDiag,
+}
+
+/// A migrated wallet as JSON, converted from its ZeWIF envelope, as
+/// described in `docs/json-output.md`.
+pub fn zewif_to_json(zewif: &ZewifTop) -> Value {
+    envelope_to_json(&Envelope::from(zewif.clone()))
+}
+
+/// A migrated wallet's ZeWIF envelope in CBOR diagnostic notation.
+pub fn zewif_diagnostic(zewif: &ZewifTop) -> String {
+    Envelope::from(zewif.clone()).diagnostic()
+}
+
+/// An envelope as JSON. A node is an object holding its `subject` and, under
+/// each predicate, the array of that predicate's objects.
+fn envelope_to_json(envelope: &Envelope) -> Value {
+    match envelope.case() {
+        EnvelopeCase::Node { subject, assertions, .. } => {
+            let mut object = Map::new();
+            object.insert("subject".to_string(), envelope_to_json(subject));
+            for assertion in assertions {
+                let (predicate, value) = match assertion.case() {
+                    EnvelopeCase::Assertion(assertion) => {
+                        (predicate_key(&assertion.predicate()), envelope_to_json(&assertion.object()))
+                    }
+                    // An elided or encrypted assertion has no predicate.
+                    _ => ("".to_string(), envelope_to_json(assertion)),
+                };
+                match object.entry(predicate).or_insert_with(|| Value::Array(Vec::new())) {
+                    Value::Array(values) => values.push(value),
+                    // A predicate named `subject` joins the subject.
+                    other => *other = Value::Array(vec![other.take(), value]),
+                }
+            }
+            Value::Object(object)
+        }
+        EnvelopeCase::Leaf { cbor, .. } => cbor_to_json(cbor),
+        EnvelopeCase::Wrapped { envelope, .. } => tagged("wrapped".to_string(), envelope_to_json(envelope)),
+        EnvelopeCase::Assertion(assertion) => {
+            let mut object = Map::new();
+            object.insert(predicate_key(&assertion.predicate()), envelope_to_json(&assertion.object()));
+            Value::Object(object)
+        }
+        EnvelopeCase::KnownValue { value, .. } => Value::String(value.name()),
+        // Elided, encrypted and compressed parts are known only by digest.
+        _ => tagged("elided".to_string(), Value::String(hex::encode(envelope.digest().data()))),
+    }
+}
+
+fn predicate_key(predicate: &Envelope) -> String {
+    match envelope_to_json(predicate) {
+        Value::String(key) => key,
+        other => other.to_string(),
+    }
+}
+
+/// A CBOR value as JSON. Byte strings are written as hex, and tagged values
+/// as `{ "tag", "value" }` objects.
+fn cbor_to_json(cbor: &CBOR) -> Value {
+    match cbor.as_case() {
+        CBORCase::Unsigned(n) => Value::from(*n),
+        // CBOR writes -1 - n for a negative n.
+        CBORCase::Negative(n) => match i64::try_from(*n) {
+            Ok(n) => Value::from(-1 - n),
+            Err(_) => Value::String(format!("-{}", *n as u128 + 1)),
+        },
+        CBORCase::ByteString(bytes) => Value::String(hex::encode(bytes.data())),
+        CBORCase::Text(text) => Value::String(text.clone()),
+        CBORCase::Array(items) => Value::Array(items.iter().map(cbor_to_json).collect()),
+        CBORCase::Map(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key_string(cbor_to_json(key)), cbor_to_json(value)))
+                .collect(),
+        ),
+        CBORCase::Tagged(tag, item) => {
+            let mut object = Map::new();
+            object.insert("tag".to_string(), Value::from(tag.value()));
+            object.insert("value".to_string(), cbor_to_json(item));
+            Value::Object(object)
+        }
+        CBORCase::Simple(Simple::True) => Value::Bool(true),
+        CBORCase::Simple(Simple::False) => Value::Bool(false),
+        CBORCase::Simple(Simple::Null) => Value::Null,
+        CBORCase::Simple(Simple::Float(f)) => {
+            serde_json::Number::from_f64(*f).map_or_else(|| Value::String(f.to_string()), Value::Number)
+        }
+    }
+}
+
+/// Convert a value to JSON from its pretty-printed `Debug` form, following
+/// the rules in `docs/json-output.md`.
+///
+/// The source wallet types of the `zewif` crates have no envelope form and
+/// describe themselves only through `Debug`, whose pretty form is regular
+/// enough to read back: binary values are already written as hex and
+/// addresses in their encoded form. Map keys come out sorted, so the same
+/// value always gives the same JSON. Migrated wallets are converted from
+/// their envelope instead, with [`zewif_to_json`].
+pub fn debug_to_json(value: &dyn Debug) -> Result<Value> {
+    let text = format!("{:#?}", value);
+    let mut parser = DebugParser { text: &text, pos: 0 };
+    let json = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != text.len() {
+        bail!("Unexpected text after the value at {}", parser.location());
+    }
+    Ok(json)
+}
+
+/// A report as JSON: its lines, without the terminal colors they are
+/// highlighted with.
+pub fn report_to_json(report: &str) -> Value {
+    Value::Array(
+        report
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Value::String(strip_ansi(line)))
+            .collect(),
+    )
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence up to its final letter.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Writes a JSON object one field at a time, flushing each as it is
+/// written, so that a large dump is streamed rather than built in memory.
+pub struct JsonObjectWriter<'a, W: Write> {
+    out: &'a mut W,
+    empty: bool,
+}
+
+impl<'a, W: Write> JsonObjectWriter<'a, W> {
+    pub fn new(out: &'a mut W) -> Result<Self> {
+        write!(out, "{{")?;
+        Ok(Self { out, empty: true })
+    }
+
+    pub fn field(&mut self, key: &str, value: &Value) -> Result<()> {
+        if !self.empty {
+            write!(self.out, ",")?;
+        }
+        self.empty = false;
+        write!(self.out, "\n{}: ", Value::String(key.to_string()))?;
+        serde_json::to_writer_pretty(&mut *self.out, value)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        writeln!(self.out, "\n}}")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// A reader of `{:#?}` output.
+struct DebugParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl DebugParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn location(&self) -> String {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        format!("line {} of the debug output", line)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.text.len() - trimmed.len();
+    }
+
+    /// Skip spaces, but not line breaks.
+    fn skip_spaces(&mut self) {
+        let trimmed = self.rest().trim_start_matches(' ');
+        self.pos = self.text.len() - trimmed.len();
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            bail!("Expected `{}` at {}", expected, self.location());
+        }
+        self.pos += expected.len_utf8();
+        Ok(())
+    }
+
+    /// Consume `closer` if it is next, after any whitespace.
+    fn close(&mut self, closer: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(closer) {
+            self.pos += closer.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// After an item, consume its separating comma, or check that the
+    /// group's closer follows.
+    fn separator(&mut self, closer: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(',') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) if c == closer => Ok(()),
+            _ => bail!("Expected `,` or `{}` at {}", closer, self.location()),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.quoted('"')?)),
+            Some('\'') => Ok(Value::String(self.quoted('\'')?)),
+            Some('[') => {
+                self.pos += 1;
+                Ok(Value::Array(self.items(']')?))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let items = self.items(')')?;
+                Ok(if items.is_empty() { Value::Null } else { Value::Array(items) })
+            }
+            Some('{') => self.braces(false),
+            Some(_) => self.named(),
+            None => bail!("Unexpected end of the debug output"),
+        }
+    }
+
+    /// A value that begins with a bare word: a type name followed by its
+    /// fields, or a scalar.
+    fn named(&mut self) -> Result<Value> {
+        let word = self.word()?;
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let mut items = self.items(')')?;
+            return Ok(match (word.as_str(), items.len()) {
+                ("Some" | "Ok", 1) => items.remove(0),
+                (_, 1) if is_byte_type(&word) => hex_string(items.remove(0)),
+                (_, 1) if !items[0].is_object() && !items[0].is_array() => items.remove(0),
+                (_, 1) => tagged(word, items.remove(0)),
+                (_, 0) => Value::String(word),
+                _ => tagged(word, Value::Array(items)),
+            });
+        }
+        self.skip_spaces();
+        if self.peek() == Some('{') {
+            return self.braces(true);
+        }
+
+        // Custom `Debug` output may run several words together, as in
+        // `1.5 ZEC`; they are kept as one string.
+        let mut words = vec![word];
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(c) if !is_delimiter(c) && c != '\n' && c != ':' => words.push(self.word()?),
+                _ => break,
+            }
+        }
+        Ok(if words.len() == 1 { scalar(words.remove(0)) } else { Value::String(words.join(" ")) })
+    }
+
+    fn word(&mut self) -> Result<String> {
+        let start = self.pos;
+        let mut chars = self.rest().char_indices().peekable();
+        let mut end = self.rest().len();
+        while let Some((i, c)) = chars.next() {
+            if c == ':' && chars.peek().map(|&(_, c)| c) == Some(':') {
+                // A path separator, as in `zewif::Network`.
+                chars.next();
+                continue;
+            }
+            if c.is_whitespace() || is_delimiter(c) || c == ':' {
+                end = i;
+                break;
+            }
+        }
+        if end == 0 {
+            bail!("Expected a value at {}", self.location());
+        }
+        self.pos = start + end;
+        Ok(self.text[start..start + end].to_string())
+    }
+
+    fn items(&mut self, closer: char) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        while !self.close(closer) {
+            items.push(self.value()?);
+            self.separator(closer)?;
+        }
+        Ok(items)
+    }
+
+    /// A brace group: a struct's fields if `named`, otherwise a map or a
+    /// set, told apart by whether the first item is followed by a colon.
+    fn braces(&mut self, named: bool) -> Result<Value> {
+        self.expect('{')?;
+        let mut object = Map::new();
+        let mut set = Vec::new();
+        while !self.close('}') {
+            self.skip_whitespace();
+            if self.rest().starts_with("..") {
+                // A non-exhaustive struct.
+                self.pos += 2;
+                self.separator('}')?;
+                continue;
+            }
+            let key = if named { Value::String(self.word()?) } else { self.value()? };
+            self.skip_whitespace();
+            if self.peek() == Some(':') {
+                self.pos += 1;
+                let value = self.value()?;
+                object.insert(key_string(key), value);
+            } else if named {
+                bail!("Expected `:` after a field name at {}", self.location());
+            } else {
+                set.push(key);
+            }
+            self.separator('}')?;
+        }
+        if !set.is_empty() {
+            if !object.is_empty() {
+                bail!("Mixed map and set entries at {}", self.location());
+            }
+            set.sort_by_cached_key(|item| item.to_string());
+            return Ok(Value::Array(set));
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        self.pos += 1;
+        let mut string = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('u') => {
+                            let hex: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .skip_while(|&c| c == '{')
+                                .take_while(|&c| c != '}')
+                                .collect();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        Some(c) => c,
+                        None => break,
+                    };
+                    string.push(escaped);
+                }
+                c => string.push(c),
+            }
+        }
+        bail!("Unterminated string at {}", self.location())
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    matches!(c, ',' | '(' | ')' | '[' | ']' | '{' | '}' | '"')
+}
+
+fn tagged(tag: String, value: Value) -> Value {
+    let mut object = Map::new();
+    object.insert(tag, value);
+    Value::Object(object)
+}
+
+fn key_string(key: Value) -> String {
+    match key {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// The `zewif` types that write their bytes as hex.
+const BYTE_TYPES: &[&str] = &["Data", "TxId", "BlockHash", "u160", "u252", "u256"];
+
+fn is_byte_type(name: &str) -> bool {
+    BYTE_TYPES.contains(&name) || name.starts_with("Blob<")
+}
+
+/// The hex of a byte type as a string, even when every digit is decimal and
+/// it was read as a number. Numbers are only read from digit strings without
+/// a leading zero, so the number writes back as the same digits.
+fn hex_string(value: Value) -> Value {
+    match value {
+        Value::Number(n) => Value::String(n.to_string()),
+        other => other,
+    }
+}
+
+/// A bare word as JSON. Decimal integers that fit in an `i64` or a `u64`
+/// and have no leading zero are numbers; other digit strings are almost
+/// always binary values written as hex, and are kept as strings like any
+/// other word.
+fn scalar(word: String) -> Value {
+    match word.as_str() {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "None" => return Value::Null,
+        _ => {}
+    }
+    let digits = word.strip_prefix('-').unwrap_or(&word);
+    let is_integer = !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'));
+    if is_integer {
+        if let Ok(n) = word.parse::<i64>() {
+            return Value::from(n);
+        }
+        if let Ok(n) = word.parse::<u64>() {
+            return Value::from(n);
+        }
+    }
+    Value::String(word)
+}
@@ -0,0 +1,268 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result, anyhow, bail};
+use bc_envelope::prelude::*;
+use bech32::{Bech32, Hrp};
+use sapling::zip32::ExtendedSpendingKey;
+use zewif::{
+    Address, Network, ProtocolAddress, SeedMaterial, SpendingKey, TransparentSpendAuthority,
+    ZewifTop,
+};
+
+use crate::deterministic::{creation_time, format_utc};
+
+/// The creation time written for a key whose wallet recorded none. zcashd's
+/// `z_importwallet` uses the earliest key time as the rescan start, so this
+/// forces a rescan from genesis rather than risk missing funds.
+const KEY_TIME: &str = "1970-01-01T00:00:01Z";
+
+/// Write the keys of a ZeWIF wallet as a `z_exportwallet`-compatible text
+/// dump, which zcashd can read back with `z_importwallet`.
+///
+/// Seed material is written only if `include_seed` is set; otherwise its
+/// lines are present but redacted. zcashd does not import seeds from a dump,
+/// so they are included for the user's own recovery records.
+///
+/// The dump is dated by [`creation_time`], which honors `SOURCE_DATE_EPOCH`.
+pub fn write_keydump(zewif: &ZewifTop, include_seed: bool) -> Result<String> {
+    write_keydump_at(zewif, include_seed, creation_time()?)
+}
+
+/// Write a key dump as [`write_keydump`] does, dated `created` seconds since
+/// the Unix epoch.
+pub fn write_keydump_at(zewif: &ZewifTop, include_seed: bool, created: u64) -> Result<String> {
+    let mut dump = String::new();
+    writeln!(dump, "# Wallet dump created by zmigrate {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(dump, "# * Created on {}", format_utc(created))?;
+    writeln!(dump)?;
+
+    // Wallets, accounts and addresses are held in hash maps, so each wallet's
+    // keys, and the wallets themselves, are sorted to give the same dump
+    // for the same wallet.
+    let mut wallets = Vec::new();
+    for wallet in zewif.wallets().values() {
+        let mut section = String::new();
+        let network = wallet.network();
+        if let Some(seed_material) = wallet.seed_material() {
+            write_seed(&mut section, seed_material, include_seed)?;
+        }
+
+        let mut transparent = Vec::new();
+        let mut sprout = Vec::new();
+        let mut sapling = Vec::new();
+        for account in wallet.accounts().values() {
+            for address in account.addresses().values() {
+                collect_keys(address, network, &mut transparent, &mut sprout, &mut sapling)?;
+            }
+        }
+        transparent.sort();
+        sprout.sort();
+        sapling.sort();
+
+        for (key, time, field, addr) in transparent {
+            writeln!(section, "{} {} {} # addr={}", key, time, field, addr)?;
+        }
+        writeln!(section)?;
+        writeln!(section, "# Zkeys")?;
+        writeln!(section)?;
+        for (key, time, addr) in sprout {
+            writeln!(section, "{} {} # zaddr={}", key, time, addr)?;
+        }
+        writeln!(section)?;
+        writeln!(section, "# Sapling keys")?;
+        writeln!(section)?;
+        for (key, time, addr) in sapling {
+            writeln!(section, "{} {} # zaddr={}", key, time, addr)?;
+        }
+        wallets.push(section);
+    }
+    wallets.sort();
+    dump.extend(wallets);
+
+    writeln!(dump)?;
+    writeln!(dump, "# End of dump")?;
+    Ok(dump)
+}
+
+fn write_seed(dump: &mut String, seed_material: &SeedMaterial, include_seed: bool) -> Result<()> {
+    match seed_material {
+        SeedMaterial::Bip39Mnemonic(mnemonic) => {
+            writeln!(dump, "# Emergency Recovery Information:")?;
+            if include_seed {
+                writeln!(dump, "# - recovery_phrase=\"{}\"", mnemonic.mnemonic())?;
+            } else {
+                writeln!(dump, "# - recovery_phrase=REDACTED (use --include-seed to write it)")?;
+            }
+            writeln!(dump, "# - language=English")?;
+        }
+        SeedMaterial::PreBIP39Seed(seed) => {
+            if include_seed {
+                writeln!(dump, "# HDSeed={}", hex::encode(seed.as_ref()))?;
+            } else {
+                writeln!(dump, "# HDSeed=REDACTED (use --include-seed to write it)")?;
+            }
+        }
+    }
+    writeln!(dump)?;
+    Ok(())
+}
+
+/// Collect the encoded spending keys of an address. Transparent keys are
+/// collected as `(WIF, time, label field, address)`, and Sprout and Sapling
+/// keys as `(spending key, time, address)`.
+fn collect_keys(
+    address: &Address,
+    network: Network,
+    transparent: &mut Vec<(String, String, String, String)>,
+    sprout: &mut Vec<(String, String, String)>,
+    sapling: &mut Vec<(String, String, String)>,
+) -> Result<()> {
+    let time = key_time(address);
+    match address.address() {
+        ProtocolAddress::Transparent(transparent_address) => {
+            if let Some(TransparentSpendAuthority::SpendingKey(key)) =
+                transparent_address.spend_authority()
+            {
+                // zcashd writes the label if there is one, and otherwise
+                // marks the key as change.
+                let field = if address.name().is_empty() {
+                    "change=1".to_string()
+                } else {
+                    format!("label={}", percent_encode(address.name()))
+                };
+                transparent.push((
+                    encode_wif(network, key.as_ref()),
+                    time,
+                    field,
+                    transparent_address.address().to_string(),
+                ));
+            }
+        }
+        ProtocolAddress::Shielded(shielded) => match shielded.spending_key() {
+            Some(SpendingKey::Sapling(key)) => {
+                sapling.push((
+                    encode_sapling_extsk(network, &key.to_bytes())?,
+                    time,
+                    shielded.address().to_string(),
+                ));
+            }
+            Some(SpendingKey::Sprout(key)) => {
+                sprout.push((
+                    encode_sprout_sk(network, key.as_ref()),
+                    time,
+                    shielded.address().to_string(),
+                ));
+            }
+            _ => {}
+        },
+        ProtocolAddress::Unified(unified) => {
+            let Some(component) = unified.sapling_component() else {
+                return Ok(());
+            };
+            if let Some(SpendingKey::Sapling(key)) = component.spending_key() {
+                sapling.push((
+                    encode_sapling_extsk(network, &key.to_bytes())?,
+                    time,
+                    component.address().to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The creation time of an address's key, as recorded by the wallet it was
+/// migrated from, or [`KEY_TIME`] if none was.
+fn key_time(address: &Address) -> String {
+    address
+        .attachments()
+        .envelopes()
+        .iter()
+        .filter_map(|attachment| attachment.attachment_payload().ok())
+        .find_map(|payload| payload.extract_object_for_predicate::<String>("created").ok())
+        .unwrap_or_else(|| KEY_TIME.to_string())
+}
+
+/// Encode a secp256k1 secret key as compressed-key WIF.
+pub(crate) fn encode_wif(network: Network, secret: &[u8]) -> String {
+    let version = match network {
+        Network::Main => 0x80,
+        Network::Test | Network::Regtest => 0xef,
+    };
+    let mut payload = vec![version];
+    payload.extend_from_slice(secret);
+    payload.push(0x01);
+    bs58::encode(payload).with_check().into_string()
+}
+
+/// Decode a WIF-encoded secp256k1 secret key, compressed or not.
+pub(crate) fn decode_wif(encoded: &str) -> Result<[u8; 32]> {
+    let payload = bs58::decode(encoded)
+        .with_check(None)
+        .into_vec()
+        .context("Decoding WIF key")?;
+    let secret = match payload.as_slice() {
+        [0x80 | 0xef, secret @ .., 0x01] if secret.len() == 32 => secret,
+        [0x80 | 0xef, secret @ ..] if secret.len() == 32 => secret,
+        _ => bail!("Not a WIF-encoded secret key"),
+    };
+    Ok(secret.try_into().unwrap())
+}
+
+/// Encode a Sprout spending key's 32-byte `a_sk` as an `SK…`/`ST…` string.
+pub(crate) fn encode_sprout_sk(network: Network, a_sk: &[u8]) -> String {
+    let mut payload = match network {
+        Network::Main => vec![0xab, 0x36],
+        Network::Test | Network::Regtest => vec![0xac, 0x08],
+    };
+    payload.extend_from_slice(a_sk);
+    bs58::encode(payload).with_check().into_string()
+}
+
+/// Decode a Sprout spending key (`SK…`/`ST…`) to its 32-byte `a_sk`.
+pub(crate) fn decode_sprout_sk(encoded: &str) -> Result<[u8; 32]> {
+    let payload = bs58::decode(encoded)
+        .with_check(None)
+        .into_vec()
+        .context("Decoding Sprout spending key")?;
+    let a_sk = match payload.as_slice() {
+        [0xab, 0x36, a_sk @ ..] | [0xac, 0x08, a_sk @ ..] if a_sk.len() == 32 => a_sk,
+        _ => bail!("Not a Sprout spending key"),
+    };
+    Ok(a_sk.try_into().unwrap())
+}
+
+/// Decode a `secret-extended-key` string to a Sapling extended spending key.
+pub(crate) fn decode_sapling_extsk(encoded: &str) -> Result<ExtendedSpendingKey> {
+    let (hrp, bytes) = bech32::decode(encoded).context("Decoding Sapling extended spending key")?;
+    if !hrp.as_str().starts_with("secret-extended-key-") {
+        bail!("Not a Sapling extended spending key: {}", hrp);
+    }
+    ExtendedSpendingKey::from_bytes(&bytes)
+        .map_err(|e| anyhow!("Reading Sapling extended spending key: {:?}", e))
+}
+
+/// Encode a Sapling extended spending key as a `secret-extended-key` string.
+pub(crate) fn encode_sapling_extsk(network: Network, bytes: &[u8]) -> Result<String> {
+    let hrp = match network {
+        Network::Main => "secret-extended-key-main",
+        Network::Test => "secret-extended-key-test",
+        Network::Regtest => "secret-extended-key-regtest",
+    };
+    bech32::encode::<Bech32>(Hrp::parse_unchecked(hrp), bytes)
+        .context("Encoding Sapling extended spending key")
+}
+
+/// Percent-encode a label as zcashd's `EncodeDumpString` does: control
+/// characters, spaces, `%` and non-ASCII bytes become lowercase `%xx`.
+fn percent_encode(label: &str) -> String {
+    let mut encoded = String::new();
+    for byte in label.bytes() {
+        if byte <= b' ' || byte >= 0x80 || byte == b'%' {
+            encoded.push_str(&format!("%{:02x}", byte));
+        } else {
+            encoded.push(byte as char);
+        }
+    }
+    encoded
+}
@@ -1,4 +1,39 @@
 pub mod zcashd_cmd;
 pub mod zingo_cmd;
+pub mod sqlite_cmd;
+pub mod exportwallet_cmd;
+pub mod export_cmd;
+pub mod from_seed_cmd;
+pub mod zecwallet_cmd;
+pub mod ywallet_cmd;
+pub mod list_formats_cmd;
+pub mod migrate_cmd;
 pub mod exec;
+pub mod cli;
 pub mod file_args;
+pub mod address_encoding;
+pub mod annotate;
+pub mod asset_risk;
+pub mod balances;
+pub mod datadir;
+pub mod deterministic;
+pub mod exported_wallet;
+pub mod formats;
+pub mod hd_verification;
+pub mod json_output;
+pub mod keydump;
+pub mod mapped_file;
+pub mod note_decryption;
+pub mod salvage;
+pub mod seed_wallet;
+pub mod sent_outputs;
+pub mod sprout;
+pub mod sqlite_export;
+pub mod sqlite_wallet;
+mod styles;
+pub mod tx_decode;
+pub mod viewing_keys;
+pub mod wallet_access;
+pub mod ywallet;
+pub mod zecwallet;
+pub mod zingo_remainder;
@@ -0,0 +1,38 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::formats::Registry;
+
+/// List the wallet formats that can be migrated from and exported to
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {}
+
+impl CommandArgs {
+    pub fn exec_with(&self, registry: &Registry, out: &mut impl Write) -> Result<()> {
+        writeln!(out, "Source formats (migrate --from, export --from):")?;
+        for frontend in registry.frontends() {
+            writeln!(out, "- {}: {}", frontend.name(), frontend.description())?;
+        }
+        writeln!(out)?;
+        writeln!(out, "Export formats (export <format>):")?;
+        for backend in registry.backends() {
+            writeln!(out, "- {}: {}", backend.name(), backend.description())?;
+            let kept = backend.capabilities().names();
+            if kept.is_empty() {
+                writeln!(out, "  Keeps: nothing")?;
+            } else {
+                writeln!(out, "  Keeps: {}", kept.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        self.exec_with(&Registry::with_builtin(), out)
+    }
+}
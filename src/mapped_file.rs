@@ -0,0 +1,55 @@
+use std::{fs::File, ops::Deref, path::Path};
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+
+/// A wallet file mapped into memory, so that it is read in place rather than
+/// copied into a buffer.
+///
+/// Only parsers that take a byte slice can read the mapping: the Zecwallet
+/// Lite parser and the page scan of `zcashd --salvage`. The others cannot
+/// use it:
+///
+/// - `ZingoParser` reads from a zewif `Data`, which owns its bytes, so a
+///   mapped wallet would be copied into one in full. That costs the same
+///   buffer as reading the file, plus the mapping, so zingo wallets are read
+///   with [`read_data`](crate::file_args::read_data).
+/// - zcashd wallets are parsed from the output of `db_dump`, which reads the
+///   wallet in its own process; zmigrate never loads the file.
+///
+/// `benches/parse_fixtures.rs` measures both loads for every format.
+pub struct MappedFile {
+    // Empty files cannot be mapped, so they have no mapping.
+    mmap: Option<Mmap>,
+}
+
+impl MappedFile {
+    /// Map a file read-only.
+    ///
+    /// The file must not be modified while it is mapped. Wallets are read
+    /// while their wallet app is closed, and `zcashd --snapshot` copies a
+    /// wallet that may still be open before reading it.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+        let len = file.metadata().with_context(|| format!("Reading {}", path.display()))?.len();
+        if len == 0 {
+            return Ok(Self { mmap: None });
+        }
+        // SAFETY: The mapping is read-only, and the file is not modified
+        // while it is mapped, as documented above.
+        let mmap = unsafe { Mmap::map(&file) }.with_context(|| format!("Mapping {}", path.display()))?;
+        Ok(Self { mmap: Some(mmap) })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.mmap.as_deref().unwrap_or_default()
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
@@ -0,0 +1,79 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use zewif::Network;
+
+use crate::{
+    file_args::{FileArgs, FileArgsLike},
+    formats::{FrontendOptions, Registry, dump_source_wallet},
+    from_seed_cmd::parse_network,
+    zcashd_cmd::{MigrationArgs, MigrationOptions},
+    json_output::OutputFormat,
+};
+
+/// Migrate a wallet in any known format to ZeWIF, detecting its format
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// The format of the source wallet file, as given by `list-formats`, or
+    /// `auto` to detect it.
+    #[arg(long, default_value = "auto")]
+    from: String,
+
+    /// The network the wallet is used on, for formats that do not record it.
+    #[arg(long, value_parser = parse_network)]
+    network: Option<Network>,
+
+    /// Read the password of an encrypted wallet from this file instead of
+    /// prompting for it.
+    #[arg(long)]
+    password_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    zcashd: MigrationArgs,
+
+    /// How to render the source and migrated wallets.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Derive the migrated wallet's IDs from its contents rather than at
+    /// random, and write its maps and sets sorted, so that the same wallet
+    /// always gives the same output. Times written honor `SOURCE_DATE_EPOCH`.
+    #[arg(long)]
+    deterministic: bool,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        &self.file_args.file
+    }
+}
+
+impl CommandArgs {
+    fn frontend_options(&self) -> FrontendOptions {
+        FrontendOptions {
+            network: self.network,
+            password_file: self.password_file.clone(),
+            zcashd: MigrationOptions { deterministic: self.deterministic, ..self.zcashd.options() },
+        }
+    }
+
+    pub fn exec_with(&self, registry: &Registry, out: &mut impl Write) -> Result<()> {
+        let frontend = registry.resolve(&self.from, self.file())?;
+        let wallet = frontend
+            .parse(self.file(), &self.frontend_options())
+            .with_context(|| format!("Reading {} as {}", self.file().display(), frontend.name()))?;
+
+        dump_source_wallet(wallet.as_ref(), Some(frontend.name()), self.format, self.deterministic, out)
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        self.exec_with(&Registry::with_builtin(), out)
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use bc_envelope::prelude::*;
+use orchard::note_encryption::OrchardDomain;
+use sapling::note_encryption::{Zip212Enforcement, try_sapling_note_decryption};
+use zcash_note_encryption::try_note_decryption;
+use zcash_protocol::memo::{Memo, MemoBytes};
+use zewif::{TxId, ZewifTop};
+
+use crate::{
+    tx_decode::decode_transaction,
+    viewing_keys::{KeySource, WalletIvks},
+};
+
+/// The vendor used for attachments produced by zmigrate's offline analysis passes.
+pub const ZMIGRATE_VENDOR: &str = "com.blockchaincommons.zmigrate";
+
+/// The shielded pool an output belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Pool {
+    Sapling,
+    Orchard,
+}
+
+impl std::fmt::Display for Pool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pool::Sapling => write!(f, "Sapling"),
+            Pool::Orchard => write!(f, "Orchard"),
+        }
+    }
+}
+
+/// The decrypted note itself, needed to derive its nullifier.
+#[derive(Debug, Clone)]
+pub enum ReceivedNote {
+    Sapling(sapling::Note),
+    Orchard(orchard::Note),
+}
+
+/// A shielded output that one of the wallet's own IVKs could decrypt.
+#[derive(Debug, Clone)]
+pub struct DecryptedNote {
+    txid: TxId,
+    pool: Pool,
+    index: u32,
+    value: u64,
+    memo: MemoBytes,
+    recipient: KeySource,
+    note: ReceivedNote,
+}
+
+impl DecryptedNote {
+    pub fn txid(&self) -> &TxId {
+        &self.txid
+    }
+
+    pub fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    /// The index of the output (Sapling) or action (Orchard) within its bundle.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The note value in zatoshis.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn memo(&self) -> &MemoBytes {
+        &self.memo
+    }
+
+    pub fn recipient(&self) -> &KeySource {
+        &self.recipient
+    }
+
+    pub fn note(&self) -> &ReceivedNote {
+        &self.note
+    }
+
+    /// A human-readable rendering of the memo: the text for text memos, and
+    /// the hex encoding (without trailing zero padding) otherwise.
+    pub fn memo_text(&self) -> String {
+        memo_text(&self.memo)
+    }
+}
+
+/// A human-readable rendering of a memo: the text for text memos, and the hex
+/// encoding (without trailing zero padding) otherwise.
+pub fn memo_text(memo: &MemoBytes) -> String {
+    match Memo::try_from(memo) {
+        Ok(Memo::Empty) => String::new(),
+        Ok(Memo::Text(text)) => text.to_string(),
+        _ => {
+            let bytes = memo.as_slice();
+            let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            hex::encode(&bytes[..end])
+        }
+    }
+}
+
+/// The results of trial-decrypting every shielded output in a wallet.
+#[derive(Debug, Default)]
+pub struct NoteDecryption {
+    notes: Vec<DecryptedNote>,
+    sapling_outputs: usize,
+    orchard_actions: usize,
+    transactions_without_raw: usize,
+    undecodable_transactions: Vec<(TxId, String)>,
+}
+
+impl NoteDecryption {
+    /// Trial-decrypt every Sapling output and Orchard action of every
+    /// transaction in `zewif` with each of `ivks`.
+    ///
+    /// Outputs that no IVK decrypts are normal (outgoing payments, or change
+    /// to keys the wallet does not hold) and are counted rather than treated
+    /// as errors. A transaction that cannot be decoded is recorded and skipped.
+    pub fn run(zewif: &ZewifTop, ivks: &WalletIvks) -> Self {
+        let mut result = Self::default();
+
+        for (txid, tx) in zewif.transactions() {
+            let decoded = match decode_transaction(tx) {
+                Ok(Some(decoded)) => decoded,
+                Ok(None) => {
+                    result.transactions_without_raw += 1;
+                    continue;
+                }
+                Err(e) => {
+                    result.undecodable_transactions.push((txid.clone(), format!("{:#}", e)));
+                    continue;
+                }
+            };
+
+            if let Some(bundle) = decoded.sapling_bundle() {
+                for (index, output) in bundle.shielded_outputs().iter().enumerate() {
+                    result.sapling_outputs += 1;
+                    let decrypted = ivks.sapling().iter().find_map(|(source, ivk)| {
+                        try_sapling_note_decryption(ivk, output, Zip212Enforcement::GracePeriod)
+                            .map(|(note, _, memo)| (source, note, memo))
+                    });
+                    if let Some((source, note, memo)) = decrypted {
+                        let note = ReceivedNote::Sapling(note);
+                        result.push(txid, Pool::Sapling, index, &memo, source, note);
+                    }
+                }
+            }
+
+            if let Some(bundle) = decoded.orchard_bundle() {
+                for (index, action) in bundle.actions().iter().enumerate() {
+                    result.orchard_actions += 1;
+                    let domain = OrchardDomain::for_action(action);
+                    let decrypted = ivks.orchard().iter().find_map(|(source, ivk)| {
+                        try_note_decryption(&domain, ivk, action)
+                            .map(|(note, _, memo)| (source, note, memo))
+                    });
+                    if let Some((source, note, memo)) = decrypted {
+                        let note = ReceivedNote::Orchard(note);
+                        result.push(txid, Pool::Orchard, index, &memo, source, note);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn push(
+        &mut self,
+        txid: &TxId,
+        pool: Pool,
+        index: usize,
+        memo: &[u8; 512],
+        recipient: &KeySource,
+        note: ReceivedNote,
+    ) {
+        let value = match &note {
+            ReceivedNote::Sapling(note) => note.value().inner(),
+            ReceivedNote::Orchard(note) => note.value().inner(),
+        };
+        self.notes.push(DecryptedNote {
+            txid: txid.clone(),
+            pool,
+            index: index as u32,
+            value,
+            memo: MemoBytes::from_bytes(memo).expect("512-byte memo is always valid"),
+            recipient: recipient.clone(),
+            note,
+        });
+    }
+
+    pub fn notes(&self) -> &[DecryptedNote] {
+        &self.notes
+    }
+
+    pub fn decrypted_count(&self, pool: Pool) -> usize {
+        self.notes.iter().filter(|n| n.pool == pool).count()
+    }
+
+    pub fn output_count(&self, pool: Pool) -> usize {
+        match pool {
+            Pool::Sapling => self.sapling_outputs,
+            Pool::Orchard => self.orchard_actions,
+        }
+    }
+
+    pub fn transactions_without_raw(&self) -> usize {
+        self.transactions_without_raw
+    }
+
+    pub fn undecodable_transactions(&self) -> &[(TxId, String)] {
+        &self.undecodable_transactions
+    }
+
+    /// Attach each decrypted note's value, memo and recipient key to the
+    /// corresponding output of the migrated wallet.
+    pub fn attach_to(&self, zewif: &mut ZewifTop) {
+        for note in &self.notes {
+            let envelope = Envelope::new("DecryptedNote")
+                .add_assertion("value", note.value)
+                .add_assertion("memo", note.memo_text())
+                .add_assertion("recipient", note.recipient.to_string());
+            attach_to_output(zewif, &note.txid, note.pool, note.index, envelope);
+        }
+    }
+
+    /// Append the decryption statistics to a migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Memo Decryption:")?;
+        for pool in [Pool::Sapling, Pool::Orchard] {
+            let total = self.output_count(pool);
+            let decrypted = self.decrypted_count(pool);
+            writeln!(
+                report,
+                "  * {} outputs: {}/{} decrypted, {} not decryptable with wallet IVKs",
+                pool,
+                decrypted,
+                total,
+                total - decrypted
+            )?;
+        }
+        let memo_count = self.notes.iter().filter(|n| !n.memo_text().is_empty()).count();
+        writeln!(report, "  * Non-empty memos recovered: {}", memo_count)?;
+        if self.transactions_without_raw > 0 {
+            writeln!(
+                report,
+                "  * Transactions skipped (no raw data): {}",
+                self.transactions_without_raw
+            )?;
+        }
+        for (txid, error) in &self.undecodable_transactions {
+            writeln!(report, "  * ❌ Could not decode {:?}: {}", txid, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Attach `envelope` to the Sapling output or Orchard action at `index` in the
+/// migrated transaction `txid`, if the migrated wallet contains it.
+pub fn attach_to_output(zewif: &mut ZewifTop, txid: &TxId, pool: Pool, index: u32, envelope: Envelope) {
+    let Some(tx) = zewif.transactions_mut().get_mut(txid) else {
+        return;
+    };
+    match pool {
+        Pool::Sapling => {
+            if let Some(output) = tx
+                .sapling_outputs_mut()
+                .and_then(|outputs| outputs.iter_mut().find(|o| o.index() == index))
+            {
+                output.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            }
+        }
+        Pool::Orchard => {
+            if let Some(action) = tx
+                .orchard_actions_mut()
+                .and_then(|actions| actions.iter_mut().find(|a| a.index() == index))
+            {
+                action.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            }
+        }
+    }
+}
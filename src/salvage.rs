@@ -0,0 +1,329 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    ops::RangeInclusive,
+    path::Path,
+    process::{Command, Output},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use owo_colors::{OwoColorize, Stream};
+use zewif_zcashd::BDBDump;
+
+use crate::mapped_file::MappedFile;
+
+/// The magic number of a Berkeley DB btree file, stored little-endian at
+/// offset 12 of its metadata page.
+const BDB_BTREE_MAGIC: [u8; 4] = [0x62, 0x31, 0x05, 0x00];
+
+/// The page size assumed when the metadata page itself is unreadable.
+const DEFAULT_PAGE_SIZE: usize = 4096;
+
+/// The size of the generic Berkeley DB page header.
+const PAGE_HEADER_LEN: usize = 26;
+
+/// The highest page type Berkeley DB defines.
+const MAX_PAGE_TYPE: u8 = 16;
+
+/// The record names zcashd writes to `wallet.dat`, used to identify which
+/// kinds of records were stored on damaged pages.
+pub const KNOWN_KEYNAMES: &[&str] = &[
+    "acc", "acentry", "bestblock", "bestblock_nomerkle", "chdseed", "ckey", "cmnemonicphrase",
+    "cscript", "czkey", "defaultkey", "destdata", "hdchain", "hdseed", "key", "keymeta",
+    "minversion", "mnemonichdchain", "mnemonicphrase", "name", "networkinfo",
+    "orchard_note_commitment_tree", "orderposnext", "pool", "purpose", "recipientmapping",
+    "sapextfvk", "sapkeymeta", "sapzaddr", "sapzkey", "tx", "unifiedaccount", "unifiedaddrmeta",
+    "unifiedfvk", "version", "watchs", "witnesscachesize", "wkey", "zkey", "zkeymeta",
+];
+
+/// How hard `db_dump` should try to recover records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SalvageMode {
+    /// Salvage with `db_dump -r`, which skips pages it cannot read.
+    Normal,
+    /// Salvage with `db_dump -R`, which also outputs records from damaged
+    /// pages, including some that may be garbage.
+    Aggressive,
+}
+
+impl SalvageMode {
+    fn flag(&self) -> &'static str {
+        match self {
+            SalvageMode::Normal => "-r",
+            SalvageMode::Aggressive => "-R",
+        }
+    }
+}
+
+/// What was lost and recovered while salvaging a damaged wallet file.
+#[derive(Debug, Clone)]
+pub struct SalvageReport {
+    mode: SalvageMode,
+    page_count: u32,
+    lost_pages: Vec<RangeInclusive<u32>>,
+    affected_keynames: BTreeSet<String>,
+    recovered: BTreeMap<String, usize>,
+}
+
+impl SalvageReport {
+    pub fn mode(&self) -> SalvageMode {
+        self.mode
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    /// The ranges of page numbers that are damaged or missing.
+    pub fn lost_pages(&self) -> &[RangeInclusive<u32>] {
+        &self.lost_pages
+    }
+
+    /// The names of the record types found on damaged pages, which may have
+    /// lost records.
+    pub fn affected_keynames(&self) -> &BTreeSet<String> {
+        &self.affected_keynames
+    }
+
+    /// The number of records recovered, by record name.
+    pub fn recovered(&self) -> &BTreeMap<String, usize> {
+        &self.recovered
+    }
+
+    /// Append the salvage results to a migration report. Lost pages are
+    /// highlighted in red, as the records on them may have held keys.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Salvage ({}):", self.mode.flag())?;
+        writeln!(
+            report,
+            "  * Records recovered: {}",
+            self.recovered.values().sum::<usize>()
+        )?;
+        for (keyname, count) in &self.recovered {
+            writeln!(report, "    {}: {}", keyname, count)?;
+        }
+        if self.lost_pages.is_empty() {
+            writeln!(report, "  * No damaged pages found in {} pages", self.page_count)?;
+            return Ok(());
+        }
+        let ranges: Vec<_> = self
+            .lost_pages
+            .iter()
+            .map(|range| {
+                if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}-{}", range.start(), range.end())
+                }
+            })
+            .collect();
+        let lost = format!(
+            "🔴 Lost pages: {} of {} ({})",
+            self.lost_pages.iter().map(|r| r.end() - r.start() + 1).sum::<u32>(),
+            self.page_count,
+            ranges.join(", ")
+        );
+        writeln!(
+            report,
+            "  * {}",
+            lost.if_supports_color(Stream::Stdout, |text| text.red().bold())
+        )?;
+        if self.affected_keynames.is_empty() {
+            writeln!(report, "  * Affected record types: unknown")?;
+        } else {
+            let affected = format!(
+                "Affected record types: {}",
+                self.affected_keynames.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+            writeln!(
+                report,
+                "  * {}",
+                affected.if_supports_color(Stream::Stdout, |text| text.red())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Recover every readable record from a damaged wallet file.
+///
+/// The file's pages are checked to find which are damaged and which record
+/// types they held. The readable records are then salvaged with `db_dump`
+/// and loaded with `db_load` into a fresh wallet file in a temporary
+/// directory, which is read in place of the original.
+pub fn salvage_wallet(wallet: &Path, mode: SalvageMode) -> Result<(BDBDump, SalvageReport)> {
+    let (page_count, lost_pages, affected_keynames) = scan_pages(&MappedFile::open(wallet)?);
+
+    let dump = run_bdb_utility("dump", |command| {
+        command.arg(mode.flag()).arg(wallet);
+    })?;
+    // db_dump reports the errors it skipped past with a failure status, so
+    // its output is used as long as it recovered anything.
+    if dump.stdout.is_empty() {
+        bail!(
+            "db_dump {} recovered nothing from {}: {}",
+            mode.flag(),
+            wallet.display(),
+            String::from_utf8_lossy(&dump.stderr).trim()
+        );
+    }
+    let recovered = count_records(&String::from_utf8_lossy(&dump.stdout));
+
+    let salvage_dir = tempfile::tempdir().context("Creating salvage directory")?;
+    let dump_file = salvage_dir.path().join("salvage.dump");
+    let salvaged_wallet = salvage_dir.path().join("wallet.dat");
+    std::fs::write(&dump_file, &dump.stdout)?;
+    let load = run_bdb_utility("load", |command| {
+        command.arg("-f").arg(&dump_file).arg(&salvaged_wallet);
+    })?;
+    if !load.status.success() {
+        bail!(
+            "db_load could not rebuild the salvaged wallet: {}",
+            String::from_utf8_lossy(&load.stderr).trim()
+        );
+    }
+
+    let db_dump = BDBDump::from_file(&salvaged_wallet).context("Parsing salvaged BerkeleyDB file")?;
+    let report = SalvageReport { mode, page_count, lost_pages, affected_keynames, recovered };
+    Ok((db_dump, report))
+}
+
+/// Run a Berkeley DB utility such as `db_dump`, trying the version-suffixed
+/// names that distributions use when the plain name is not installed.
+pub fn run_bdb_utility(utility: &str, args: impl Fn(&mut Command)) -> Result<Output> {
+    let names = [format!("db_{}", utility), format!("db6.2_{}", utility), format!("db4.8_{}", utility)];
+    for name in &names {
+        let mut command = Command::new(name);
+        args(&mut command);
+        match command.output() {
+            Ok(output) => return Ok(output),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Running {}", name)),
+        }
+    }
+    bail!("Salvage needs the Berkeley DB utilities; install one of: {}", names.join(", "))
+}
+
+/// Find the damaged and missing pages of a Berkeley DB btree file, and the
+/// record names that appear on the damaged ones. Returns the page count
+/// recorded in the metadata page, the merged ranges of lost pages, and the
+/// affected record names.
+///
+/// The metadata page may itself be corrupt, so the page count it records is
+/// trusted only to report pages missing from the end of the file, which are
+/// counted as one range rather than visited. An empty file has no pages, and
+/// so none are lost.
+pub fn scan_pages(bytes: &[u8]) -> (u32, Vec<RangeInclusive<u32>>, BTreeSet<String>) {
+    if bytes.is_empty() {
+        return (0, Vec::new(), BTreeSet::new());
+    }
+    let meta_ok = bytes.len() >= 36 && bytes[12..16] == BDB_BTREE_MAGIC;
+    let page_size = if meta_ok {
+        u32::from_le_bytes(bytes[20..24].try_into().unwrap()) as usize
+    } else {
+        DEFAULT_PAGE_SIZE
+    };
+    let page_size = if page_size.is_power_of_two() && page_size >= 512 {
+        page_size
+    } else {
+        DEFAULT_PAGE_SIZE
+    };
+    let pages_in_file = u32::try_from(bytes.len().div_ceil(page_size)).unwrap_or(u32::MAX);
+    let page_count = if meta_ok {
+        u32::from_le_bytes(bytes[32..36].try_into().unwrap()).saturating_add(1).max(pages_in_file)
+    } else {
+        pages_in_file
+    };
+
+    let mut lost = Vec::new();
+    let mut affected = BTreeSet::new();
+    if !meta_ok {
+        lost.push(0);
+    }
+    for pgno in 1..pages_in_file {
+        let start = pgno as usize * page_size;
+        let Some(page) = bytes.get(start..start + page_size) else {
+            // A partial last page is lost.
+            lost.push(pgno);
+            continue;
+        };
+        if is_damaged(page, pgno, page_size) {
+            lost.push(pgno);
+            affected.extend(keynames_in(page));
+        }
+    }
+
+    let mut ranges: Vec<RangeInclusive<u32>> = Vec::new();
+    for pgno in lost {
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == pgno => *range = *range.start()..=pgno,
+            _ => ranges.push(pgno..=pgno),
+        }
+    }
+    // Pages past the end of a truncated file are lost entirely.
+    if page_count > pages_in_file {
+        let missing = pages_in_file.max(1)..=page_count - 1;
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == *missing.start() => *range = *range.start()..=*missing.end(),
+            _ => ranges.push(missing),
+        }
+    }
+    (page_count, ranges, affected)
+}
+
+/// Whether a page's header is inconsistent with its position in the file.
+/// Pages that were allocated but never written are all zeros, and are not
+/// counted as damaged.
+fn is_damaged(page: &[u8], pgno: u32, page_size: usize) -> bool {
+    if page.iter().all(|&b| b == 0) {
+        return false;
+    }
+    let header_pgno = u32::from_le_bytes(page[8..12].try_into().unwrap());
+    let entries = u16::from_le_bytes(page[20..22].try_into().unwrap()) as usize;
+    let hf_offset = u16::from_le_bytes(page[22..24].try_into().unwrap()) as usize;
+    let page_type = page[25];
+    header_pgno != pgno
+        || page_type > MAX_PAGE_TYPE
+        || hf_offset > page_size
+        || PAGE_HEADER_LEN + entries * 2 > page_size
+}
+
+/// The known record names that appear, length-prefixed as zcashd writes
+/// them, anywhere in a page.
+fn keynames_in(page: &[u8]) -> impl Iterator<Item = String> + '_ {
+    KNOWN_KEYNAMES.iter().filter_map(move |keyname| {
+        let mut pattern = vec![keyname.len() as u8];
+        pattern.extend_from_slice(keyname.as_bytes());
+        page.windows(pattern.len())
+            .any(|window| window == pattern.as_slice())
+            .then(|| keyname.to_string())
+    })
+}
+
+/// Count the records in `db_dump` output by record name. Data lines begin
+/// with a space and alternate between keys and values; each key begins with
+/// its length-prefixed record name.
+fn count_records(dump: &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    let mut is_key = true;
+    for line in dump.lines() {
+        let Some(data) = line.strip_prefix(' ') else {
+            // Each database section restarts with a key.
+            is_key = true;
+            continue;
+        };
+        if is_key {
+            let keyname = hex::decode(data)
+                .ok()
+                .and_then(|key| {
+                    let len = *key.first()? as usize;
+                    key.get(1..1 + len).map(|name| String::from_utf8_lossy(name).to_string())
+                })
+                .unwrap_or_else(|| "(unreadable)".to_string());
+            *counts.entry(keyname).or_default() += 1;
+        }
+        is_key = !is_key;
+    }
+    counts
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result, anyhow, bail};
+use bip0039::{
+    ChineseSimplified, ChineseTraditional, Czech, English, French, Italian, Japanese, Korean,
+    Mnemonic, Portuguese, Spanish,
+};
+use bip32::{DerivationPath, XPrv};
+use orchard::keys::{FullViewingKey as OrchardFullViewingKey, SpendingKey as OrchardSpendingKey};
+use sapling::zip32::ExtendedSpendingKey;
+use zcash_address::unified::Receiver;
+use zcash_primitives::legacy::TransparentAddress as LegacyTransparentAddress;
+use zewif::{
+    Account, Address, Bip39Mnemonic, BlockHeight, Network, ProtocolAddress, SeedMaterial,
+    TransparentAddress, UnifiedAddress, ZewifTop, ZewifWallet,
+};
+use zeroize::Zeroizing;
+use zip32::{AccountId, ChildIndex, DiversifierIndex, Scope};
+
+use crate::{
+    address_encoding::{encode_transparent, encode_unified, pubkey_hash},
+    hd_verification::coin_type,
+};
+
+/// The parameters of a wallet restored from a seed phrase.
+#[derive(Debug, Clone)]
+pub struct SeedWalletOptions {
+    pub network: Network,
+    /// The height at which the wallet's first transaction could have been
+    /// mined, from which a restoring wallet should scan.
+    pub birthday: u32,
+    /// The number of ZIP-32 accounts to derive, starting at account 0.
+    pub accounts: u32,
+    /// The number of external transparent addresses to derive for each
+    /// account, in addition to the default unified address.
+    pub transparent_gap_limit: u32,
+}
+
+/// Validate a BIP-39 recovery phrase, checking that every word is in the
+/// English word list (the only language zcashd and zingo write) and that the
+/// checksum is correct. A valid phrase in another BIP-39 language is
+/// rejected as unsupported, since its seed would differ from the English
+/// phrase a wallet expects.
+pub fn validate_phrase(phrase: &str) -> Result<Mnemonic<English>> {
+    let normalized = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" "));
+    match Mnemonic::<English>::from_phrase(normalized.as_str()) {
+        Ok(mnemonic) => Ok(mnemonic),
+        Err(e) => match other_language(&normalized) {
+            Some(language) => bail!(
+                "Unsupported recovery phrase language: the phrase is a valid {} BIP-39 phrase, \
+                 but only English phrases are supported",
+                language
+            ),
+            None => bail!("Invalid English BIP-39 recovery phrase: {}", e),
+        },
+    }
+}
+
+/// The BIP-39 language other than English in which `phrase` is valid, if any.
+fn other_language(phrase: &str) -> Option<&'static str> {
+    [
+        ("Chinese (Simplified)", Mnemonic::<ChineseSimplified>::from_phrase(phrase).is_ok()),
+        ("Chinese (Traditional)", Mnemonic::<ChineseTraditional>::from_phrase(phrase).is_ok()),
+        ("Czech", Mnemonic::<Czech>::from_phrase(phrase).is_ok()),
+        ("French", Mnemonic::<French>::from_phrase(phrase).is_ok()),
+        ("Italian", Mnemonic::<Italian>::from_phrase(phrase).is_ok()),
+        ("Japanese", Mnemonic::<Japanese>::from_phrase(phrase).is_ok()),
+        ("Korean", Mnemonic::<Korean>::from_phrase(phrase).is_ok()),
+        ("Portuguese", Mnemonic::<Portuguese>::from_phrase(phrase).is_ok()),
+        ("Spanish", Mnemonic::<Spanish>::from_phrase(phrase).is_ok()),
+    ]
+    .into_iter()
+    .find_map(|(language, valid)| valid.then_some(language))
+}
+
+/// Build a fresh ZeWIF wallet from a BIP-39 recovery phrase, with a ZIP-32
+/// account holding a default unified address for each requested account.
+pub fn build_seed_wallet(phrase: &str, options: &SeedWalletOptions) -> Result<ZewifTop> {
+    let mnemonic = validate_phrase(phrase)?;
+    let seed = mnemonic.to_seed("");
+    let coin_type = coin_type(options.network);
+
+    let mut zewif_wallet = ZewifWallet::new(options.network);
+    zewif_wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+        mnemonic.phrase().to_string(),
+    )));
+
+    let master = ExtendedSpendingKey::master(&seed);
+    for account_index in 0..options.accounts {
+        let account_id = AccountId::try_from(account_index)
+            .map_err(|_| anyhow!("Invalid ZIP-32 account index {}", account_index))?;
+
+        let mut account = Account::new();
+        account.set_name(format!("Account #{}", account_index));
+        account.set_zip32_account_id(account_index);
+        account.set_birthday_height(BlockHeight::from(options.birthday));
+
+        let extsk = ExtendedSpendingKey::from_path(
+            &master,
+            &[
+                ChildIndex::hardened(32),
+                ChildIndex::hardened(coin_type),
+                ChildIndex::hardened(account_index),
+            ],
+        );
+        let (diversifier_index, sapling_address) =
+            extsk.to_diversifiable_full_viewing_key().default_address();
+
+        let orchard_sk = OrchardSpendingKey::from_zip32_seed(&seed, coin_type, account_id)
+            .map_err(|e| anyhow!("Deriving Orchard key for account {}: {:?}", account_index, e))?;
+        let orchard_address =
+            OrchardFullViewingKey::from(&orchard_sk).address_at(diversifier_index, Scope::External);
+
+        let mut receivers = vec![
+            Receiver::Orchard(orchard_address.to_raw_address_bytes()),
+            Receiver::Sapling(sapling_address.to_bytes()),
+        ];
+        // The transparent receiver of a unified address is derived at the
+        // address's diversifier index, where that index is a valid BIP-44
+        // child.
+        if let Some(index) = transparent_index(diversifier_index) {
+            receivers.push(Receiver::P2pkh(p2pkh_hash(&seed, coin_type, account_index, index)?));
+        }
+        let mut unified = Address::new(ProtocolAddress::Unified(UnifiedAddress::new(
+            encode_unified(options.network, receivers)?,
+        )));
+        unified.set_purpose("default".to_string());
+        account.add_address(unified);
+
+        for index in 0..options.transparent_gap_limit {
+            let hash = p2pkh_hash(&seed, coin_type, account_index, index)?;
+            let encoded =
+                encode_transparent(options.network, &LegacyTransparentAddress::PublicKeyHash(hash));
+            let mut address =
+                Address::new(ProtocolAddress::Transparent(TransparentAddress::new(encoded)));
+            address.set_purpose("receive".to_string());
+            account.add_address(address);
+        }
+
+        zewif_wallet.add_account(account);
+    }
+
+    let mut zewif = ZewifTop::new();
+    zewif.add_wallet(zewif_wallet);
+    Ok(zewif)
+}
+
+fn transparent_index(diversifier_index: DiversifierIndex) -> Option<u32> {
+    u32::try_from(diversifier_index).ok().filter(|index| *index < 1 << 31)
+}
+
+/// The public key hash of the external transparent key at
+/// `m/44'/coin_type'/account'/0/index`.
+pub(crate) fn p2pkh_hash(seed: &[u8], coin_type: u32, account: u32, index: u32) -> Result<[u8; 20]> {
+    let path: DerivationPath = format!("m/44'/{}'/{}'/0/{}", coin_type, account, index)
+        .parse()
+        .context("Building transparent derivation path")?;
+    let xprv = XPrv::derive_from_path(seed, &path).context("Deriving transparent key")?;
+    Ok(pubkey_hash(&xprv.public_key().to_bytes()))
+}
@@ -0,0 +1,410 @@
+use std::{collections::HashSet, fmt::Write};
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use orchard::note_encryption::OrchardDomain;
+use sapling::{
+    Rseed,
+    note_encryption::{Zip212Enforcement, try_sapling_output_recovery},
+};
+use zcash_address::unified::Receiver;
+use zcash_note_encryption::try_output_recovery_with_ovk;
+use zcash_protocol::memo::MemoBytes;
+use zewif::{Amount, Blob, OrchardSentOutput, TxId, ZewifTop, sapling::SaplingSentOutput, u256};
+use zewif_zcashd::ZcashdWallet;
+
+use crate::{
+    address_encoding::{encode_orchard, encode_sapling, unified_receivers},
+    note_decryption::{Pool, attach_to_output, memo_text},
+    tx_decode::decode_transaction,
+    viewing_keys::{KeySource, UnifiedAccountFvks, WalletOvks},
+};
+
+/// The note fields recovered from an out-ciphertext that are needed to
+/// reconstruct the sent note.
+#[derive(Debug, Clone)]
+enum RecoveredNote {
+    Sapling { note_version: u8, rcm: [u8; 32] },
+    Orchard { rho: [u8; 32], rseed: [u8; 32] },
+}
+
+/// A shielded output sent by the wallet, recovered with one of its OVKs.
+#[derive(Debug, Clone)]
+pub struct RecoveredOutput {
+    txid: TxId,
+    pool: Pool,
+    index: u32,
+    sender: KeySource,
+    recipient: String,
+    receiver: Receiver,
+    is_change: bool,
+    value: u64,
+    memo: MemoBytes,
+    note: RecoveredNote,
+}
+
+impl RecoveredOutput {
+    pub fn txid(&self) -> &TxId {
+        &self.txid
+    }
+
+    pub fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The OVK that recovered the output, which identifies the sending account.
+    pub fn sender(&self) -> &KeySource {
+        &self.sender
+    }
+
+    /// The encoded recipient address. Orchard recipients are encoded as
+    /// unified addresses with a single Orchard receiver.
+    pub fn recipient(&self) -> &str {
+        &self.recipient
+    }
+
+    /// Whether the recipient is one of the wallet's own addresses.
+    pub fn is_change(&self) -> bool {
+        self.is_change
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn memo(&self) -> &MemoBytes {
+        &self.memo
+    }
+
+    /// The memo rendered as text, or as hex if it is not text.
+    pub fn memo_text(&self) -> String {
+        memo_text(&self.memo)
+    }
+}
+
+/// A disagreement between a recovered output and zcashd's `recipientmapping`
+/// records for the same transaction.
+#[derive(Debug, Clone)]
+pub enum RecipientMismatch {
+    /// An output paid a recipient that zcashd did not record for the transaction.
+    Unrecorded { txid: TxId, recipient: String },
+    /// zcashd recorded a recipient that no recovered output pays.
+    Unmatched { txid: TxId, recipient: String },
+}
+
+impl std::fmt::Display for RecipientMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipientMismatch::Unrecorded { txid, recipient } => write!(
+                f,
+                "{:?}: recovered recipient {} is missing from zcashd's recipient mapping",
+                txid, recipient
+            ),
+            RecipientMismatch::Unmatched { txid, recipient } => write!(
+                f,
+                "{:?}: zcashd recorded recipient {} but no recovered output pays it",
+                txid, recipient
+            ),
+        }
+    }
+}
+
+/// The results of recovering the wallet's sent shielded outputs.
+#[derive(Debug, Default)]
+pub struct SentOutputRecovery {
+    outputs: Vec<RecoveredOutput>,
+    mismatches: Vec<RecipientMismatch>,
+    /// `recipientmapping` records whose unified address could not be
+    /// decoded, with the error, which were left out of the comparison.
+    undecodable_mappings: Vec<(TxId, String, String)>,
+    /// Indices into `outputs` of recovered outputs whose sending account was
+    /// not found among the migrated accounts, so no sent output was filled.
+    unassigned: Vec<usize>,
+    sapling_outputs: usize,
+    orchard_actions: usize,
+}
+
+impl SentOutputRecovery {
+    /// Try every OVK of the wallet against every Sapling output and Orchard
+    /// action in the wallet's transactions, then compare the recovered
+    /// external recipients with zcashd's stored recipient mapping.
+    pub fn run(zcashd_wallet: &ZcashdWallet, zewif: &ZewifTop) -> Result<Self> {
+        let ovks = WalletOvks::from_zcashd_wallet(zcashd_wallet)
+            .context("Collecting outgoing viewing keys")?;
+        let fvks = UnifiedAccountFvks::from_zcashd_wallet(zcashd_wallet)?;
+        let network = zcashd_wallet.network();
+        let own_sapling: HashSet<String> = zcashd_wallet
+            .sapling_z_addresses()
+            .keys()
+            .map(|address| address.to_string(network))
+            .collect();
+
+        let mut result = Self::default();
+        for (txid, tx) in zewif.transactions() {
+            // Transactions that cannot be decoded are reported by the memo
+            // decryption pass; here they simply contribute no outputs.
+            let Ok(Some(decoded)) = decode_transaction(tx) else {
+                continue;
+            };
+
+            if let Some(bundle) = decoded.sapling_bundle() {
+                for (index, output) in bundle.shielded_outputs().iter().enumerate() {
+                    result.sapling_outputs += 1;
+                    let recovered = ovks.sapling().iter().find_map(|(source, ovk)| {
+                        try_sapling_output_recovery(ovk, output, Zip212Enforcement::GracePeriod)
+                            .map(|recovered| (source, recovered))
+                    });
+                    let Some((sender, (note, address, memo))) = recovered else {
+                        continue;
+                    };
+                    let recipient = encode_sapling(network, &address);
+                    let is_change = own_sapling.contains(&recipient)
+                        || fvks.iter().any(|f| f.owns_sapling(&address));
+                    let note_fields = match note.rseed() {
+                        Rseed::BeforeZip212(rcm) => {
+                            RecoveredNote::Sapling { note_version: 1, rcm: rcm.to_bytes() }
+                        }
+                        Rseed::AfterZip212(rseed) => {
+                            RecoveredNote::Sapling { note_version: 2, rcm: *rseed }
+                        }
+                    };
+                    result.outputs.push(RecoveredOutput {
+                        txid: txid.clone(),
+                        pool: Pool::Sapling,
+                        index: index as u32,
+                        sender: sender.clone(),
+                        recipient,
+                        receiver: Receiver::Sapling(address.to_bytes()),
+                        is_change,
+                        value: note.value().inner(),
+                        memo: MemoBytes::from_bytes(&memo).expect("512-byte memo is always valid"),
+                        note: note_fields,
+                    });
+                }
+            }
+
+            if let Some(bundle) = decoded.orchard_bundle() {
+                for (index, action) in bundle.actions().iter().enumerate() {
+                    result.orchard_actions += 1;
+                    let domain = OrchardDomain::for_action(action);
+                    let recovered = ovks.orchard().iter().find_map(|(source, ovk)| {
+                        try_output_recovery_with_ovk(
+                            &domain,
+                            ovk,
+                            action,
+                            action.cv_net(),
+                            &action.encrypted_note().out_ciphertext,
+                        )
+                        .map(|recovered| (source, recovered))
+                    });
+                    let Some((sender, (note, address, memo))) = recovered else {
+                        continue;
+                    };
+                    result.outputs.push(RecoveredOutput {
+                        txid: txid.clone(),
+                        pool: Pool::Orchard,
+                        index: index as u32,
+                        sender: sender.clone(),
+                        recipient: encode_orchard(network, &address)?,
+                        receiver: Receiver::Orchard(address.to_raw_address_bytes()),
+                        is_change: fvks.iter().any(|f| f.owns_orchard(&address)),
+                        value: note.value().inner(),
+                        memo: MemoBytes::from_bytes(&memo).expect("512-byte memo is always valid"),
+                        note: RecoveredNote::Orchard {
+                            rho: note.rho().to_bytes(),
+                            rseed: *note.rseed().as_bytes(),
+                        },
+                    });
+                }
+            }
+        }
+
+        result.compare_recipient_mappings(zcashd_wallet);
+        Ok(result)
+    }
+
+    /// zcashd only writes `recipientmapping` records for sends to unified
+    /// addresses, so transactions without records are not compared. A record
+    /// whose unified address cannot be decoded is skipped and reported.
+    fn compare_recipient_mappings(&mut self, zcashd_wallet: &ZcashdWallet) {
+        for (txid, mappings) in zcashd_wallet.send_recipients() {
+            let recovered: Vec<_> = self
+                .outputs
+                .iter()
+                .filter(|o| o.txid == *txid && !o.is_change)
+                .collect();
+            if recovered.is_empty() {
+                continue;
+            }
+
+            let mut recorded = Vec::new();
+            for mapping in mappings {
+                let unified_address = mapping.unified_address().to_string();
+                let receivers = match unified_receivers(&unified_address) {
+                    Ok(receivers) => receivers,
+                    Err(e) => {
+                        self.undecodable_mappings.push((txid.clone(), unified_address, format!("{:#}", e)));
+                        continue;
+                    }
+                };
+                recorded.push((unified_address, mapping.recipient_address().to_string(), receivers));
+            }
+
+            for output in &recovered {
+                let is_recorded = recorded.iter().any(|(_, recipient, receivers)| {
+                    *recipient == output.recipient || receivers.contains(&output.receiver)
+                });
+                if !is_recorded {
+                    self.mismatches.push(RecipientMismatch::Unrecorded {
+                        txid: txid.clone(),
+                        recipient: output.recipient.clone(),
+                    });
+                }
+            }
+
+            for (unified_address, recipient, receivers) in &recorded {
+                let is_paid = recovered.iter().any(|output| {
+                    *recipient == output.recipient || receivers.contains(&output.receiver)
+                });
+                if !is_paid {
+                    self.mismatches.push(RecipientMismatch::Unmatched {
+                        txid: txid.clone(),
+                        recipient: unified_address.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn outputs(&self) -> &[RecoveredOutput] {
+        &self.outputs
+    }
+
+    pub fn mismatches(&self) -> &[RecipientMismatch] {
+        &self.mismatches
+    }
+
+    pub fn undecodable_mappings(&self) -> &[(TxId, String, String)] {
+        &self.undecodable_mappings
+    }
+
+    /// Recovered outputs that `fill_sent_outputs` could not assign to a
+    /// migrated account.
+    pub fn unassigned_outputs(&self) -> impl Iterator<Item = &RecoveredOutput> {
+        self.unassigned.iter().map(|&i| &self.outputs[i])
+    }
+
+    /// Add each recovered output to the sent-output list of the migrated
+    /// account that owns the OVK which recovered it. ZeWIF's sent outputs
+    /// have no memo field, so the recipient, value and memo are also attached
+    /// to the transaction output they were recovered from. Outputs whose
+    /// sending account was not migrated are kept for the report.
+    pub fn fill_sent_outputs(&mut self, zewif: &mut ZewifTop) -> Result<()> {
+        self.unassigned.clear();
+        for (i, output) in self.outputs.iter().enumerate() {
+            let envelope = Envelope::new("SentOutput")
+                .add_assertion("recipient", output.recipient.clone())
+                .add_assertion("value", output.value)
+                .add_assertion("memo", output.memo_text())
+                .add_assertion("change", output.is_change);
+            attach_to_output(zewif, &output.txid, output.pool, output.index, envelope);
+
+            let account = zewif
+                .wallets_mut()
+                .values_mut()
+                .flat_map(|wallet| wallet.accounts_mut().values_mut())
+                .find(|account| output.sender.owns(account));
+            let Some(account) = account else {
+                self.unassigned.push(i);
+                continue;
+            };
+
+            let value = Amount::from_u64(output.value)
+                .with_context(|| format!("Invalid value in {:?}", output.txid))?;
+            match (&output.note, &output.receiver) {
+                (RecoveredNote::Sapling { note_version, rcm }, Receiver::Sapling(recipient)) => {
+                    let mut sent = SaplingSentOutput::new();
+                    sent.set_note_version(*note_version);
+                    sent.set_recipient_address(Blob::new(*recipient));
+                    sent.set_value(value);
+                    sent.set_rcm(u256::from(*rcm));
+                    account.add_sapling_sent_output(sent);
+                }
+                (RecoveredNote::Orchard { rho, rseed }, Receiver::Orchard(recipient)) => {
+                    let mut sent = OrchardSentOutput::new();
+                    sent.set_recipient_address(Blob::new(*recipient));
+                    sent.set_value(value);
+                    sent.set_rho(u256::from(*rho));
+                    sent.set_rseed(u256::from(*rseed));
+                    account.add_orchard_sent_output(sent);
+                }
+                _ => bail!(
+                    "{:?}: {} output {} was recovered with a receiver from another pool",
+                    output.txid,
+                    output.pool,
+                    output.index
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the recovery statistics and any recipient mismatches to a
+    /// migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Sent Output Recovery:")?;
+        for pool in [Pool::Sapling, Pool::Orchard] {
+            let total = match pool {
+                Pool::Sapling => self.sapling_outputs,
+                Pool::Orchard => self.orchard_actions,
+            };
+            let recovered: Vec<_> = self.outputs.iter().filter(|o| o.pool == pool).collect();
+            let change = recovered.iter().filter(|o| o.is_change).count();
+            writeln!(
+                report,
+                "  * {} outputs: {}/{} recovered with wallet OVKs ({} to the wallet's own addresses)",
+                pool,
+                recovered.len(),
+                total,
+                change
+            )?;
+        }
+        if self.mismatches.is_empty() {
+            writeln!(report, "  * Recipient mapping: consistent with recovered outputs")?;
+        } else {
+            writeln!(report, "  * Recipient mapping disagreements: {}", self.mismatches.len())?;
+            for mismatch in &self.mismatches {
+                writeln!(report, "    ⚠️ {}", mismatch)?;
+            }
+        }
+        if !self.unassigned.is_empty() {
+            writeln!(
+                report,
+                "  * Recovered outputs with no migrated sending account: {}",
+                self.unassigned.len()
+            )?;
+            for output in self.unassigned_outputs() {
+                writeln!(
+                    report,
+                    "    ⚠️ {:?}: {} output {} recovered with the OVK of {} was not added to any account",
+                    output.txid, output.pool, output.index, output.sender
+                )?;
+            }
+        }
+        if !self.undecodable_mappings.is_empty() {
+            writeln!(
+                report,
+                "  * Recipient mapping records skipped as undecodable: {}",
+                self.undecodable_mappings.len()
+            )?;
+            for (txid, unified_address, error) in &self.undecodable_mappings {
+                writeln!(report, "    ⚠️ {:?}: {}: {}", txid, unified_address, error)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use std::{collections::HashSet, fmt::Write};
+
+use anyhow::Result;
+use bc_envelope::prelude::*;
+use blake2b_simd::Params as Blake2bParams;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, aead::Aead};
+use owo_colors::{OwoColorize, Stream};
+use sha2::digest::generic_array::GenericArray;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zewif::{TxId, ZewifTop};
+use zewif_zcashd::ZcashdWallet;
+
+use crate::{balances::format_zec, note_decryption::ZMIGRATE_VENDOR, tx_decode::decode_transaction};
+
+/// The SHA-256 initial state, used by the `SHA256Compress` PRFs of the Sprout
+/// key and nullifier derivations.
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The length of a Sprout note plaintext: lead byte, value, rho, r and memo.
+const NOTE_PLAINTEXT_LEN: usize = 1 + 8 + 32 + 32 + 512;
+
+/// A Sprout spending key and the encryption keys derived from it.
+struct SproutKey {
+    address: String,
+    a_sk: [u8; 32],
+    sk_enc: StaticSecret,
+    pk_enc: PublicKey,
+}
+
+impl SproutKey {
+    fn new(address: String, a_sk: [u8; 32]) -> Self {
+        let mut t = [0u8; 32];
+        t[0] = 1;
+        // StaticSecret clamps the scalar when it is used, as Sprout requires.
+        let sk_enc = StaticSecret::from(sha256_compress(0xc0, &a_sk, &t));
+        let pk_enc = PublicKey::from(&sk_enc);
+        Self { address, a_sk, sk_enc, pk_enc }
+    }
+
+    /// `PRF^nf` of the note's rho under this key.
+    fn nullifier(&self, rho: &[u8; 32]) -> [u8; 32] {
+        sha256_compress(0xe0, &self.a_sk, rho)
+    }
+}
+
+/// `SHA256Compress` of a block holding a four-bit prefix, the 252-bit spending
+/// key and 256 further bits, as used by `PRF^addr` and `PRF^nf`.
+fn sha256_compress(prefix: u8, a_sk: &[u8; 32], rest: &[u8; 32]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    block[..32].copy_from_slice(a_sk);
+    block[0] = prefix | (a_sk[0] & 0x0f);
+    block[32..].copy_from_slice(rest);
+
+    let mut state = SHA256_IV;
+    sha2::compress256(&mut state, &[GenericArray::clone_from_slice(&block)]);
+    let mut result = [0u8; 32];
+    for (chunk, word) in result.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    result
+}
+
+/// A Sprout note received by one of the wallet's spending keys.
+#[derive(Debug, Clone)]
+pub struct SproutNote {
+    txid: TxId,
+    joinsplit: usize,
+    output: usize,
+    address: String,
+    value: u64,
+    nullifier: [u8; 32],
+    spent: bool,
+}
+
+impl SproutNote {
+    pub fn txid(&self) -> &TxId {
+        &self.txid
+    }
+
+    /// The index of the JoinSplit description within its transaction.
+    pub fn joinsplit(&self) -> usize {
+        self.joinsplit
+    }
+
+    /// The index of the output within its JoinSplit description.
+    pub fn output(&self) -> usize {
+        self.output
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn nullifier(&self) -> &[u8; 32] {
+        &self.nullifier
+    }
+
+    /// Whether the note's nullifier is revealed by one of the wallet's
+    /// transactions.
+    pub fn is_spent(&self) -> bool {
+        self.spent
+    }
+}
+
+/// The Sprout keys, JoinSplit outputs and notes found in a zcashd wallet.
+#[derive(Debug, Default)]
+pub struct SproutFunds {
+    addresses: Vec<String>,
+    joinsplits: usize,
+    notes: Vec<SproutNote>,
+}
+
+impl SproutFunds {
+    /// Trial-decrypt every JoinSplit output in the wallet's transactions with
+    /// the wallet's Sprout spending keys, and mark each recovered note spent
+    /// if its nullifier appears in any wallet transaction.
+    pub fn run(zcashd_wallet: &ZcashdWallet, zewif: &ZewifTop) -> Self {
+        let network = zcashd_wallet.network();
+        let keys: Vec<SproutKey> = zcashd_wallet
+            .sprout_keys()
+            .map(|sprout_keys| {
+                sprout_keys
+                    .iter()
+                    .map(|(address, key)| {
+                        let mut a_sk = [0u8; 32];
+                        a_sk.copy_from_slice(key.as_ref());
+                        SproutKey::new(address.to_string(network), a_sk)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut result = Self {
+            addresses: keys.iter().map(|key| key.address.clone()).collect(),
+            ..Default::default()
+        };
+        let mut revealed_nullifiers = HashSet::new();
+
+        for (txid, tx) in zewif.transactions() {
+            // Transactions that cannot be decoded are reported by the memo
+            // decryption pass; here they simply contribute no JoinSplits.
+            let Ok(Some(decoded)) = decode_transaction(tx) else {
+                continue;
+            };
+            let Some(bundle) = decoded.sprout_bundle() else {
+                continue;
+            };
+
+            for (js_index, joinsplit) in bundle.joinsplits.iter().enumerate() {
+                result.joinsplits += 1;
+                let nullifiers = joinsplit.nullifiers();
+                revealed_nullifiers.extend(nullifiers.iter().copied());
+
+                let h_sig = Blake2bParams::new()
+                    .hash_length(32)
+                    .personal(b"ZcashComputehSig")
+                    .to_state()
+                    .update(joinsplit.random_seed())
+                    .update(&nullifiers[0])
+                    .update(&nullifiers[1])
+                    .update(&bundle.joinsplit_pubkey)
+                    .finalize();
+                let epk = PublicKey::from(*joinsplit.ephemeral_key());
+
+                for (output, ciphertext) in joinsplit.ciphertexts().iter().enumerate() {
+                    for key in &keys {
+                        let Some(plaintext) =
+                            decrypt_note(key, h_sig.as_bytes(), &epk, output as u8, ciphertext)
+                        else {
+                            continue;
+                        };
+                        let value = u64::from_le_bytes(plaintext[1..9].try_into().unwrap());
+                        let rho: [u8; 32] = plaintext[9..41].try_into().unwrap();
+                        result.notes.push(SproutNote {
+                            txid: txid.clone(),
+                            joinsplit: js_index,
+                            output,
+                            address: key.address.clone(),
+                            value,
+                            nullifier: key.nullifier(&rho),
+                            spent: false,
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+
+        for note in &mut result.notes {
+            note.spent = revealed_nullifiers.contains(&note.nullifier);
+        }
+        result
+    }
+
+    /// The encoded addresses of the wallet's Sprout spending keys.
+    pub fn addresses(&self) -> &[String] {
+        &self.addresses
+    }
+
+    pub fn notes(&self) -> &[SproutNote] {
+        &self.notes
+    }
+
+    /// The total value of all received Sprout notes.
+    pub fn total_value(&self) -> u64 {
+        self.notes.iter().map(|note| note.value).sum()
+    }
+
+    /// The value of the Sprout notes that have not been spent.
+    pub fn unspent_value(&self) -> u64 {
+        self.notes.iter().filter(|note| !note.spent).map(|note| note.value).sum()
+    }
+
+    /// Whether the wallet holds any Sprout keys or notes at all.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.notes.is_empty()
+    }
+
+    /// Preserve each received Sprout note as an attachment on its migrated
+    /// transaction.
+    pub fn attach_to(&self, zewif: &mut ZewifTop) {
+        for note in &self.notes {
+            let Some(tx) = zewif.transactions_mut().get_mut(&note.txid) else {
+                continue;
+            };
+            let envelope = Envelope::new("SproutNote")
+                .add_assertion("joinsplit", note.joinsplit as u64)
+                .add_assertion("output", note.output as u64)
+                .add_assertion("address", note.address.clone())
+                .add_assertion("value", note.value)
+                .add_assertion("nullifier", hex::encode(note.nullifier))
+                .add_assertion("spent", note.spent);
+            tx.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+        }
+    }
+
+    /// Append the Sprout totals and, if Sprout funds remain, a ZIP-308 sweep
+    /// advisory to a migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Sprout:")?;
+        writeln!(report, "  * Spending keys: {}", self.addresses.len())?;
+        writeln!(report, "  * JoinSplit descriptions: {}", self.joinsplits)?;
+        writeln!(
+            report,
+            "  * Notes: {} received ({}), {} unspent ({})",
+            self.notes.len(),
+            format_zec(self.total_value()),
+            self.notes.iter().filter(|note| !note.spent).count(),
+            format_zec(self.unspent_value())
+        )?;
+        if self.unspent_value() > 0 {
+            let advisory = format!(
+                "🔴 {} of Sprout funds may become unspendable under ZIP-2003; \
+                 sweep them to Sapling with zcashd's Sprout-to-Sapling migration (ZIP-308) \
+                 and migrate the post-sweep wallet",
+                format_zec(self.unspent_value())
+            );
+            writeln!(
+                report,
+                "    {}",
+                advisory.if_supports_color(Stream::Stdout, |text| text.red().bold())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Decrypt one JoinSplit output ciphertext with a Sprout key, returning the
+/// note plaintext if the key is the recipient.
+fn decrypt_note(
+    key: &SproutKey,
+    h_sig: &[u8],
+    epk: &PublicKey,
+    output: u8,
+    ciphertext: &[u8],
+) -> Option<Vec<u8>> {
+    let shared_secret = key.sk_enc.diffie_hellman(epk);
+    let mut personal = [0u8; 16];
+    personal[..8].copy_from_slice(b"ZcashKDF");
+    personal[8] = output;
+    let kdf = Blake2bParams::new()
+        .hash_length(32)
+        .personal(&personal)
+        .to_state()
+        .update(h_sig)
+        .update(shared_secret.as_bytes())
+        .update(epk.as_bytes())
+        .update(key.pk_enc.as_bytes())
+        .finalize();
+
+    let cipher = ChaCha20Poly1305::new_from_slice(kdf.as_bytes()).ok()?;
+    let plaintext = cipher.decrypt(&[0u8; 12].into(), ciphertext).ok()?;
+    (plaintext.len() == NOTE_PLAINTEXT_LEN && plaintext[0] == 0x00).then_some(plaintext)
+}
@@ -0,0 +1,35 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    file_args::{FileArgs, FileArgsLike},
+    formats::{FrontendOptions, SqliteFrontend, WalletFrontend, dump_source_wallet},
+    json_output::OutputFormat,
+};
+
+/// Process a `zcash_client_sqlite` wallet database
+#[derive(Debug, Args)]
+#[group(skip)]
+pub struct CommandArgs {
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// How to render the source and migrated wallets.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+impl FileArgsLike for CommandArgs {
+    fn file(&self) -> &PathBuf {
+        &self.file_args.file
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        let wallet = SqliteFrontend.parse(self.file(), &FrontendOptions::default())?;
+        dump_source_wallet(wallet.as_ref(), None, self.format, false, out)
+    }
+}
@@ -0,0 +1,332 @@
+use std::{fmt::Write, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail};
+use bip0039::{English, Mnemonic};
+use rand_core::OsRng;
+use secrecy::SecretVec;
+use zcash_client_backend::{
+    data_api::{
+        Account as _, AccountBirthday, AccountPurpose, WalletRead, WalletWrite,
+        chain::ChainState, wallet::decrypt_and_store_transaction,
+    },
+    proto::service::TreeState,
+};
+use zcash_client_sqlite::{WalletDb, util::SystemClock, wallet::init::init_wallet_db};
+use zcash_keys::{
+    address::UnifiedAddress as KeysUnifiedAddress,
+    keys::{UnifiedAddressRequest, UnifiedFullViewingKey},
+};
+use zcash_protocol::consensus::{self, BlockHeight as ChainHeight, NetworkUpgrade, Parameters};
+use zewif::{Account, Network, ProtocolAddress, SeedMaterial, ZewifTop, ZewifWallet};
+
+use crate::{address_encoding::unified_receivers, tx_decode::decode_transaction};
+
+/// What was written to a new `zcash_client_sqlite` wallet database.
+#[derive(Debug, Default)]
+pub struct SqliteExportReport {
+    derived_accounts: usize,
+    imported_accounts: usize,
+    addresses: usize,
+    transactions: usize,
+    birthday: u32,
+    from_tree_state: bool,
+    account_birthdays: Vec<(String, u32)>,
+    skipped: Vec<String>,
+}
+
+impl SqliteExportReport {
+    /// The accounts created from the wallet's seed.
+    pub fn derived_accounts(&self) -> usize {
+        self.derived_accounts
+    }
+
+    /// The view-only accounts imported from a UFVK.
+    pub fn imported_accounts(&self) -> usize {
+        self.imported_accounts
+    }
+
+    /// The diversified addresses reserved in addition to each account's
+    /// default address.
+    pub fn addresses(&self) -> usize {
+        self.addresses
+    }
+
+    pub fn transactions(&self) -> usize {
+        self.transactions
+    }
+
+    /// The height the note commitment trees start from: the tree state's
+    /// height, or Sapling activation without one.
+    pub fn birthday(&self) -> u32 {
+        self.birthday
+    }
+
+    pub fn from_tree_state(&self) -> bool {
+        self.from_tree_state
+    }
+
+    /// The birthday height each written account recorded in the source
+    /// wallet. Every account is given [`birthday`](Self::birthday) instead.
+    pub fn account_birthdays(&self) -> &[(String, u32)] {
+        &self.account_birthdays
+    }
+
+    /// The accounts whose recorded birthday is below the tree state, so
+    /// that notes they received before it will not be found by scanning.
+    pub fn accounts_born_before_tree_state(&self) -> impl Iterator<Item = &(String, u32)> {
+        self.account_birthdays
+            .iter()
+            .filter(|(_, height)| self.from_tree_state && *height < self.birthday)
+    }
+
+    /// Accounts, addresses and transactions that could not be written, and
+    /// why.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Accounts: {} from seed, {} view-only", self.derived_accounts, self.imported_accounts)?;
+        writeln!(report, "- Diversified addresses: {}", self.addresses)?;
+        writeln!(report, "- Transactions stored: {}", self.transactions)?;
+        if self.from_tree_state {
+            writeln!(report, "- Note commitment trees: from the tree state at height {}", self.birthday)?;
+            for (account, height) in self.accounts_born_before_tree_state() {
+                writeln!(
+                    report,
+                    "  * ⚠️ account {} was born at height {}, before the tree state; notes it received \
+                     before height {} will not be found",
+                    account, height, self.birthday
+                )?;
+            }
+        } else {
+            writeln!(
+                report,
+                "- Note commitment trees: no tree state given; the wallet will scan from Sapling activation at height {}",
+                self.birthday
+            )?;
+            for (account, height) in &self.account_birthdays {
+                writeln!(
+                    report,
+                    "  * account {}: recorded birthday {} discarded; scanning starts at {}",
+                    account, height, self.birthday
+                )?;
+            }
+        }
+        if !self.skipped.is_empty() {
+            writeln!(report, "- Not written:")?;
+            for skipped in &self.skipped {
+                writeln!(report, "  * ⚠️ {}", skipped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the JSON output of lightwalletd's `GetTreeState`, as written by
+/// `grpcurl`.
+pub fn parse_tree_state(json: &str) -> Result<TreeState> {
+    let value: serde_json::Value = serde_json::from_str(json).context("Parsing tree state JSON")?;
+    let field = |name: &str| -> Result<&serde_json::Value> {
+        value.get(name).with_context(|| format!("Tree state has no {} field", name))
+    };
+    let number = |name: &str| -> Result<u64> {
+        let field = field(name)?;
+        field
+            .as_u64()
+            .or_else(|| field.as_str().and_then(|s| s.parse().ok()))
+            .with_context(|| format!("Tree state field {} is not a number", name))
+    };
+    let string = |name: &str| -> String {
+        value.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+    };
+    Ok(TreeState {
+        network: string("network"),
+        height: number("height")?,
+        hash: string("hash"),
+        time: number("time").unwrap_or_default() as u32,
+        sapling_tree: string("saplingTree"),
+        orchard_tree: string("orchardTree"),
+    })
+}
+
+/// Write a ZeWIF wallet to a new `zcash_client_sqlite` wallet database.
+///
+/// Accounts with a ZIP-32 index are re-created from the wallet's seed, and
+/// accounts carrying a UFVK are imported view-only. The diversified
+/// addresses the source wallet gave out are reserved, and every transaction
+/// with raw data is decrypted with the new accounts' keys and stored, which
+/// records received notes, memos and sent outputs.
+///
+/// The note commitment trees start from `tree_state`, which should be at or
+/// below every account's birthday; accounts born before it are reported.
+/// Without one, the trees start empty at Sapling activation and the wallet
+/// must scan from there, and the accounts' own birthdays are reported as
+/// discarded.
+pub fn write_sqlite_wallet(
+    zewif: &ZewifTop,
+    path: &Path,
+    tree_state: Option<&TreeState>,
+) -> Result<SqliteExportReport> {
+    if path.exists() {
+        bail!("{} already exists; the wallet database must be new", path.display());
+    }
+    let mut wallets = zewif.wallets().values();
+    let (Some(wallet), None) = (wallets.next(), wallets.next()) else {
+        bail!("Exactly one wallet can be written to a wallet database");
+    };
+    let params = match wallet.network() {
+        Network::Main => consensus::Network::MainNetwork,
+        Network::Test => consensus::Network::TestNetwork,
+        Network::Regtest => bail!("Regtest wallets cannot be written to a wallet database"),
+    };
+
+    let mut db = WalletDb::for_path(path, params, SystemClock, OsRng)
+        .with_context(|| format!("Creating {}", path.display()))?;
+    init_wallet_db(&mut db, None).map_err(|e| anyhow!("Initializing wallet database: {:?}", e))?;
+
+    let mut report = SqliteExportReport::default();
+    let birthday = match tree_state {
+        Some(tree_state) => {
+            report.from_tree_state = true;
+            report.birthday = tree_state.height as u32;
+            AccountBirthday::from_treestate(tree_state.clone(), None)
+                .map_err(|e| anyhow!("Invalid tree state: {:?}", e))?
+        }
+        None => {
+            let activation = params
+                .activation_height(NetworkUpgrade::Sapling)
+                .context("Network has no Sapling activation height")?;
+            report.birthday = u32::from(activation);
+            AccountBirthday::from_parts(ChainState::empty(activation - 1, Default::default()), None)
+        }
+    };
+
+    let seed = wallet_seed(wallet)?;
+    let mut accounts: Vec<&Account> = wallet.accounts().values().collect();
+    accounts.sort_by_key(|account| account.zip32_account_id());
+    for account in accounts {
+        let ufvk = match (account.zip32_account_id(), &seed) {
+            (Some(index), Some(seed)) => {
+                let account_index = zip32::AccountId::try_from(index)
+                    .map_err(|_| anyhow!("Invalid ZIP-32 account index {}", index))?;
+                let (_, usk) = db
+                    .import_account_hd(account.name(), seed, account_index, &birthday, None)
+                    .map_err(|e| anyhow!("Creating account {}: {:?}", account.name(), e))?;
+                report.derived_accounts += 1;
+                usk.to_unified_full_viewing_key()
+            }
+            _ => match account_ufvk(account, params) {
+                Some(ufvk) => {
+                    db.import_account_ufvk(account.name(), &ufvk, &birthday, AccountPurpose::ViewOnly, None)
+                        .map_err(|e| anyhow!("Importing account {}: {:?}", account.name(), e))?;
+                    report.imported_accounts += 1;
+                    ufvk
+                }
+                None => {
+                    report.skipped.push(format!(
+                        "account {}: no seed-derived ZIP-32 index or UFVK",
+                        account.name()
+                    ));
+                    continue;
+                }
+            },
+        };
+        if let Some(height) = account.birthday_height() {
+            report.account_birthdays.push((account.name().to_string(), u32::from(height)));
+        }
+        reserve_addresses(&mut db, account, &ufvk, &mut report)?;
+    }
+
+    let mut transactions: Vec<_> = zewif.transactions().values().collect();
+    transactions.sort_by_key(|tx| tx.mined_height().map(u32::from));
+    for tx in transactions {
+        let decoded = match decode_transaction(tx) {
+            Ok(Some(decoded)) => decoded,
+            Ok(None) => {
+                report.skipped.push(format!("transaction {:?}: no raw data", tx.txid()));
+                continue;
+            }
+            Err(e) => {
+                report.skipped.push(format!("transaction {:?}: {}", tx.txid(), e));
+                continue;
+            }
+        };
+        let mined_height = tx.mined_height().map(|height| ChainHeight::from(u32::from(height)));
+        decrypt_and_store_transaction(&params, &mut db, &decoded, mined_height)
+            .map_err(|e| anyhow!("Storing transaction {:?}: {:?}", tx.txid(), e))?;
+        report.transactions += 1;
+    }
+
+    Ok(report)
+}
+
+/// The seed ZIP-32 accounts are derived from: the BIP-39 seed of the
+/// mnemonic, or the legacy HD seed that zcashd used before mnemonics.
+pub(crate) fn wallet_seed(wallet: &ZewifWallet) -> Result<Option<SecretVec<u8>>> {
+    match wallet.seed_material() {
+        Some(SeedMaterial::Bip39Mnemonic(mnemonic)) => {
+            let mnemonic = Mnemonic::<English>::from_phrase(mnemonic.mnemonic())
+                .map_err(|e| anyhow!("Parsing the wallet's BIP-39 mnemonic: {}", e))?;
+            Ok(Some(SecretVec::new(mnemonic.to_seed("").to_vec())))
+        }
+        Some(SeedMaterial::PreBIP39Seed(seed)) => Ok(Some(SecretVec::new(seed.as_ref().to_vec()))),
+        None => Ok(None),
+    }
+}
+
+/// The UFVK that the `sqlite` front-end attached to an account it migrated.
+fn account_ufvk(account: &Account, params: consensus::Network) -> Option<UnifiedFullViewingKey> {
+    account
+        .attachments()
+        .envelopes()
+        .iter()
+        .filter_map(|attachment| attachment.attachment_payload().ok())
+        .find_map(|payload| payload.extract_object_for_predicate::<String>("ufvk").ok())
+        .and_then(|encoded| UnifiedFullViewingKey::decode(&params, &encoded).ok())
+}
+
+/// Reserve the account's diversified unified addresses in the new wallet, so
+/// it recognizes them as given out. Addresses are matched to their
+/// diversifier index through their Sapling receiver.
+fn reserve_addresses(
+    db: &mut WalletDb<rusqlite::Connection, consensus::Network, SystemClock, OsRng>,
+    account: &Account,
+    ufvk: &UnifiedFullViewingKey,
+    report: &mut SqliteExportReport,
+) -> Result<()> {
+    let account_id = db
+        .get_account_for_ufvk(ufvk)
+        .map_err(|e| anyhow!("Looking up account {}: {:?}", account.name(), e))?
+        .context("Account was not created")?
+        .id();
+    let Some(dfvk) = ufvk.sapling() else {
+        return Ok(());
+    };
+    for address in account.addresses().values() {
+        let ProtocolAddress::Unified(_) = address.address() else {
+            continue;
+        };
+        let encoded = address.as_string();
+        let index = unified_receivers(&encoded)?.into_iter().find_map(|receiver| match receiver {
+            zcash_address::unified::Receiver::Sapling(bytes) => sapling::PaymentAddress::from_bytes(&bytes)
+                .and_then(|payment_address| dfvk.decrypt_diversifier(&payment_address)),
+            _ => None,
+        });
+        let Some((index, zip32::Scope::External)) = index else {
+            report.skipped.push(format!("address {}: not derived from the account's keys", encoded));
+            continue;
+        };
+        // The default address was reserved when the account was created.
+        if index == zip32::DiversifierIndex::default() {
+            continue;
+        }
+        let reserved: Option<KeysUnifiedAddress> = db
+            .get_address_for_index(account_id, index, UnifiedAddressRequest::AllAvailableKeys)
+            .map_err(|e| anyhow!("Reserving address {}: {:?}", encoded, e))?;
+        if reserved.is_some() {
+            report.addresses += 1;
+        }
+    }
+    Ok(())
+}
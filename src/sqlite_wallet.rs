@@ -0,0 +1,881 @@
+use std::{collections::HashSet, fmt::Write, path::Path};
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use rusqlite::{Connection, OpenFlags, Row};
+use zewif::{
+    Account, Address, BlockHeight, Network, ProtocolAddress, Transaction, TxId, UnifiedAddress,
+    ZewifTop, ZewifWallet,
+};
+
+use crate::note_decryption::{Pool, ZMIGRATE_VENDOR};
+
+/// How a `zcash_client_sqlite` account's keys were obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqliteAccountKind {
+    /// Derived from a seed, identified by its fingerprint, at a ZIP-32
+    /// account index.
+    Derived,
+    /// Imported from a UFVK or UIVK.
+    Imported,
+}
+
+/// A row of the `accounts` table.
+#[derive(Debug, Clone)]
+pub struct SqliteAccount {
+    id: i64,
+    name: Option<String>,
+    uuid: Option<Vec<u8>>,
+    kind: SqliteAccountKind,
+    seed_fingerprint: Option<Vec<u8>>,
+    hd_account_index: Option<u32>,
+    ufvk: Option<String>,
+    uivk: String,
+    birthday_height: u32,
+    recover_until_height: Option<u32>,
+    has_spend_key: bool,
+}
+
+impl SqliteAccount {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn kind(&self) -> SqliteAccountKind {
+        self.kind
+    }
+
+    /// The ZIP-32 fingerprint of the seed a derived account comes from.
+    pub fn seed_fingerprint(&self) -> Option<&[u8]> {
+        self.seed_fingerprint.as_deref()
+    }
+
+    pub fn hd_account_index(&self) -> Option<u32> {
+        self.hd_account_index
+    }
+
+    /// The account's encoded unified full viewing key. Accounts imported from
+    /// a UIVK have none.
+    pub fn ufvk(&self) -> Option<&str> {
+        self.ufvk.as_deref()
+    }
+
+    pub fn uivk(&self) -> &str {
+        &self.uivk
+    }
+
+    pub fn birthday_height(&self) -> u32 {
+        self.birthday_height
+    }
+
+    /// Whether the wallet can spend from the account, given its seed.
+    pub fn has_spend_key(&self) -> bool {
+        self.has_spend_key
+    }
+}
+
+/// A row of the `addresses` table.
+#[derive(Debug, Clone)]
+pub struct SqliteAddress {
+    account_id: i64,
+    diversifier_index_be: Vec<u8>,
+    address: String,
+    transparent_address: Option<String>,
+}
+
+impl SqliteAddress {
+    pub fn account_id(&self) -> i64 {
+        self.account_id
+    }
+
+    /// The diversifier index, big-endian as the wallet stores it.
+    pub fn diversifier_index_be(&self) -> &[u8] {
+        &self.diversifier_index_be
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The encoded transparent receiver of the address, if it has one.
+    pub fn transparent_address(&self) -> Option<&str> {
+        self.transparent_address.as_deref()
+    }
+}
+
+/// A row of the `transactions` table.
+#[derive(Debug, Clone)]
+pub struct SqliteTransaction {
+    id: i64,
+    txid: [u8; 32],
+    created: Option<String>,
+    mined_height: Option<u32>,
+    tx_index: Option<u32>,
+    expiry_height: Option<u32>,
+    raw: Option<Vec<u8>>,
+    fee: Option<u64>,
+}
+
+impl SqliteTransaction {
+    pub fn txid(&self) -> TxId {
+        TxId::from_bytes(self.txid)
+    }
+
+    pub fn mined_height(&self) -> Option<u32> {
+        self.mined_height
+    }
+
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
+
+    pub fn fee(&self) -> Option<u64> {
+        self.fee
+    }
+}
+
+/// A row of the `sapling_received_notes` or `orchard_received_notes` table.
+#[derive(Debug, Clone)]
+pub struct SqliteReceivedNote {
+    pool: Pool,
+    tx: i64,
+    index: u32,
+    account_id: i64,
+    value: u64,
+    is_change: bool,
+    memo: Option<Vec<u8>>,
+    nullifier: Option<Vec<u8>>,
+    commitment_tree_position: Option<u64>,
+}
+
+impl SqliteReceivedNote {
+    pub fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    /// The output (Sapling) or action (Orchard) index in its transaction.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn account_id(&self) -> i64 {
+        self.account_id
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn is_change(&self) -> bool {
+        self.is_change
+    }
+
+    pub fn commitment_tree_position(&self) -> Option<u64> {
+        self.commitment_tree_position
+    }
+}
+
+/// The pool of a `sent_notes` output, by the code `zcash_client_sqlite`
+/// stores for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SentPool {
+    Transparent,
+    Sapling,
+    Orchard,
+}
+
+impl SentPool {
+    fn from_code(code: i64) -> Result<Self> {
+        match code {
+            0 => Ok(SentPool::Transparent),
+            2 => Ok(SentPool::Sapling),
+            3 => Ok(SentPool::Orchard),
+            _ => bail!("Unknown output pool code {}", code),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SentPool::Transparent => "transparent",
+            SentPool::Sapling => "sapling",
+            SentPool::Orchard => "orchard",
+        }
+    }
+}
+
+/// A row of the `sent_notes` table.
+#[derive(Debug, Clone)]
+pub struct SqliteSentNote {
+    tx: i64,
+    pool: SentPool,
+    index: u32,
+    from_account_id: i64,
+    to_address: Option<String>,
+    to_account_id: Option<i64>,
+    value: u64,
+    memo: Option<Vec<u8>>,
+}
+
+impl SqliteSentNote {
+    pub fn pool(&self) -> SentPool {
+        self.pool
+    }
+
+    pub fn from_account_id(&self) -> i64 {
+        self.from_account_id
+    }
+
+    /// The encoded recipient, or `None` for an internal transfer to
+    /// `to_account_id`.
+    pub fn to_address(&self) -> Option<&str> {
+        self.to_address.as_deref()
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+/// One subtree of a pool's note commitment shardtree.
+#[derive(Debug, Clone)]
+pub struct SqliteShard {
+    index: u64,
+    subtree_end_height: Option<u32>,
+    root_hash: Option<Vec<u8>>,
+    data: Vec<u8>,
+    contains_marked: bool,
+}
+
+/// A pool's note commitment tree state, as `shardtree` persists it: the
+/// subtree shards, the cap above them, and the checkpoints the wallet can
+/// rewind to. Marked leaves are the witnesses of the wallet's own notes.
+#[derive(Debug, Clone)]
+pub struct SqliteShardTree {
+    pool: Pool,
+    shards: Vec<SqliteShard>,
+    cap: Option<Vec<u8>>,
+    checkpoints: Vec<(u32, Option<u64>)>,
+}
+
+impl SqliteShardTree {
+    pub fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The shards holding witnesses for the wallet's own notes.
+    pub fn marked_shard_count(&self) -> usize {
+        self.shards.iter().filter(|shard| shard.contains_marked).count()
+    }
+
+    /// The checkpoints as `(block height, tree position)`; the position is
+    /// `None` while the tree is empty.
+    pub fn checkpoints(&self) -> &[(u32, Option<u64>)] {
+        &self.checkpoints
+    }
+
+    fn to_envelope(&self) -> Envelope {
+        let mut envelope = Envelope::new("ShardTree").add_assertion("pool", self.pool.to_string());
+        for shard in &self.shards {
+            let mut shard_envelope = Envelope::new(shard.index)
+                .add_assertion("shardData", hex::encode(&shard.data))
+                .add_assertion("containsMarked", shard.contains_marked);
+            if let Some(height) = shard.subtree_end_height {
+                shard_envelope = shard_envelope.add_assertion("subtreeEndHeight", height);
+            }
+            if let Some(root_hash) = &shard.root_hash {
+                shard_envelope = shard_envelope.add_assertion("rootHash", hex::encode(root_hash));
+            }
+            envelope = envelope.add_assertion("shard", shard_envelope);
+        }
+        if let Some(cap) = &self.cap {
+            envelope = envelope.add_assertion("cap", hex::encode(cap));
+        }
+        for (height, position) in &self.checkpoints {
+            let mut checkpoint = Envelope::new(*height);
+            if let Some(position) = position {
+                checkpoint = checkpoint.add_assertion("position", *position);
+            }
+            envelope = envelope.add_assertion("checkpoint", checkpoint);
+        }
+        envelope
+    }
+}
+
+/// A `zcash_client_sqlite` wallet database, read offline.
+#[derive(Debug, Clone)]
+pub struct SqliteWallet {
+    migrations: usize,
+    accounts: Vec<SqliteAccount>,
+    addresses: Vec<SqliteAddress>,
+    transactions: Vec<SqliteTransaction>,
+    received_notes: Vec<SqliteReceivedNote>,
+    sent_notes: Vec<SqliteSentNote>,
+    trees: Vec<SqliteShardTree>,
+}
+
+impl SqliteWallet {
+    /// Read a wallet database. It is opened read-only, so it is never
+    /// migrated or otherwise modified.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Opening {}", path.display()))?;
+        if !table_exists(&conn, "accounts")? {
+            bail!("{} is not a zcash_client_sqlite wallet database", path.display());
+        }
+
+        let migrations = if table_exists(&conn, "schemer_migrations")? {
+            conn.query_row("SELECT COUNT(*) FROM schemer_migrations", [], |row| row.get(0))?
+        } else {
+            0
+        };
+
+        let mut trees = Vec::new();
+        for pool in [Pool::Sapling, Pool::Orchard] {
+            if let Some(tree) = read_shard_tree(&conn, pool)? {
+                trees.push(tree);
+            }
+        }
+
+        Ok(Self {
+            migrations,
+            accounts: read_accounts(&conn).context("Reading accounts")?,
+            addresses: read_addresses(&conn).context("Reading addresses")?,
+            transactions: read_transactions(&conn).context("Reading transactions")?,
+            received_notes: read_received_notes(&conn).context("Reading received notes")?,
+            sent_notes: read_sent_notes(&conn).context("Reading sent notes")?,
+            trees,
+        })
+    }
+
+    pub fn accounts(&self) -> &[SqliteAccount] {
+        &self.accounts
+    }
+
+    pub fn addresses(&self) -> &[SqliteAddress] {
+        &self.addresses
+    }
+
+    pub fn transactions(&self) -> &[SqliteTransaction] {
+        &self.transactions
+    }
+
+    pub fn received_notes(&self) -> &[SqliteReceivedNote] {
+        &self.received_notes
+    }
+
+    pub fn sent_notes(&self) -> &[SqliteSentNote] {
+        &self.sent_notes
+    }
+
+    pub fn trees(&self) -> &[SqliteShardTree] {
+        &self.trees
+    }
+
+    /// The network of the wallet, from the encoding of its accounts' UIVKs.
+    /// The database itself does not record it.
+    pub fn network(&self) -> Result<Network> {
+        let uivk = self
+            .accounts
+            .first()
+            .map(|account| account.uivk.as_str())
+            .context("The wallet has no accounts to infer its network from")?;
+        if uivk.starts_with("uivkregtest1") {
+            Ok(Network::Regtest)
+        } else if uivk.starts_with("uivktest1") {
+            Ok(Network::Test)
+        } else if uivk.starts_with("uivk1") {
+            Ok(Network::Main)
+        } else {
+            bail!("Unrecognized UIVK encoding: {}…", &uivk[..uivk.len().min(12)])
+        }
+    }
+
+    /// Migrate the wallet to ZeWIF.
+    ///
+    /// `zcash_client_sqlite` never stores seeds, so accounts carry their
+    /// viewing keys and seed fingerprints as attachments; a derived account is
+    /// restored by supplying its seed. Received and sent notes are attached to
+    /// their transactions, and each pool's shardtree to the wallet.
+    pub fn to_zewif(&self) -> Result<ZewifTop> {
+        let network = self.network()?;
+        let mut zewif = ZewifTop::new();
+        let mut zewif_wallet = ZewifWallet::new(network);
+
+        for tx in &self.transactions {
+            let mut transaction = Transaction::new(tx.txid());
+            if let Some(raw) = &tx.raw {
+                transaction.set_raw(raw.clone().into());
+            }
+            if let Some(height) = tx.mined_height {
+                transaction.set_mined_height(BlockHeight::from(height));
+            }
+            let mut envelope = Envelope::new("SqliteTransaction");
+            if let Some(created) = &tx.created {
+                envelope = envelope.add_assertion("created", created.clone());
+            }
+            if let Some(tx_index) = tx.tx_index {
+                envelope = envelope.add_assertion("txIndex", tx_index);
+            }
+            if let Some(expiry_height) = tx.expiry_height {
+                envelope = envelope.add_assertion("expiryHeight", expiry_height);
+            }
+            if let Some(fee) = tx.fee {
+                envelope = envelope.add_assertion("fee", fee);
+            }
+            for note in self.received_notes.iter().filter(|note| note.tx == tx.id) {
+                envelope = envelope.add_assertion("receivedNote", note.to_envelope());
+            }
+            for note in self.sent_notes.iter().filter(|note| note.tx == tx.id) {
+                envelope = envelope.add_assertion("sentNote", note.to_envelope());
+            }
+            transaction.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            zewif.transactions_mut().insert(tx.txid(), transaction);
+        }
+
+        for sqlite_account in &self.accounts {
+            let mut account = Account::new();
+            account.set_name(
+                sqlite_account
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Account #{}", sqlite_account.id)),
+            );
+            if let Some(index) = sqlite_account.hd_account_index {
+                account.set_zip32_account_id(index);
+            }
+            account.set_birthday_height(BlockHeight::from(sqlite_account.birthday_height));
+
+            for address in self.addresses.iter().filter(|a| a.account_id == sqlite_account.id) {
+                let mut zewif_address = Address::new(ProtocolAddress::Unified(UnifiedAddress::new(
+                    address.address.clone(),
+                )));
+                zewif_address.add_attachment(
+                    Envelope::new("DiversifierIndex")
+                        .add_assertion("indexBE", hex::encode(&address.diversifier_index_be)),
+                    ZMIGRATE_VENDOR,
+                    None,
+                );
+                account.add_address(zewif_address);
+            }
+
+            for txid in self.relevant_transactions(sqlite_account.id) {
+                account.add_relevant_transaction(txid);
+            }
+
+            account.add_attachment(sqlite_account.to_envelope(), ZMIGRATE_VENDOR, None);
+            zewif_wallet.add_account(account);
+        }
+
+        for tree in &self.trees {
+            zewif_wallet.add_attachment(tree.to_envelope(), ZMIGRATE_VENDOR, None);
+        }
+
+        zewif.add_wallet(zewif_wallet);
+        Ok(zewif)
+    }
+
+    /// The transactions in which an account received or sent a note.
+    fn relevant_transactions(&self, account_id: i64) -> Vec<TxId> {
+        let tx_ids: HashSet<i64> = self
+            .received_notes
+            .iter()
+            .filter(|note| note.account_id == account_id)
+            .map(|note| note.tx)
+            .chain(
+                self.sent_notes
+                    .iter()
+                    .filter(|note| note.from_account_id == account_id)
+                    .map(|note| note.tx),
+            )
+            .collect();
+        self.transactions
+            .iter()
+            .filter(|tx| tx_ids.contains(&tx.id))
+            .map(SqliteTransaction::txid)
+            .collect()
+    }
+
+    /// Append the database contents to a migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- Schema migrations applied: {}", self.migrations)?;
+        writeln!(report, "- Accounts: {}", self.accounts.len())?;
+        for account in &self.accounts {
+            let key = match (account.kind, account.ufvk.is_some()) {
+                (SqliteAccountKind::Derived, _) => format!(
+                    "derived, ZIP-32 account {}",
+                    account.hd_account_index.map_or("?".to_string(), |i| i.to_string())
+                ),
+                (SqliteAccountKind::Imported, true) => "imported UFVK".to_string(),
+                (SqliteAccountKind::Imported, false) => "imported UIVK (incoming only)".to_string(),
+            };
+            writeln!(
+                report,
+                "  * {}: {}, birthday {}",
+                account.name.as_deref().unwrap_or("(unnamed)"),
+                key,
+                account.birthday_height
+            )?;
+        }
+        writeln!(report, "- Addresses: {}", self.addresses.len())?;
+        writeln!(report, "- Transactions: {}", self.transactions.len())?;
+        for pool in [Pool::Sapling, Pool::Orchard] {
+            let notes: Vec<_> = self.received_notes.iter().filter(|n| n.pool == pool).collect();
+            writeln!(
+                report,
+                "- {} received notes: {} ({} zatoshis)",
+                pool,
+                notes.len(),
+                notes.iter().map(|n| n.value).sum::<u64>()
+            )?;
+        }
+        writeln!(report, "- Sent notes: {}", self.sent_notes.len())?;
+        for tree in &self.trees {
+            writeln!(
+                report,
+                "- {} shardtree: {} shards ({} with witnesses), {} checkpoints",
+                tree.pool,
+                tree.shard_count(),
+                tree.marked_shard_count(),
+                tree.checkpoints.len()
+            )?;
+        }
+        writeln!(
+            report,
+            "- Seed material: not stored by zcash_client_sqlite; derived accounts are restored from their seed"
+        )?;
+        Ok(())
+    }
+}
+
+impl SqliteAccount {
+    fn to_envelope(&self) -> Envelope {
+        let mut envelope = Envelope::new("SqliteAccount")
+            .add_assertion("id", self.id)
+            .add_assertion("uivk", self.uivk.clone())
+            .add_assertion("hasSpendKey", self.has_spend_key);
+        if let Some(ufvk) = &self.ufvk {
+            envelope = envelope.add_assertion("ufvk", ufvk.clone());
+        }
+        if let Some(uuid) = &self.uuid {
+            envelope = envelope.add_assertion("uuid", hex::encode(uuid));
+        }
+        if let Some(fingerprint) = &self.seed_fingerprint {
+            envelope = envelope.add_assertion("seedFingerprint", hex::encode(fingerprint));
+        }
+        if let Some(height) = self.recover_until_height {
+            envelope = envelope.add_assertion("recoverUntilHeight", height);
+        }
+        envelope
+    }
+}
+
+impl SqliteReceivedNote {
+    fn to_envelope(&self) -> Envelope {
+        let mut envelope = Envelope::new("ReceivedNote")
+            .add_assertion("pool", self.pool.to_string())
+            .add_assertion("index", self.index)
+            .add_assertion("accountId", self.account_id)
+            .add_assertion("value", self.value)
+            .add_assertion("isChange", self.is_change);
+        if let Some(nullifier) = &self.nullifier {
+            envelope = envelope.add_assertion("nullifier", hex::encode(nullifier));
+        }
+        if let Some(position) = self.commitment_tree_position {
+            envelope = envelope.add_assertion("commitmentTreePosition", position);
+        }
+        if let Some(memo) = &self.memo {
+            envelope = envelope.add_assertion("memo", hex::encode(memo));
+        }
+        envelope
+    }
+}
+
+impl SqliteSentNote {
+    fn to_envelope(&self) -> Envelope {
+        let mut envelope = Envelope::new("SentNote")
+            .add_assertion("pool", self.pool.name())
+            .add_assertion("index", self.index)
+            .add_assertion("fromAccountId", self.from_account_id)
+            .add_assertion("value", self.value);
+        if let Some(to_address) = &self.to_address {
+            envelope = envelope.add_assertion("toAddress", to_address.clone());
+        }
+        if let Some(to_account_id) = self.to_account_id {
+            envelope = envelope.add_assertion("toAccountId", to_account_id);
+        }
+        if let Some(memo) = &self.memo {
+            envelope = envelope.add_assertion("memo", hex::encode(memo));
+        }
+        envelope
+    }
+}
+
+pub(crate) fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// The columns of a table. Columns have been added and renamed across
+/// `zcash_client_sqlite` schema versions, so queries select each optional
+/// column only if it exists.
+pub(crate) fn columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut statement = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = statement.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+/// The first of `candidates` that is a column of the table, or `NULL`.
+pub(crate) fn column_or_null(columns: &HashSet<String>, candidates: &[&str]) -> String {
+    candidates
+        .iter()
+        .find(|candidate| columns.contains(**candidate))
+        .map_or_else(|| "NULL".to_string(), |column| column.to_string())
+}
+
+pub(crate) fn get_u32(row: &Row, index: usize) -> rusqlite::Result<Option<u32>> {
+    row.get::<_, Option<i64>>(index).map(|value| value.map(|v| v as u32))
+}
+
+fn read_accounts(conn: &Connection) -> Result<Vec<SqliteAccount>> {
+    let cols = columns(conn, "accounts")?;
+    let query = format!(
+        "SELECT id, {}, {}, account_kind, hd_seed_fingerprint, hd_account_index, ufvk, uivk, \
+         birthday_height, recover_until_height, {} FROM accounts ORDER BY id",
+        column_or_null(&cols, &["name"]),
+        column_or_null(&cols, &["uuid"]),
+        column_or_null(&cols, &["has_spend_key"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            SqliteAccount {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                uuid: row.get(2)?,
+                kind: SqliteAccountKind::Derived,
+                seed_fingerprint: row.get(4)?,
+                hd_account_index: get_u32(row, 5)?,
+                ufvk: row.get(6)?,
+                uivk: row.get(7)?,
+                birthday_height: row.get::<_, i64>(8)? as u32,
+                recover_until_height: get_u32(row, 9)?,
+                // Before `has_spend_key` was added, every account could spend.
+                has_spend_key: row.get::<_, Option<bool>>(10)?.unwrap_or(true),
+            },
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+    rows.map(|row| {
+        let (mut account, kind) = row?;
+        account.kind = match kind {
+            0 => SqliteAccountKind::Derived,
+            1 => SqliteAccountKind::Imported,
+            other => bail!("Unknown account kind {} for account {}", other, account.id),
+        };
+        Ok(account)
+    })
+    .collect()
+}
+
+fn read_addresses(conn: &Connection) -> Result<Vec<SqliteAddress>> {
+    let cols = columns(conn, "addresses")?;
+    let query = format!(
+        "SELECT account_id, diversifier_index_be, address, {} FROM addresses \
+         ORDER BY account_id, diversifier_index_be",
+        column_or_null(&cols, &["cached_transparent_receiver_address"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok(SqliteAddress {
+            account_id: row.get(0)?,
+            diversifier_index_be: row.get(1)?,
+            address: row.get(2)?,
+            transparent_address: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_transactions(conn: &Connection) -> Result<Vec<SqliteTransaction>> {
+    let cols = columns(conn, "transactions")?;
+    let query = format!(
+        "SELECT id_tx, txid, created, {}, tx_index, expiry_height, raw, fee FROM transactions \
+         ORDER BY id_tx",
+        column_or_null(&cols, &["mined_height", "block"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Vec<u8>>(1)?,
+            row.get(2)?,
+            get_u32(row, 3)?,
+            get_u32(row, 4)?,
+            get_u32(row, 5)?,
+            row.get(6)?,
+            row.get::<_, Option<i64>>(7)?,
+        ))
+    })?;
+    rows.map(|row| {
+        let (id, txid, created, mined_height, tx_index, expiry_height, raw, fee) = row?;
+        let txid: [u8; 32] = txid
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Transaction {} has a malformed txid", id))?;
+        Ok(SqliteTransaction {
+            id,
+            txid,
+            created,
+            mined_height,
+            tx_index,
+            expiry_height,
+            raw,
+            fee: fee.map(|fee| fee as u64),
+        })
+    })
+    .collect()
+}
+
+fn read_received_notes(conn: &Connection) -> Result<Vec<SqliteReceivedNote>> {
+    let mut notes = Vec::new();
+    for (pool, table, index_column) in [
+        (Pool::Sapling, "sapling_received_notes", "output_index"),
+        (Pool::Orchard, "orchard_received_notes", "action_index"),
+    ] {
+        if !table_exists(conn, table)? {
+            continue;
+        }
+        let cols = columns(conn, table)?;
+        let query = format!(
+            "SELECT {}, {}, account_id, value, is_change, memo, nf, {} FROM {} ORDER BY id",
+            column_or_null(&cols, &["transaction_id", "tx"]),
+            index_column,
+            column_or_null(&cols, &["commitment_tree_position"]),
+            table,
+        );
+        let mut statement = conn.prepare(&query)?;
+        let rows = statement.query_map([], |row| {
+            Ok(SqliteReceivedNote {
+                pool,
+                tx: row.get(0)?,
+                index: row.get::<_, i64>(1)? as u32,
+                account_id: row.get(2)?,
+                value: row.get::<_, i64>(3)? as u64,
+                is_change: row.get(4)?,
+                memo: row.get(5)?,
+                nullifier: row.get(6)?,
+                commitment_tree_position: row.get::<_, Option<i64>>(7)?.map(|p| p as u64),
+            })
+        })?;
+        for row in rows {
+            notes.push(row.with_context(|| format!("Reading {}", table))?);
+        }
+    }
+    Ok(notes)
+}
+
+fn read_sent_notes(conn: &Connection) -> Result<Vec<SqliteSentNote>> {
+    if !table_exists(conn, "sent_notes")? {
+        return Ok(Vec::new());
+    }
+    let cols = columns(conn, "sent_notes")?;
+    let query = format!(
+        "SELECT {}, output_pool, output_index, from_account_id, to_address, {}, value, memo \
+         FROM sent_notes ORDER BY id",
+        column_or_null(&cols, &["transaction_id", "tx"]),
+        column_or_null(&cols, &["to_account_id"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get::<_, i64>(6)?,
+            row.get(7)?,
+        ))
+    })?;
+    rows.map(|row| {
+        let (tx, pool, index, from_account_id, to_address, to_account_id, value, memo) = row?;
+        Ok(SqliteSentNote {
+            tx,
+            pool: SentPool::from_code(pool)?,
+            index: index as u32,
+            from_account_id,
+            to_address,
+            to_account_id,
+            value: value as u64,
+            memo,
+        })
+    })
+    .collect()
+}
+
+fn read_shard_tree(conn: &Connection, pool: Pool) -> Result<Option<SqliteShardTree>> {
+    let prefix = match pool {
+        Pool::Sapling => "sapling",
+        Pool::Orchard => "orchard",
+    };
+    let shards_table = format!("{}_tree_shards", prefix);
+    if !table_exists(conn, &shards_table)? {
+        return Ok(None);
+    }
+
+    let mut statement = conn.prepare(&format!(
+        "SELECT shard_index, subtree_end_height, root_hash, shard_data, contains_marked \
+         FROM {} ORDER BY shard_index",
+        shards_table
+    ))?;
+    let shards = statement
+        .query_map([], |row| {
+            Ok(SqliteShard {
+                index: row.get::<_, i64>(0)? as u64,
+                subtree_end_height: get_u32(row, 1)?,
+                root_hash: row.get(2)?,
+                data: row.get::<_, Option<Vec<u8>>>(3)?.unwrap_or_default(),
+                contains_marked: row.get::<_, Option<bool>>(4)?.unwrap_or(false),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()
+        .with_context(|| format!("Reading {}", shards_table))?;
+
+    let cap_table = format!("{}_tree_cap", prefix);
+    let cap = if table_exists(conn, &cap_table)? {
+        conn.prepare(&format!("SELECT cap_data FROM {} WHERE cap_id = 0", cap_table))?
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .next()
+            .transpose()?
+    } else {
+        None
+    };
+
+    let checkpoints_table = format!("{}_tree_checkpoints", prefix);
+    let checkpoints = if table_exists(conn, &checkpoints_table)? {
+        conn.prepare(&format!(
+            "SELECT checkpoint_id, position FROM {} ORDER BY checkpoint_id",
+            checkpoints_table
+        ))?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)? as u32, row.get::<_, Option<i64>>(1)?.map(|p| p as u64)))
+        })?
+        .collect::<rusqlite::Result<_>>()?
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(SqliteShardTree { pool, shards, cap, checkpoints }))
+}
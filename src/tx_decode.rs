@@ -0,0 +1,20 @@
+use anyhow::{Context, Result};
+use zcash_primitives::{consensus::BranchId, transaction::Transaction as ChainTransaction};
+
+/// Decode the raw consensus bytes of a ZeWIF transaction.
+///
+/// zcashd stores every wallet transaction in full, so the shielded ciphertexts,
+/// nullifiers and transparent inputs needed by the offline analysis passes are
+/// available without chain access. Returns `None` if the transaction carries no
+/// raw bytes.
+///
+/// The branch ID is only used for v4 and earlier transactions, where it affects
+/// signature hashing but not parsing; v5 transactions carry their own branch ID.
+pub fn decode_transaction(tx: &zewif::Transaction) -> Result<Option<ChainTransaction>> {
+    let Some(raw) = tx.raw() else {
+        return Ok(None);
+    };
+    let decoded = ChainTransaction::read(raw.as_ref(), BranchId::Canopy)
+        .with_context(|| format!("Decoding raw transaction {:?}", tx.txid()))?;
+    Ok(Some(decoded))
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context, Result, bail};
+use orchard::keys::{
+    FullViewingKey as OrchardFullViewingKey, OutgoingViewingKey as OrchardOvk,
+    PreparedIncomingViewingKey as OrchardPreparedIvk,
+};
+use sapling::{
+    SaplingIvk,
+    keys::{OutgoingViewingKey as SaplingOvk, PreparedIncomingViewingKey as SaplingPreparedIvk},
+    zip32::DiversifiableFullViewingKey,
+};
+use zcash_address::unified::{Container, Encoding, Fvk, Ufvk};
+use zewif::Account;
+use zewif_zcashd::ZcashdWallet;
+use zip32::Scope;
+
+const SCOPES: [(Scope, &str); 2] = [(Scope::External, "external"), (Scope::Internal, "internal")];
+
+/// Identifies which part of the wallet a viewing key came from, so that
+/// decrypted notes can be attributed back to an address or account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeySource {
+    /// A legacy Sapling address stored in `sapzaddr` records.
+    SaplingAddress(String),
+    /// A unified account, identified by the hex key of its UFVK record and,
+    /// where the account metadata is present, its ZIP-32 account index.
+    UnifiedAccount {
+        ufvk_id: String,
+        account_id: Option<u32>,
+        scope: &'static str,
+    },
+}
+
+impl KeySource {
+    /// Whether `account` is the migrated account that holds this key.
+    pub fn owns(&self, account: &Account) -> bool {
+        match self {
+            KeySource::SaplingAddress(address) => {
+                account.addresses().values().any(|a| a.as_string() == *address)
+            }
+            KeySource::UnifiedAccount { account_id, .. } => {
+                account_id.is_some() && account.zip32_account_id() == *account_id
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySource::SaplingAddress(address) => write!(f, "sapling address {}", address),
+            KeySource::UnifiedAccount { ufvk_id, account_id: Some(account_id), scope } => {
+                write!(f, "unified account {} #{} ({})", ufvk_id, account_id, scope)
+            }
+            KeySource::UnifiedAccount { ufvk_id, account_id: None, scope } => {
+                write!(f, "unified account {} ({})", ufvk_id, scope)
+            }
+        }
+    }
+}
+
+/// The shielded full viewing keys of one zcashd unified account.
+pub struct UnifiedAccountFvks {
+    ufvk_id: String,
+    account_id: Option<u32>,
+    sapling: Option<DiversifiableFullViewingKey>,
+    orchard: Option<OrchardFullViewingKey>,
+}
+
+impl UnifiedAccountFvks {
+    /// Decode the UFVK of every unified account in the wallet.
+    pub fn from_zcashd_wallet(wallet: &ZcashdWallet) -> Result<Vec<Self>> {
+        let Some(unified_accounts) = wallet.unified_accounts() else {
+            return Ok(Vec::new());
+        };
+        let mut result = Vec::new();
+        for (ufvk_fingerprint, encoded) in unified_accounts.full_viewing_keys() {
+            let ufvk_id = hex::encode(ufvk_fingerprint.as_ref());
+            let account_id = unified_accounts
+                .account_metadata()
+                .values()
+                .find(|metadata| metadata.ufvk_fingerprint() == ufvk_fingerprint)
+                .map(|metadata| metadata.account_id());
+            let (_, ufvk) =
+                Ufvk::decode(encoded).with_context(|| format!("Decoding UFVK {}", ufvk_id))?;
+            let mut fvks = Self { ufvk_id, account_id, sapling: None, orchard: None };
+            for item in ufvk.items() {
+                match item {
+                    Fvk::Sapling(bytes) => {
+                        fvks.sapling = Some(
+                            DiversifiableFullViewingKey::from_bytes(&bytes).with_context(|| {
+                                format!("Invalid Sapling FVK in UFVK {}", fvks.ufvk_id)
+                            })?,
+                        );
+                    }
+                    Fvk::Orchard(bytes) => {
+                        fvks.orchard = Some(
+                            Option::from(OrchardFullViewingKey::from_bytes(&bytes)).with_context(
+                                || format!("Invalid Orchard FVK in UFVK {}", fvks.ufvk_id),
+                            )?,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            result.push(fvks);
+        }
+        result.sort_by(|a, b| a.ufvk_id.cmp(&b.ufvk_id));
+        Ok(result)
+    }
+
+    pub fn ufvk_id(&self) -> &str {
+        &self.ufvk_id
+    }
+
+    /// The ZIP-32 account index, if the wallet stores metadata for this UFVK.
+    pub fn account_id(&self) -> Option<u32> {
+        self.account_id
+    }
+
+    pub fn sapling(&self) -> Option<&DiversifiableFullViewingKey> {
+        self.sapling.as_ref()
+    }
+
+    pub fn orchard(&self) -> Option<&OrchardFullViewingKey> {
+        self.orchard.as_ref()
+    }
+
+    /// Whether `address` is one of this account's Sapling addresses, in either scope.
+    pub fn owns_sapling(&self, address: &sapling::PaymentAddress) -> bool {
+        self.sapling.as_ref().is_some_and(|dfvk| dfvk.decrypt_diversifier(address).is_some())
+    }
+
+    /// Whether `address` is one of this account's Orchard addresses, in either scope.
+    pub fn owns_orchard(&self, address: &orchard::Address) -> bool {
+        self.orchard.as_ref().is_some_and(|fvk| fvk.scope_for_address(address).is_some())
+    }
+
+    fn source(&self, scope: &'static str) -> KeySource {
+        KeySource::UnifiedAccount {
+            ufvk_id: self.ufvk_id.clone(),
+            account_id: self.account_id,
+            scope,
+        }
+    }
+}
+
+/// Incoming viewing keys recovered from a zcashd wallet, prepared for trial
+/// decryption.
+pub struct WalletIvks {
+    sapling: Vec<(KeySource, SaplingPreparedIvk)>,
+    orchard: Vec<(KeySource, OrchardPreparedIvk)>,
+}
+
+impl WalletIvks {
+    /// Collect every IVK the wallet holds: the IVKs of legacy Sapling addresses,
+    /// and the external and internal IVKs of each unified account's UFVK.
+    pub fn from_zcashd_wallet(wallet: &ZcashdWallet) -> Result<Self> {
+        let mut sapling = Vec::new();
+        let mut orchard = Vec::new();
+
+        for (address, ivk) in wallet.sapling_z_addresses() {
+            let address = address.to_string(wallet.network());
+            let ivk = sapling_ivk_from_bytes(ivk.as_ref())
+                .with_context(|| format!("Decoding IVK of {}", address))?;
+            sapling.push((KeySource::SaplingAddress(address), SaplingPreparedIvk::new(&ivk)));
+        }
+
+        for fvks in UnifiedAccountFvks::from_zcashd_wallet(wallet)? {
+            for (scope, name) in SCOPES {
+                if let Some(dfvk) = fvks.sapling() {
+                    sapling.push((fvks.source(name), SaplingPreparedIvk::new(&dfvk.to_ivk(scope))));
+                }
+                if let Some(fvk) = fvks.orchard() {
+                    orchard.push((fvks.source(name), OrchardPreparedIvk::new(&fvk.to_ivk(scope))));
+                }
+            }
+        }
+
+        Ok(Self { sapling, orchard })
+    }
+
+    pub fn sapling(&self) -> &[(KeySource, SaplingPreparedIvk)] {
+        &self.sapling
+    }
+
+    pub fn orchard(&self) -> &[(KeySource, OrchardPreparedIvk)] {
+        &self.orchard
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sapling.is_empty() && self.orchard.is_empty()
+    }
+}
+
+/// Outgoing viewing keys recovered from a zcashd wallet, used to recover the
+/// recipients of the wallet's own shielded sends.
+pub struct WalletOvks {
+    sapling: Vec<(KeySource, SaplingOvk)>,
+    orchard: Vec<(KeySource, OrchardOvk)>,
+}
+
+impl WalletOvks {
+    /// Collect the OVK of each legacy Sapling spending key, and the external
+    /// and internal OVKs of each unified account's UFVK.
+    ///
+    /// Sends from transparent addresses use an OVK derived from the legacy HD
+    /// seed, which zcashd does not store as a key; those outputs cannot be
+    /// recovered here.
+    pub fn from_zcashd_wallet(wallet: &ZcashdWallet) -> Result<Self> {
+        let mut sapling = Vec::new();
+        let mut orchard = Vec::new();
+
+        for (address, ivk) in wallet.sapling_z_addresses() {
+            let Some(key) = wallet.sapling_keys().get(ivk) else {
+                continue;
+            };
+            let Ok(ovk) = <[u8; 32]>::try_from(key.key().expsk().ovk().as_ref()) else {
+                bail!("Sapling OVK must be 32 bytes");
+            };
+            sapling.push((
+                KeySource::SaplingAddress(address.to_string(wallet.network())),
+                SaplingOvk(ovk),
+            ));
+        }
+
+        for fvks in UnifiedAccountFvks::from_zcashd_wallet(wallet)? {
+            for (scope, name) in SCOPES {
+                if let Some(dfvk) = fvks.sapling() {
+                    sapling.push((fvks.source(name), dfvk.to_ovk(scope)));
+                }
+                if let Some(fvk) = fvks.orchard() {
+                    orchard.push((fvks.source(name), fvk.to_ovk(scope)));
+                }
+            }
+        }
+
+        Ok(Self { sapling, orchard })
+    }
+
+    pub fn sapling(&self) -> &[(KeySource, SaplingOvk)] {
+        &self.sapling
+    }
+
+    pub fn orchard(&self) -> &[(KeySource, OrchardOvk)] {
+        &self.orchard
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sapling.is_empty() && self.orchard.is_empty()
+    }
+}
+
+fn sapling_ivk_from_bytes(bytes: &[u8]) -> Result<SaplingIvk> {
+    let Ok(repr) = <[u8; 32]>::try_from(bytes) else {
+        bail!("Sapling IVK must be 32 bytes, got {}", bytes.len());
+    };
+    let fr = Option::from(jubjub::Fr::from_bytes(&repr))
+        .context("Sapling IVK is not a canonical scalar")?;
+    Ok(SaplingIvk(fr))
+}
@@ -0,0 +1,208 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, bail};
+use tempfile::TempDir;
+
+/// The Berkeley DB utilities that can replay a wallet's environment log, in
+/// the order they are tried. zcashd links against BDB 6.2, but distributions
+/// often ship the tools with a version suffix.
+const DB_RECOVER_COMMANDS: [&str; 3] = ["db_recover", "db6.2_recover", "db4.8_recover"];
+
+/// Why a wallet file may be unsafe to read in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletInUse {
+    /// Another process holds the data directory's `.lock`, so zcashd is
+    /// running and may be writing to the wallet.
+    Locked { lock_file: PathBuf },
+    /// The data directory's `database/` environment holds log files, so the
+    /// wallet file may be missing changes that have not been checkpointed.
+    UncheckpointedLog { log_dir: PathBuf },
+}
+
+impl std::fmt::Display for WalletInUse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletInUse::Locked { lock_file } => {
+                write!(f, "zcashd is running: {} is locked", lock_file.display())
+            }
+            WalletInUse::UncheckpointedLog { log_dir } => write!(
+                f,
+                "the Berkeley DB environment in {} has log files that may not be checkpointed into the wallet",
+                log_dir.display()
+            ),
+        }
+    }
+}
+
+/// The data directory of a wallet file: the directory holding it, or for a
+/// wallet kept under `wallets/`, the directory above that.
+pub fn wallet_datadir(wallet: &Path) -> Option<&Path> {
+    let parent = wallet.parent()?;
+    if parent.file_name().is_some_and(|name| name == "wallets") {
+        return parent.parent();
+    }
+    Some(parent)
+}
+
+/// Check whether the wallets of a data directory are held open by a running
+/// zcashd, or were left with an uncheckpointed environment log by one that
+/// did not shut down cleanly. A cleanly stopped zcashd leaves its `.lock`
+/// file unlocked and removes the logs from `database/`.
+pub fn check_datadir_in_use(datadir: &Path) -> Result<Option<WalletInUse>> {
+    let lock_file = datadir.join(".lock");
+    if lock_file.is_file() && is_locked(&lock_file)? {
+        return Ok(Some(WalletInUse::Locked { lock_file }));
+    }
+
+    let log_dir = datadir.join("database");
+    if !log_files(&log_dir)?.is_empty() {
+        return Ok(Some(WalletInUse::UncheckpointedLog { log_dir }));
+    }
+    Ok(None)
+}
+
+/// A wallet file that is safe to read: either the original, or a recovered
+/// copy in a private temporary environment that lives as long as this value.
+#[derive(Debug)]
+pub struct WalletFile {
+    path: PathBuf,
+    _snapshot_dir: Option<TempDir>,
+}
+
+impl WalletFile {
+    /// Prepare a wallet file for reading. `datadir` is the zcashd data
+    /// directory the wallet belongs to; if it is not given, it is found with
+    /// [`wallet_datadir`].
+    ///
+    /// By default a wallet that zcashd may still have open is refused. With
+    /// `snapshot`, the wallet and its environment log are instead copied into
+    /// a private temporary environment, and the log is replayed there.
+    pub fn open(wallet: &Path, datadir: Option<&Path>, snapshot: bool) -> Result<Self> {
+        let datadir = datadir.or_else(|| wallet_datadir(wallet));
+        let in_use = match datadir {
+            Some(datadir) => check_datadir_in_use(datadir)?,
+            None => None,
+        };
+        if !snapshot {
+            if let Some(in_use) = in_use {
+                bail!(
+                    "Refusing to read {}: {}.\n\
+                     Stop zcashd cleanly (zcash-cli stop) and try again, or export the wallet \
+                     with z_exportwallet, or pass --snapshot to read a recovered copy.",
+                    wallet.display(),
+                    in_use
+                );
+            }
+            return Ok(Self { path: wallet.to_path_buf(), _snapshot_dir: None });
+        }
+
+        let snapshot_dir = tempfile::tempdir().context("Creating snapshot directory")?;
+        let file_name = wallet.file_name().context("Wallet path has no file name")?;
+        let snapshot_wallet = snapshot_dir.path().join(file_name);
+        std::fs::copy(wallet, &snapshot_wallet)
+            .with_context(|| format!("Copying {} to a snapshot", wallet.display()))?;
+
+        if let Some(datadir) = datadir {
+            let logs = log_files(&datadir.join("database"))?;
+            if !logs.is_empty() {
+                let snapshot_logs = snapshot_dir.path().join("database");
+                std::fs::create_dir(&snapshot_logs)?;
+                for log in &logs {
+                    std::fs::copy(log, snapshot_logs.join(log.file_name().unwrap()))
+                        .with_context(|| format!("Copying {} to a snapshot", log.display()))?;
+                }
+                recover_environment(snapshot_dir.path())?;
+            }
+        }
+
+        Ok(Self { path: snapshot_wallet, _snapshot_dir: Some(snapshot_dir) })
+    }
+
+    /// The path to read the wallet from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Replay the log of the environment in `home` into its database files with
+/// `db_recover`, which also checkpoints the result.
+fn recover_environment(home: &Path) -> Result<()> {
+    // zcashd keeps its logs in `database/`, which `db_recover` must be told
+    // through the environment's DB_CONFIG.
+    std::fs::write(home.join("DB_CONFIG"), "set_lg_dir database\n")?;
+
+    for command in DB_RECOVER_COMMANDS {
+        match Command::new(command).arg("-h").arg(home).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => bail!(
+                "{} failed on the wallet snapshot: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Running {}", command)),
+        }
+    }
+    bail!(
+        "Replaying the wallet's environment log needs the Berkeley DB utilities; install one of: {}",
+        DB_RECOVER_COMMANDS.join(", ")
+    )
+}
+
+/// The `log.NNNNNNNNNN` files of a Berkeley DB environment log directory.
+fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !log_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut logs = Vec::new();
+    for entry in std::fs::read_dir(log_dir).with_context(|| format!("Reading {}", log_dir.display()))? {
+        let path = entry?.path();
+        let is_log = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("log."));
+        if is_log && path.is_file() {
+            logs.push(path);
+        }
+    }
+    logs.sort();
+    Ok(logs)
+}
+
+/// Whether another process holds a lock on `lock_file`. zcashd locks its
+/// data directory with a POSIX record lock, which `F_GETLK` reports without
+/// taking the lock ourselves, and so without needing write access.
+///
+/// A lock file that cannot be opened at all cannot be inspected, so as on
+/// platforms without record locks, only the environment log is checked.
+#[cfg(unix)]
+fn is_locked(lock_file: &Path) -> Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    let file = match std::fs::File::open(lock_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("Opening {}", lock_file.display())),
+    };
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    // SAFETY: `file` is open for the duration of the call and `lock` is a
+    // valid, initialized `flock` structure.
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Checking the lock on {}", lock_file.display()));
+    }
+    Ok(lock.l_type != libc::F_UNLCK as _)
+}
+
+/// On other platforms the lock cannot be inspected, so only the environment
+/// log is checked.
+#[cfg(not(unix))]
+fn is_locked(_lock_file: &Path) -> Result<bool> {
+    Ok(false)
+}
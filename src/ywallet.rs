@@ -0,0 +1,714 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use orchard::keys::{FullViewingKey as OrchardFullViewingKey, Scope, SpendingKey as OrchardSpendingKey};
+use rusqlite::{Connection, OpenFlags};
+use sapling::zip32::ExtendedFullViewingKey;
+use zewif::{
+    Account, Address, Bip39Mnemonic, BlockHeight, Network, ProtocolAddress, SeedMaterial,
+    ShieldedAddress, SpendingKey, Transaction, TransparentAddress, TransparentSpendAuthority,
+    TransparentSpendingKey, TxId, UnifiedAddress, ZewifTop, ZewifWallet,
+};
+use zip32::DiversifierIndex;
+
+use crate::{
+    address_encoding::{encode_orchard, encode_sapling},
+    keydump::{decode_sapling_extsk, decode_wif},
+    note_decryption::ZMIGRATE_VENDOR,
+    sqlite_wallet::{column_or_null, columns, get_u32, table_exists},
+};
+
+/// The vendor of attachments holding YWallet-specific data.
+pub const YWALLET_VENDOR: &str = "app.ywallet";
+
+/// The schema of the YWallet database that YWallet-specific attachments
+/// are copied from.
+pub const YWALLET_CONFORMS_TO: &str =
+    "https://github.com/hhanh00/zwallet/blob/main/native/zcash-sync/src/db/migration.rs";
+
+/// A row of the `accounts` table, with the account's rows from `taddrs`,
+/// `orchard_addrs`, `ua_settings` and `diversifiers`.
+#[derive(Debug, Clone)]
+pub struct YwalletAccount {
+    id: i64,
+    name: String,
+    seed: Option<String>,
+    aindex: u32,
+    sapling_sk: Option<String>,
+    sapling_fvk: String,
+    sapling_address: String,
+    transparent_sk: Option<String>,
+    transparent_address: Option<String>,
+    orchard_sk: Option<Vec<u8>>,
+    orchard_fvk: Option<Vec<u8>>,
+    ua_settings: Option<(bool, bool, bool)>,
+    next_diversifier_index: u32,
+}
+
+impl YwalletAccount {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The BIP-39 phrase the account was derived from, if it was not
+    /// imported from a key.
+    pub fn seed(&self) -> Option<&str> {
+        self.seed.as_deref()
+    }
+
+    /// The ZIP-32 account index within the account's seed.
+    pub fn aindex(&self) -> u32 {
+        self.aindex
+    }
+
+    /// The encoded Sapling extended full viewing key. YWallet stores it in
+    /// the column it names `ivk`.
+    pub fn sapling_fvk(&self) -> &str {
+        &self.sapling_fvk
+    }
+
+    pub fn sapling_address(&self) -> &str {
+        &self.sapling_address
+    }
+
+    pub fn transparent_address(&self) -> Option<&str> {
+        self.transparent_address.as_deref()
+    }
+
+    /// Whether the account holds any spending key.
+    pub fn can_spend(&self) -> bool {
+        self.seed.is_some() || self.sapling_sk.is_some() || self.transparent_sk.is_some() || self.orchard_sk.is_some()
+    }
+
+    /// The Sapling addresses YWallet has handed out: every valid diversified
+    /// address below the account's next diversifier index, starting with the
+    /// default address.
+    pub fn diversified_addresses(&self, network: Network) -> Result<Vec<String>> {
+        let (_, bytes) = bech32::decode(&self.sapling_fvk)
+            .with_context(|| format!("Decoding the viewing key of account {}", self.id))?;
+        let extfvk = ExtendedFullViewingKey::read(bytes.as_slice())
+            .with_context(|| format!("Reading the viewing key of account {}", self.id))?;
+        let mut addresses = Vec::new();
+        let mut index = 0u32;
+        while index < self.next_diversifier_index.max(1) {
+            let Some((found, address)) = extfvk.find_address(DiversifierIndex::from(index)) else {
+                break;
+            };
+            let Ok(found) = u32::try_from(found) else {
+                break;
+            };
+            if found >= self.next_diversifier_index.max(1) {
+                break;
+            }
+            addresses.push(encode_sapling(network, &address));
+            index = found + 1;
+        }
+        Ok(addresses)
+    }
+
+    /// The account's Orchard spending key, if it was derived from a seed or
+    /// imported with one. It must match the account's Orchard viewing key.
+    pub fn orchard_spending_key(&self) -> Result<Option<OrchardSpendingKey>> {
+        let Some(bytes) = &self.orchard_sk else {
+            return Ok(None);
+        };
+        let bytes: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .with_context(|| format!("Orchard spending key of account {} is not 32 bytes", self.id))?;
+        let sk = Option::from(OrchardSpendingKey::from_bytes(bytes))
+            .with_context(|| format!("Invalid Orchard spending key for account {}", self.id))?;
+        if self.orchard_fvk.as_deref() != Some(&OrchardFullViewingKey::from(&sk).to_bytes()[..]) {
+            bail!("The Orchard spending key of account {} does not match its viewing key", self.id);
+        }
+        Ok(Some(sk))
+    }
+
+    /// The account's default Orchard address, as a single-receiver unified
+    /// address.
+    pub fn orchard_address(&self, network: Network) -> Result<Option<String>> {
+        let Some(bytes) = &self.orchard_fvk else {
+            return Ok(None);
+        };
+        let bytes: [u8; 96] = bytes
+            .as_slice()
+            .try_into()
+            .with_context(|| format!("Orchard viewing key of account {} is not 96 bytes", self.id))?;
+        let fvk = OrchardFullViewingKey::from_bytes(&bytes)
+            .with_context(|| format!("Invalid Orchard viewing key for account {}", self.id))?;
+        encode_orchard(network, &fvk.address_at(0u32, Scope::External)).map(Some)
+    }
+}
+
+/// A row of the `transactions` table. YWallet records each transaction once
+/// per account, with the net value it had for that account.
+#[derive(Debug, Clone)]
+pub struct YwalletTransaction {
+    id: i64,
+    account: i64,
+    txid: [u8; 32],
+    height: Option<u32>,
+    timestamp: Option<i64>,
+    value: i64,
+    address: Option<String>,
+    memo: Option<String>,
+}
+
+impl YwalletTransaction {
+    pub fn txid(&self) -> TxId {
+        TxId::from_bytes(self.txid)
+    }
+
+    pub fn account(&self) -> i64 {
+        self.account
+    }
+
+    /// The net change in the account's balance, in zatoshis.
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub fn memo(&self) -> Option<&str> {
+        self.memo.as_deref()
+    }
+}
+
+/// A row of the `received_notes` table.
+#[derive(Debug, Clone)]
+pub struct YwalletNote {
+    account: i64,
+    tx: i64,
+    orchard: bool,
+    output_index: u32,
+    value: u64,
+    position: Option<u64>,
+    nullifier: Option<Vec<u8>>,
+    spent: Option<u32>,
+}
+
+impl YwalletNote {
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn is_orchard(&self) -> bool {
+        self.orchard
+    }
+
+    /// The height at which the note was spent, if it was.
+    pub fn spent(&self) -> Option<u32> {
+        self.spent
+    }
+}
+
+/// A row of the `messages` table: a memo YWallet shows as a message.
+#[derive(Debug, Clone)]
+pub struct YwalletMessage {
+    account: i64,
+    tx: i64,
+    sender: Option<String>,
+    recipient: Option<String>,
+    subject: String,
+    body: String,
+    incoming: bool,
+    read: bool,
+}
+
+/// A row of the `contacts` table.
+#[derive(Debug, Clone)]
+pub struct YwalletContact {
+    name: String,
+    address: String,
+}
+
+impl YwalletContact {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+/// A YWallet database, read offline. YWallet keeps one database per coin;
+/// only the Zcash database can be migrated.
+#[derive(Debug, Clone)]
+pub struct YwalletDatabase {
+    schema_version: Option<u32>,
+    accounts: Vec<YwalletAccount>,
+    transactions: Vec<YwalletTransaction>,
+    notes: Vec<YwalletNote>,
+    messages: Vec<YwalletMessage>,
+    contacts: Vec<YwalletContact>,
+    properties: Vec<(String, String)>,
+}
+
+impl YwalletDatabase {
+    /// Read a YWallet database. It is opened read-only.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Opening {}", path.display()))?;
+        // Old zcash_client_sqlite databases also have `accounts` and
+        // `received_notes` tables, but no `ivk` column.
+        if !table_exists(&conn, "received_notes")? || !columns(&conn, "accounts")?.contains("ivk") {
+            bail!("{} is not a YWallet database", path.display());
+        }
+
+        let schema_version = if table_exists(&conn, "schema_version")? {
+            conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| get_u32(row, 0))?
+        } else {
+            None
+        };
+
+        Ok(Self {
+            schema_version,
+            accounts: read_accounts(&conn).context("Reading accounts")?,
+            transactions: read_transactions(&conn).context("Reading transactions")?,
+            notes: read_notes(&conn).context("Reading received notes")?,
+            messages: read_messages(&conn).context("Reading messages")?,
+            contacts: read_contacts(&conn).context("Reading contacts")?,
+            properties: read_properties(&conn).context("Reading properties")?,
+        })
+    }
+
+    pub fn accounts(&self) -> &[YwalletAccount] {
+        &self.accounts
+    }
+
+    pub fn transactions(&self) -> &[YwalletTransaction] {
+        &self.transactions
+    }
+
+    pub fn notes(&self) -> &[YwalletNote] {
+        &self.notes
+    }
+
+    pub fn contacts(&self) -> &[YwalletContact] {
+        &self.contacts
+    }
+
+    /// The network of the database, from the encoding of its Sapling
+    /// addresses. Databases of other coins, such as Ycash, are refused.
+    pub fn network(&self) -> Result<Network> {
+        let address = self
+            .accounts
+            .first()
+            .map(|account| account.sapling_address.as_str())
+            .context("The database has no accounts to infer its network from")?;
+        if address.starts_with("zregtestsapling") {
+            Ok(Network::Regtest)
+        } else if address.starts_with("ztestsapling") {
+            Ok(Network::Test)
+        } else if address.starts_with("zs") {
+            Ok(Network::Main)
+        } else {
+            bail!("Not a Zcash database: unrecognized address {}", address)
+        }
+    }
+
+    /// Migrate the database to ZeWIF.
+    ///
+    /// YWallet accounts can each have their own seed, so accounts are grouped
+    /// into one ZeWIF wallet per seed, with accounts imported from keys in a
+    /// wallet of their own. Spending keys go in ZeWIF's typed fields; notes,
+    /// memos and messages are preserved as attachments. Contacts,
+    /// `ua_settings` and `properties` are YWallet's own settings, and are
+    /// kept as Class II attachments under its vendor. Contacts and properties
+    /// belong to the database rather than any seed, so they are attached once,
+    /// to the top level.
+    pub fn to_zewif(&self) -> Result<ZewifTop> {
+        let network = self.network()?;
+        let mut zewif = ZewifTop::new();
+
+        for tx in &self.transactions {
+            let txid = tx.txid();
+            if zewif.transactions().contains_key(&txid) {
+                continue;
+            }
+            let mut transaction = Transaction::new(txid.clone());
+            if let Some(height) = tx.height {
+                transaction.set_mined_height(BlockHeight::from(height));
+            }
+            zewif.transactions_mut().insert(txid, transaction);
+        }
+        for tx in &self.transactions {
+            let Some(transaction) = zewif.transactions_mut().get_mut(&tx.txid()) else {
+                continue;
+            };
+            transaction.add_attachment(self.transaction_envelope(tx), YWALLET_VENDOR, Some(YWALLET_CONFORMS_TO));
+        }
+
+        let mut groups: BTreeMap<Option<&str>, Vec<&YwalletAccount>> = BTreeMap::new();
+        for account in &self.accounts {
+            groups.entry(account.seed.as_deref()).or_default().push(account);
+        }
+        for (seed, accounts) in groups {
+            let mut zewif_wallet = ZewifWallet::new(network);
+            if let Some(seed) = seed {
+                zewif_wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(
+                    seed.to_string(),
+                )));
+            }
+            for account in accounts {
+                zewif_wallet.add_account(self.migrate_account(account, network)?);
+            }
+            zewif.add_wallet(zewif_wallet);
+        }
+
+        if !self.contacts.is_empty() {
+            zewif.add_attachment(self.contacts_envelope(), YWALLET_VENDOR, Some(YWALLET_CONFORMS_TO));
+        }
+        if !self.properties.is_empty() {
+            zewif.add_attachment(self.properties_envelope(), YWALLET_VENDOR, Some(YWALLET_CONFORMS_TO));
+        }
+        Ok(zewif)
+    }
+
+    fn migrate_account(&self, ywallet_account: &YwalletAccount, network: Network) -> Result<Account> {
+        let mut account = Account::new();
+        account.set_name(ywallet_account.name.clone());
+        if ywallet_account.seed.is_some() {
+            account.set_zip32_account_id(ywallet_account.aindex);
+        }
+
+        let sapling_sk = ywallet_account
+            .sapling_sk
+            .as_deref()
+            .map(decode_sapling_extsk)
+            .transpose()
+            .with_context(|| format!("Sapling spending key of account {}", ywallet_account.id))?;
+        for (i, encoded) in ywallet_account.diversified_addresses(network)?.into_iter().enumerate() {
+            let mut shielded = ShieldedAddress::new(encoded);
+            // Each diversified address is spent with the account's key.
+            if let Some(extsk) = &sapling_sk {
+                shielded.set_spending_key(SpendingKey::Sapling(extsk.clone()));
+            }
+            let mut address = Address::new(ProtocolAddress::Shielded(shielded));
+            // ZeWIF has no field for the viewing key of an address that
+            // cannot spend, so an imported viewing key is attached to the
+            // default address.
+            if i == 0 && sapling_sk.is_none() {
+                let envelope =
+                    Envelope::new("ViewingKey").add_assertion("key", ywallet_account.sapling_fvk.clone());
+                address.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            }
+            account.add_address(address);
+        }
+        if let Some(taddr) = &ywallet_account.transparent_address {
+            let mut transparent = TransparentAddress::new(taddr.clone());
+            if let Some(sk) = &ywallet_account.transparent_sk {
+                let key = decode_transparent_sk(sk)
+                    .with_context(|| format!("Transparent spending key of account {}", ywallet_account.id))?;
+                transparent.set_spend_authority(TransparentSpendAuthority::SpendingKey(
+                    TransparentSpendingKey::new(key),
+                ));
+            }
+            account.add_address(Address::new(ProtocolAddress::Transparent(transparent)));
+        }
+        if let Some(orchard) = ywallet_account.orchard_address(network)? {
+            let mut address = Address::new(ProtocolAddress::Unified(UnifiedAddress::new(orchard)));
+            // ZeWIF has no field for an Orchard spending key, so it is
+            // attached to the account's Orchard address.
+            if let Some(sk) = ywallet_account.orchard_spending_key()? {
+                let envelope = Envelope::new("OrchardSpendingKey").add_assertion("key", hex::encode(sk.to_bytes()));
+                address.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            }
+            account.add_address(address);
+        }
+
+        for tx in self.transactions.iter().filter(|tx| tx.account == ywallet_account.id) {
+            account.add_relevant_transaction(tx.txid());
+        }
+
+        if let Some((transparent, sapling, orchard)) = ywallet_account.ua_settings {
+            account.add_attachment(
+                Envelope::new("UaSettings")
+                    .add_assertion("transparent", transparent)
+                    .add_assertion("sapling", sapling)
+                    .add_assertion("orchard", orchard),
+                YWALLET_VENDOR,
+                Some(YWALLET_CONFORMS_TO),
+            );
+        }
+        Ok(account)
+    }
+
+    fn transaction_envelope(&self, tx: &YwalletTransaction) -> Envelope {
+        let mut envelope = Envelope::new("YwalletTransaction")
+            .add_assertion("account", tx.account)
+            .add_assertion("value", tx.value);
+        if let Some(timestamp) = tx.timestamp {
+            envelope = envelope.add_assertion("timestamp", timestamp);
+        }
+        if let Some(address) = &tx.address {
+            envelope = envelope.add_assertion("address", address.clone());
+        }
+        if let Some(memo) = &tx.memo {
+            envelope = envelope.add_assertion("memo", memo.clone());
+        }
+        for note in self.notes.iter().filter(|note| note.tx == tx.id && note.account == tx.account) {
+            let mut note_envelope = Envelope::new("ReceivedNote")
+                .add_assertion("pool", if note.orchard { "Orchard" } else { "Sapling" })
+                .add_assertion("index", note.output_index)
+                .add_assertion("value", note.value);
+            if let Some(position) = note.position {
+                note_envelope = note_envelope.add_assertion("position", position);
+            }
+            if let Some(nullifier) = &note.nullifier {
+                note_envelope = note_envelope.add_assertion("nullifier", hex::encode(nullifier));
+            }
+            if let Some(spent) = note.spent {
+                note_envelope = note_envelope.add_assertion("spentHeight", spent);
+            }
+            envelope = envelope.add_assertion("receivedNote", note_envelope);
+        }
+        for message in self.messages.iter().filter(|m| m.tx == tx.id && m.account == tx.account) {
+            let mut message_envelope = Envelope::new("Message")
+                .add_assertion("subject", message.subject.clone())
+                .add_assertion("body", message.body.clone())
+                .add_assertion("incoming", message.incoming)
+                .add_assertion("read", message.read);
+            if let Some(sender) = &message.sender {
+                message_envelope = message_envelope.add_assertion("sender", sender.clone());
+            }
+            if let Some(recipient) = &message.recipient {
+                message_envelope = message_envelope.add_assertion("recipient", recipient.clone());
+            }
+            envelope = envelope.add_assertion("message", message_envelope);
+        }
+        envelope
+    }
+
+    fn contacts_envelope(&self) -> Envelope {
+        self.contacts.iter().fold(Envelope::new("Contacts"), |envelope, contact| {
+            envelope.add_assertion(
+                "contact",
+                Envelope::new(contact.name.clone()).add_assertion("address", contact.address.clone()),
+            )
+        })
+    }
+
+    fn properties_envelope(&self) -> Envelope {
+        self.properties.iter().fold(Envelope::new("Properties"), |envelope, (name, value)| {
+            envelope.add_assertion(name.clone(), value.clone())
+        })
+    }
+
+    /// Append the database contents to a migration report.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        if let Some(version) = self.schema_version {
+            writeln!(report, "- Schema version: {}", version)?;
+        }
+        let seeds = self
+            .accounts
+            .iter()
+            .filter_map(|account| account.seed.as_deref())
+            .collect::<std::collections::BTreeSet<_>>()
+            .len();
+        writeln!(report, "- Accounts: {} ({} distinct seeds)", self.accounts.len(), seeds)?;
+        for account in &self.accounts {
+            let source = if account.seed.is_some() {
+                format!("seed, ZIP-32 account {}", account.aindex)
+            } else if account.can_spend() {
+                "imported spending key".to_string()
+            } else {
+                "imported viewing key".to_string()
+            };
+            writeln!(report, "  * {}: {}", account.name, source)?;
+        }
+        writeln!(report, "- Transactions: {}", self.transactions.len())?;
+        let unspent: Vec<_> = self.notes.iter().filter(|note| note.spent.is_none()).collect();
+        writeln!(
+            report,
+            "- Notes: {} received, {} unspent ({} zatoshis)",
+            self.notes.len(),
+            unspent.len(),
+            unspent.iter().map(|note| note.value).sum::<u64>()
+        )?;
+        writeln!(report, "- Messages: {}", self.messages.len())?;
+        writeln!(report, "- Contacts: {}", self.contacts.len())?;
+        Ok(())
+    }
+}
+
+fn read_accounts(conn: &Connection) -> Result<Vec<YwalletAccount>> {
+    let cols = columns(conn, "accounts")?;
+    let query = format!(
+        "SELECT a.id_account, a.name, a.seed, {}, a.sk, a.ivk, a.address FROM accounts a ORDER BY a.id_account",
+        column_or_null(&cols, &["aindex"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok(YwalletAccount {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            seed: row.get::<_, Option<String>>(2)?.filter(|seed| !seed.is_empty()),
+            aindex: get_u32(row, 3)?.unwrap_or(0),
+            sapling_sk: row.get::<_, Option<String>>(4)?.filter(|sk| !sk.is_empty()),
+            sapling_fvk: row.get(5)?,
+            sapling_address: row.get(6)?,
+            transparent_sk: None,
+            transparent_address: None,
+            orchard_sk: None,
+            orchard_fvk: None,
+            ua_settings: None,
+            next_diversifier_index: 0,
+        })
+    })?;
+    let mut accounts = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for account in &mut accounts {
+        if table_exists(conn, "taddrs")? {
+            let taddr = conn
+                .prepare("SELECT sk, address FROM taddrs WHERE account = ?1")?
+                .query_map([account.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .next()
+                .transpose()?;
+            if let Some((sk, address)) = taddr {
+                account.transparent_sk = sk;
+                account.transparent_address = Some(address);
+            }
+        }
+        if table_exists(conn, "orchard_addrs")? {
+            let orchard = conn
+                .prepare("SELECT sk, fvk FROM orchard_addrs WHERE account = ?1")?
+                .query_map([account.id], |row| {
+                    Ok((row.get::<_, Option<Vec<u8>>>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })?
+                .next()
+                .transpose()?;
+            if let Some((sk, fvk)) = orchard {
+                account.orchard_sk = sk;
+                account.orchard_fvk = Some(fvk);
+            }
+        }
+        if table_exists(conn, "ua_settings")? {
+            account.ua_settings = conn
+                .prepare("SELECT transparent, sapling, orchard FROM ua_settings WHERE account = ?1")?
+                .query_map([account.id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .next()
+                .transpose()?;
+        }
+        if table_exists(conn, "diversifiers")? {
+            let index = conn
+                .prepare("SELECT diversifier_index FROM diversifiers WHERE account = ?1")?
+                .query_map([account.id], |row| row.get::<_, Vec<u8>>(0))?
+                .next()
+                .transpose()?;
+            // The index is stored as an 11-byte little-endian diversifier
+            // index; YWallet never advances it past 32 bits.
+            if let Some(index) = index {
+                let mut low = [0u8; 4];
+                let len = index.len().min(4);
+                low[..len].copy_from_slice(&index[..len]);
+                account.next_diversifier_index = u32::from_le_bytes(low);
+            }
+        }
+    }
+    Ok(accounts)
+}
+
+fn read_transactions(conn: &Connection) -> Result<Vec<YwalletTransaction>> {
+    let mut statement = conn.prepare(
+        "SELECT id_tx, account, txid, height, timestamp, value, address, memo FROM transactions ORDER BY id_tx",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Vec<u8>>(2)?,
+            get_u32(row, 3)?,
+            row.get(4)?,
+            row.get::<_, i64>(5)?,
+            row.get(6)?,
+            row.get(7)?,
+        ))
+    })?;
+    rows.map(|row| {
+        let (id, account, txid, height, timestamp, value, address, memo) = row?;
+        let txid: [u8; 32] = txid
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Transaction {} has a malformed txid", id))?;
+        Ok(YwalletTransaction { id, account, txid, height, timestamp, value, address, memo })
+    })
+    .collect()
+}
+
+fn read_notes(conn: &Connection) -> Result<Vec<YwalletNote>> {
+    let cols = columns(conn, "received_notes")?;
+    let query = format!(
+        "SELECT account, tx, {}, output_index, value, position, nf, spent FROM received_notes ORDER BY id_note",
+        column_or_null(&cols, &["orchard"]),
+    );
+    let mut statement = conn.prepare(&query)?;
+    let rows = statement.query_map([], |row| {
+        Ok(YwalletNote {
+            account: row.get(0)?,
+            tx: row.get(1)?,
+            orchard: row.get::<_, Option<bool>>(2)?.unwrap_or(false),
+            output_index: row.get::<_, i64>(3)? as u32,
+            value: row.get::<_, i64>(4)? as u64,
+            position: row.get::<_, Option<i64>>(5)?.map(|p| p as u64),
+            nullifier: row.get(6)?,
+            spent: get_u32(row, 7)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_messages(conn: &Connection) -> Result<Vec<YwalletMessage>> {
+    if !table_exists(conn, "messages")? {
+        return Ok(Vec::new());
+    }
+    let mut statement = conn.prepare(
+        "SELECT account, id_tx, sender, recipient, subject, body, incoming, read FROM messages ORDER BY id",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok(YwalletMessage {
+            account: row.get(0)?,
+            tx: row.get(1)?,
+            sender: row.get(2)?,
+            recipient: row.get(3)?,
+            subject: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            body: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            incoming: row.get(6)?,
+            read: row.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_contacts(conn: &Connection) -> Result<Vec<YwalletContact>> {
+    if !table_exists(conn, "contacts")? {
+        return Ok(Vec::new());
+    }
+    let mut statement = conn.prepare("SELECT name, address FROM contacts ORDER BY id")?;
+    let rows = statement.query_map([], |row| Ok(YwalletContact { name: row.get(0)?, address: row.get(1)? }))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_properties(conn: &Connection) -> Result<Vec<(String, String)>> {
+    if !table_exists(conn, "properties")? {
+        return Ok(Vec::new());
+    }
+    let mut statement = conn.prepare("SELECT name, value FROM properties ORDER BY name")?;
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Decode a `taddrs.sk` value. zcash-sync stores the secret key as 64 hex
+/// digits; WIF, as other wallets export it, is accepted too.
+fn decode_transparent_sk(sk: &str) -> Result<[u8; 32]> {
+    if sk.len() == 64 {
+        if let Ok(bytes) = hex::decode(sk) {
+            return Ok(bytes.try_into().expect("64 hex digits are 32 bytes"));
+        }
+    }
+    decode_wif(sk)
+}
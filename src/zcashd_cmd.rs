@@ -1,75 +1,594 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
-use clap::Args;
-use std::fmt::Write;
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use serde_json::json;
 
-use crate::file_args::{FileArgs, FileArgsLike};
+use crate::{
+    annotate::{AnnotationFormat, record_spans, write_annotations},
+    asset_risk::{AssetRisk, Target},
+    balances::{Balances, format_zec},
+    datadir::{DiscoveredWallet, discover_wallets},
+    deterministic::{canonicalize, write_debug},
+    exec::{collect_output, end_section},
+    hd_verification::HdVerification,
+    json_output::{JsonObjectWriter, OutputFormat, debug_to_json, report_to_json, zewif_diagnostic, zewif_to_json},
+    note_decryption::NoteDecryption,
+    salvage::{SalvageMode, SalvageReport, salvage_wallet},
+    sent_outputs::SentOutputRecovery,
+    sprout::SproutFunds,
+    viewing_keys::WalletIvks,
+    wallet_access::WalletFile,
+};
 
-use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser};
+use zewif::{TxId, ZewifTop};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, ZcashdWallet};
 
 /// Process a zcashd wallet file
 #[derive(Debug, Args)]
 #[group(skip)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CommandArgs {
+    #[command(subcommand)]
+    command: Option<ZcashdCommands>,
+
+    /// The wallet file to process
+    #[arg(required_unless_present = "datadir")]
+    file: Option<PathBuf>,
+
+    /// A zcashd data directory to search for wallets, instead of a single
+    /// wallet file.
+    #[arg(long, conflicts_with = "file")]
+    datadir: Option<PathBuf>,
+
+    /// Merge every wallet found in the data directory into a single ZeWIF,
+    /// rather than migrating each separately.
+    #[arg(long, requires = "datadir")]
+    combine: bool,
+
     #[command(flatten)]
-    file_args: FileArgs,
+    migration: MigrationArgs,
+
+    /// How to render the source and migrated wallets. JSON output is for a
+    /// single wallet file.
+    #[arg(long, value_enum, default_value = "text", conflicts_with = "datadir")]
+    format: OutputFormat,
+
+    /// Derive the migrated wallet's IDs from its contents rather than at
+    /// random, and write its maps and sets sorted, so that the same wallet
+    /// always gives the same output.
+    #[arg(long)]
+    deterministic: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ZcashdCommands {
+    /// Annotate the bytes of a wallet's records with the fields parsed from
+    /// them
+    Annotate(AnnotateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AnnotateArgs {
+    /// The wallet file to read
+    file: PathBuf,
+
+    /// The name of the records to annotate, such as `sapzkey`. Every record
+    /// of that name is annotated.
+    #[arg(long)]
+    key: String,
+
+    /// How to write the annotated fields.
+    #[arg(long, value_enum, default_value = "hexdump")]
+    format: AnnotationFormat,
+}
+
+/// The optional passes of a zcashd migration, as chosen on the command
+/// line. The generic `migrate` and `export` commands offer them too.
+#[derive(Debug, Clone, Default, Args)]
+#[group(skip)]
+pub struct MigrationArgs {
+    /// Trial-decrypt shielded outputs with the wallet's own incoming viewing
+    /// keys, attaching recovered note values and memos to the migrated outputs.
+    #[arg(long)]
+    decrypt_memos: bool,
+
+    /// Recover the recipient, value and memo of the wallet's sent shielded
+    /// outputs with its outgoing viewing keys.
+    #[arg(long)]
+    recover_sent_outputs: bool,
+
+    /// Compute note spentness and per-account balances from the wallet's own
+    /// transactions, flagging each migrated note as spent or unspent.
+    #[arg(long)]
+    balances: bool,
+
+    /// Re-derive every stored key from the wallet's seeds and flag keys that
+    /// cannot be reproduced from a seed.
+    #[arg(long)]
+    verify_hd: bool,
+
+    /// The wallet the migrated data is destined for, used to report which
+    /// keys and records it will be unable to import.
+    #[arg(long, value_enum)]
+    target: Option<Target>,
+
+    /// Exit with an error if any asset risk is critical: keys or records
+    /// holding funds that the `--target` wallet will be unable to import.
+    #[arg(long, requires = "target")]
+    fail_on_risk: bool,
+
+    /// Refuse to migrate a wallet that still holds unspent Sprout value.
+    #[arg(long)]
+    require_no_sprout: bool,
+
+    /// Read a wallet that zcashd may still have open by copying it, with its
+    /// environment log, into a private directory and replaying the log there.
+    #[arg(long)]
+    snapshot: bool,
+
+    /// Recover every readable record from a damaged wallet file, skipping
+    /// bad pages, as `db_dump -r` (or `-R` with `--salvage=aggressive`) does.
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "normal")]
+    salvage: Option<SalvageMode>,
 }
 
-impl FileArgsLike for CommandArgs {
-    fn file(&self) -> &PathBuf {
-        &self.file_args.file
+impl MigrationArgs {
+    /// The migration options chosen, with IDs drawn at random and the data
+    /// directory found from the wallet's path.
+    pub fn options(&self) -> MigrationOptions {
+        MigrationOptions {
+            decrypt_memos: self.decrypt_memos,
+            recover_sent_outputs: self.recover_sent_outputs,
+            balances: self.balances,
+            verify_hd: self.verify_hd,
+            target: self.target,
+            fail_on_risk: self.fail_on_risk,
+            require_no_sprout: self.require_no_sprout,
+            snapshot: self.snapshot,
+            salvage: self.salvage,
+            deterministic: false,
+            datadir: None,
+        }
     }
 }
 
+impl CommandArgs {
+    fn options(&self) -> MigrationOptions {
+        MigrationOptions { deterministic: self.deterministic, ..self.migration.options() }
+    }
+}
+
+/// Optional passes to run while migrating a zcashd wallet.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationOptions {
+    pub decrypt_memos: bool,
+    pub recover_sent_outputs: bool,
+    pub balances: bool,
+    pub verify_hd: bool,
+    pub target: Option<Target>,
+    pub fail_on_risk: bool,
+    pub require_no_sprout: bool,
+    pub snapshot: bool,
+    pub salvage: Option<SalvageMode>,
+    pub deterministic: bool,
+    /// The data directory the wallet belongs to, checked for a running
+    /// zcashd. By default it is found from the wallet's path.
+    pub datadir: Option<PathBuf>,
+}
+
 impl crate::exec::Exec for CommandArgs {
-    fn exec(&self) -> Result<String> {
-        dump_wallet(self.file())
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        if let Some(ZcashdCommands::Annotate(args)) = &self.command {
+            return annotate_records_to(&args.file, &args.key, args.format, out);
+        }
+        match (&self.file, &self.datadir) {
+            (_, Some(datadir)) => dump_datadir_to(datadir, self.combine, &self.options(), out),
+            (Some(file), None) => dump_wallet_to(file, &self.options(), self.format, out),
+            (None, None) => bail!("Either a wallet file or --datadir is required"),
+        }
     }
 }
 
 pub fn dump_wallet(file: &Path) -> Result<String> {
-    let db_dump = BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    dump_wallet_with_options(file, &MigrationOptions::default())
+}
+
+pub fn dump_wallet_with_options(file: &Path, options: &MigrationOptions) -> Result<String> {
+    collect_output(|out| dump_wallet_to(file, options, OutputFormat::Text, out))
+}
+
+/// Dump and migrate a zcashd wallet file, writing each section to `out` as
+/// soon as it is ready.
+pub fn dump_wallet_to(
+    file: &Path,
+    options: &MigrationOptions,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<()> {
+    let (db_dump, salvage_report) = read_dump(file, options)?;
 
     let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
 
     let (zcashd_wallet, unparsed_keys) =
         ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
 
-    let mut output = String::new();
+    if format == OutputFormat::Json {
+        // Everything is computed before the object is opened, so that an
+        // error cannot cut it short; a failed migration is recorded in it.
+        let source_wallet = debug_to_json(&zcashd_wallet).context("Rendering source wallet")?;
+
+        if !unparsed_keys.is_empty() {
+            let unparsed_keynames: Vec<String> =
+                unparsed_keys.iter().map(|key| key.keyname.to_string()).collect();
+            let asset_risk = AssetRisk::run(&zcashd_wallet, &unparsed_keynames, options.target)?;
+            let mut report = String::new();
+            if let Some(salvage_report) = &salvage_report {
+                salvage_report.write_report(&mut report)?;
+            }
+            let mut risk_report = String::new();
+            asset_risk.write_report(&mut risk_report)?;
+            report.push_str(&risk_report);
+
+            let mut sorted_keys: Vec<_> = unparsed_keys.into_iter().collect();
+            sorted_keys.sort();
+            let mut keys = Vec::new();
+            for key in sorted_keys {
+                let value = zcashd_dump.value_for_key(&key)?;
+                keys.push(json!({ "key": key.to_string(), "value": value.to_string() }));
+            }
+
+            let mut object = JsonObjectWriter::new(out)?;
+            object.field("source_wallet", &source_wallet)?;
+            object.field("unparsed_keys", &keys.into())?;
+            object.field("report", &report_to_json(&report))?;
+            object.field("success", &false.into())?;
+            object.finish()?;
+            return check_risk(&asset_risk, &risk_report, options);
+        }
+
+        let migrated = migrate_with_options(&zcashd_wallet, options).and_then(|(zewif_wallet, mut report)| {
+            if let Some(salvage_report) = &salvage_report {
+                salvage_report.write_report(&mut report)?;
+            }
+            Ok((zewif_to_json(&zewif_wallet), report))
+        });
+
+        let mut object = JsonObjectWriter::new(out)?;
+        object.field("source_wallet", &source_wallet)?;
+        return match migrated {
+            Ok((migrated_wallet, report)) => {
+                object.field("migrated_wallet", &migrated_wallet)?;
+                object.field("report", &report_to_json(&report))?;
+                object.field("success", &true.into())?;
+                object.finish()
+            }
+            Err(error) => {
+                object.field("error", &format!("{:#}", error).into())?;
+                object.field("success", &false.into())?;
+                object.finish()?;
+                Err(error)
+            }
+        };
+    }
 
-    // writeln!(output, "{}", zcashd_dump.keyname_summary())?;
-    // writeln!(output, "---")?;
+    // writeln!(out, "{}", zcashd_dump.keyname_summary())?;
+    // end_section(out)?;
 
-    writeln!(output, "Source wallet:\n{:#?}", zcashd_wallet)?;
+    writeln!(out, "Source wallet:")?;
+    write_debug(out, &zcashd_wallet, options.deterministic)?;
 
     if !unparsed_keys.is_empty() {
-        writeln!(output, "---")?;
-        writeln!(output, "🛑 Unparsed keys:")?;
+        let unparsed_keynames: Vec<String> =
+            unparsed_keys.iter().map(|key| key.keyname.to_string()).collect();
+        let asset_risk = AssetRisk::run(&zcashd_wallet, &unparsed_keynames, options.target)?;
+        let mut risk_report = String::new();
+        asset_risk.write_report(&mut risk_report)?;
+
+        end_section(out)?;
+        writeln!(out, "🛑 Unparsed keys:")?;
         let mut sorted_keys: Vec<_> = unparsed_keys.into_iter().collect();
         sorted_keys.sort();
         let mut last_keyname: Option<String> = None;
         for key in sorted_keys {
             if let Some(ref last_keyname) = last_keyname {
                 if *last_keyname != key.keyname {
-                    writeln!(output)?;
+                    writeln!(out)?;
                 }
             }
             last_keyname = Some(key.keyname.to_string());
 
             let value = zcashd_dump.value_for_key(&key)?;
-            writeln!(output, "❌ key: {}\n\tvalue: {}", key, value)?;
+            writeln!(out, "❌ key: {}\n\tvalue: {}", key, value)?;
+        }
+        end_section(out)?;
+        if let Some(salvage_report) = &salvage_report {
+            let mut report = String::new();
+            salvage_report.write_report(&mut report)?;
+            write!(out, "{}", report)?;
+        }
+        write!(out, "{}", risk_report)?;
+        out.flush()?;
+        check_risk(&asset_risk, &risk_report, options)?;
+        return Ok(());
+    }
+    end_section(out)?;
+
+    let (zewif_wallet, mut report) = migrate_with_options(&zcashd_wallet, options)?;
+    if let Some(salvage_report) = &salvage_report {
+        salvage_report.write_report(&mut report)?;
+    }
+
+    if format == OutputFormat::CborDiag {
+        writeln!(out, "Migrated wallet:\n{}", zewif_diagnostic(&zewif_wallet))?;
+    } else {
+        writeln!(out, "Migrated wallet:")?;
+        write_debug(out, &zewif_wallet, options.deterministic)?;
+    }
+    end_section(out)?;
+
+    // Add the migration quality report to the output
+    writeln!(out, "{}", report)?;
+    end_section(out)?;
+
+    writeln!(out, "✅ Success")?;
+    Ok(())
+}
+
+/// Migrate every wallet found in a zcashd data directory, either each to its
+/// own ZeWIF or all into a single combined ZeWIF.
+pub fn dump_datadir(datadir: &Path, combine: bool, options: &MigrationOptions) -> Result<String> {
+    collect_output(|out| dump_datadir_to(datadir, combine, options, out))
+}
+
+/// Migrate every wallet found in a zcashd data directory, writing each
+/// section to `out` as soon as it is ready.
+pub fn dump_datadir_to(
+    datadir: &Path,
+    combine: bool,
+    options: &MigrationOptions,
+    out: &mut impl Write,
+) -> Result<()> {
+    let wallets = discover_wallets(datadir)?;
+
+    writeln!(out, "Wallets found in {}:", datadir.display())?;
+    for wallet in &wallets {
+        match wallet.chain_cache() {
+            Some(chain_cache) => writeln!(
+                out,
+                "- {} ({:?}, chain cache {})",
+                wallet.path().display(),
+                wallet.network(),
+                chain_cache.display()
+            )?,
+            None => writeln!(out, "- {} ({:?})", wallet.path().display(), wallet.network())?,
+        }
+    }
+    out.flush()?;
+
+    let wallet_options = |wallet: &DiscoveredWallet| MigrationOptions {
+        datadir: Some(wallet.datadir().to_path_buf()),
+        ..options.clone()
+    };
+
+    if !combine {
+        for wallet in &wallets {
+            writeln!(out, "=== {} ===", wallet.path().display())?;
+            dump_wallet_to(wallet.path(), &wallet_options(wallet), OutputFormat::Text, out)
+                .with_context(|| format!("Migrating {}", wallet.path().display()))?;
+        }
+        return Ok(());
+    }
+
+    let mut combined = ZewifTop::new();
+    let mut reports = String::new();
+    // The wallet each transaction was taken from, and the transactions that
+    // later wallets also hold.
+    let mut tx_sources: HashMap<TxId, &Path> = HashMap::new();
+    let mut shared_txs = Vec::new();
+    for wallet in &wallets {
+        let options = wallet_options(wallet);
+        let (zcashd_wallet, salvage_report) = parse_wallet(wallet.path(), &options)?;
+        if zcashd_wallet.network() != wallet.network() {
+            writeln!(
+                reports,
+                "⚠️ {} is a {:?} wallet but was found in a {:?} data directory",
+                wallet.path().display(),
+                zcashd_wallet.network(),
+                wallet.network()
+            )?;
+        }
+        let (zewif, report) = migrate_with_options(&zcashd_wallet, &options)
+            .with_context(|| format!("Migrating {}", wallet.path().display()))?;
+        for zewif_wallet in zewif.wallets().values() {
+            combined.add_wallet(zewif_wallet.clone());
+        }
+        for (txid, tx) in zewif.transactions() {
+            match tx_sources.get(txid) {
+                Some(first) => shared_txs.push((txid.clone(), *first, wallet.path())),
+                None => {
+                    tx_sources.insert(txid.clone(), wallet.path());
+                    combined.transactions_mut().insert(txid.clone(), tx.clone());
+                }
+            }
+        }
+        writeln!(reports, "{}:", wallet.path().display())?;
+        reports.push_str(&report);
+        if let Some(salvage_report) = &salvage_report {
+            salvage_report.write_report(&mut reports)?;
+        }
+    }
+
+    if !shared_txs.is_empty() {
+        // Each copy carries its own wallet's note data and witnesses, which
+        // are not merged.
+        writeln!(
+            reports,
+            "⚠️ {} transactions are held by more than one wallet; only the first wallet's copy is \
+             kept:",
+            shared_txs.len()
+        )?;
+        shared_txs.sort_by_key(|(txid, _, _)| format!("{:?}", txid));
+        for (txid, first, other) in &shared_txs {
+            writeln!(
+                reports,
+                "  * {:?}: kept from {}, dropped from {}",
+                txid,
+                first.display(),
+                other.display()
+            )?;
+        }
+    }
+
+    end_section(out)?;
+    writeln!(out, "Migrated wallet:")?;
+    write_debug(out, &combined, options.deterministic)?;
+    end_section(out)?;
+    writeln!(out, "{}", reports)?;
+    end_section(out)?;
+    writeln!(out, "✅ Success")?;
+    Ok(())
+}
+
+/// Annotate the value of every record named `keyname` with the span of each
+/// field parsed from it. Offsets are from the start of each record's value.
+/// If a record fails to parse, the fields parsed before the failure are
+/// written ahead of the error.
+pub fn annotate_records_to(
+    file: &Path,
+    keyname: &str,
+    format: AnnotationFormat,
+    out: &mut impl Write,
+) -> Result<()> {
+    let db_dump = BDBDump::from_file(file).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+
+    let mut keys: Vec<_> = zcashd_dump.records().keys().filter(|key| key.keyname == keyname).collect();
+    if keys.is_empty() {
+        bail!("{} has no {} records", file.display(), keyname);
+    }
+    keys.sort();
+
+    for (i, key) in keys.into_iter().enumerate() {
+        let value = zcashd_dump.value_for_key(key)?;
+        writeln!(out, "Record {}", key)?;
+        let (result, spans) =
+            record_spans(|| ZcashdParser::parse_record(&zcashd_dump, key).map(|_| ()))?;
+        let name = format!("{}_{}", keyname, i);
+        write_annotations(&name, value.as_ref(), &spans, format, out)?;
+        if let Err(e) = result {
+            writeln!(out, "🛑 {:#}", e)?;
+        }
+        end_section(out)?;
+    }
+    Ok(())
+}
+
+/// Parse and migrate a zcashd wallet file without producing a dump, for
+/// commands that consume the migrated ZeWIF directly.
+pub fn migrate_wallet(file: &Path, options: &MigrationOptions) -> Result<ZewifTop> {
+    let (zcashd_wallet, _) = parse_wallet(file, options)?;
+    let (zewif, _) = migrate_with_options(&zcashd_wallet, options)?;
+    Ok(zewif)
+}
+
+/// Parse a zcashd wallet file that is to be migrated without its dump being
+/// shown, failing if any of its keys are unrecognized.
+pub(crate) fn parse_wallet(
+    file: &Path,
+    options: &MigrationOptions,
+) -> Result<(ZcashdWallet, Option<SalvageReport>)> {
+    let (db_dump, salvage_report) = read_dump(file, options)?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, unparsed_keys) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    if !unparsed_keys.is_empty() {
+        bail!(
+            "{} has {} unparsed keys; migrate it on its own to see them",
+            file.display(),
+            unparsed_keys.len()
+        );
+    }
+    Ok((zcashd_wallet, salvage_report))
+}
+
+/// Read a wallet file's Berkeley DB contents, salvaging what can be read
+/// from a damaged file if `options.salvage` is set.
+fn read_dump(file: &Path, options: &MigrationOptions) -> Result<(BDBDump, Option<SalvageReport>)> {
+    let wallet_file = WalletFile::open(file, options.datadir.as_deref(), options.snapshot)?;
+    match options.salvage {
+        Some(mode) => {
+            let (db_dump, report) = salvage_wallet(wallet_file.path(), mode)
+                .with_context(|| format!("Salvaging {}", file.display()))?;
+            Ok((db_dump, Some(report)))
+        }
+        None => {
+            let db_dump = BDBDump::from_file(wallet_file.path()).context("Parsing BerkeleyDB file")?;
+            Ok((db_dump, None))
         }
-        return Ok(output);
     }
+}
 
-    let zewif_wallet = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)
+/// Migrate a fully parsed zcashd wallet, running the optional passes selected
+/// by `options`, and build the migration quality report.
+pub(crate) fn migrate_with_options(
+    zcashd_wallet: &ZcashdWallet,
+    options: &MigrationOptions,
+) -> Result<(ZewifTop, String)> {
+    let mut zewif_wallet = zewif_zcashd::migrate_to_zewif(zcashd_wallet)
         .context("Migrating to Zewif")?;
-    writeln!(output, "---")?;
-    writeln!(output, "Migrated wallet:\n{:#?}", zewif_wallet)?;
 
-    // Generate migration quality report
-    writeln!(output, "---")?;
+    let sprout_funds = SproutFunds::run(zcashd_wallet, &zewif_wallet);
+    if options.require_no_sprout && sprout_funds.unspent_value() > 0 {
+        bail!(
+            "Wallet holds {} of unspent Sprout value and --require-no-sprout is set; \
+             sweep it to Sapling (ZIP-308) before migrating",
+            format_zec(sprout_funds.unspent_value())
+        );
+    }
+    sprout_funds.attach_to(&mut zewif_wallet);
+
+    // Balances are computed from the decrypted notes, so they need the
+    // decryption pass even when memos are not attached.
+    let note_decryption = if options.decrypt_memos || options.balances {
+        let ivks = WalletIvks::from_zcashd_wallet(zcashd_wallet)
+            .context("Collecting incoming viewing keys")?;
+        let note_decryption = NoteDecryption::run(&zewif_wallet, &ivks);
+        if options.decrypt_memos {
+            note_decryption.attach_to(&mut zewif_wallet);
+        }
+        Some(note_decryption)
+    } else {
+        None
+    };
+
+    let balances = match (&note_decryption, options.balances) {
+        (Some(note_decryption), true) => {
+            let balances = Balances::run(zcashd_wallet, &zewif_wallet, note_decryption)
+                .context("Computing balances")?;
+            balances.attach_to(&mut zewif_wallet);
+            Some(balances)
+        }
+        _ => None,
+    };
+
+    let sent_output_recovery = if options.recover_sent_outputs {
+        let mut recovery = SentOutputRecovery::run(zcashd_wallet, &zewif_wallet)
+            .context("Recovering sent outputs")?;
+        recovery.fill_sent_outputs(&mut zewif_wallet)?;
+        Some(recovery)
+    } else {
+        None
+    };
+
+    if options.deterministic {
+        zewif_wallet = canonicalize(&zewif_wallet)?;
+    }
 
     // Create migration quality report
     let mut report = String::new();
@@ -92,11 +611,38 @@ pub fn dump_wallet(file: &Path) -> Result<String> {
     let zewif_tx_count = zewif_wallet.transactions().len();
     writeln!(report, "- Transactions: {}/{} preserved", zewif_tx_count, zcashd_tx_count)?;
 
-    // Add the report to the output
-    writeln!(output, "{}", report)?;
+    if !sprout_funds.is_empty() {
+        sprout_funds.write_report(&mut report)?;
+    }
+    if let Some(note_decryption) = note_decryption.as_ref().filter(|_| options.decrypt_memos) {
+        note_decryption.write_report(&mut report)?;
+    }
+    if let Some(sent_output_recovery) = &sent_output_recovery {
+        sent_output_recovery.write_report(&mut report)?;
+    }
+    if let Some(balances) = &balances {
+        balances.write_report(&mut report)?;
+    }
+    if options.verify_hd {
+        HdVerification::run(zcashd_wallet)
+            .context("Verifying HD key derivation")?
+            .write_report(&mut report)?;
+    }
 
-    writeln!(output, "---")?;
-    writeln!(output, "✅ Success")?;
+    let asset_risk = AssetRisk::run(zcashd_wallet, &[], options.target)?;
+    let mut risk_report = String::new();
+    asset_risk.write_report(&mut risk_report)?;
+    report.push_str(&risk_report);
+    check_risk(&asset_risk, &risk_report, options)?;
 
-    Ok(output)
+    Ok((zewif_wallet, report))
+}
+
+/// Refuse the migration when `--fail-on-risk` is set and any finding is
+/// serious enough to put funds at risk.
+fn check_risk(asset_risk: &AssetRisk, risk_report: &str, options: &MigrationOptions) -> Result<()> {
+    if options.fail_on_risk && asset_risk.has_risk() {
+        bail!("{}Asset risks found and --fail-on-risk is set", risk_report);
+    }
+    Ok(())
 }
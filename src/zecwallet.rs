@@ -0,0 +1,784 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::{Context, Result, bail};
+use bc_envelope::prelude::*;
+use bech32::{Bech32, Hrp};
+use bip0039::{English, Mnemonic};
+use crypto_secretbox::{Key, KeyInit, Nonce, XSalsa20Poly1305, aead::Aead};
+use owo_colors::{OwoColorize, Stream};
+use sapling::zip32::{ExtendedFullViewingKey, ExtendedSpendingKey};
+use sha2::{Digest, Sha256};
+use zcash_primitives::legacy::TransparentAddress as LegacyTransparentAddress;
+use zeroize::Zeroizing;
+use zewif::{
+    Account, Address, Bip39Mnemonic, Network, ProtocolAddress, SeedMaterial, ShieldedAddress,
+    SpendingKey, TransparentAddress, TransparentSpendAuthority, TransparentSpendingKey, ZewifTop,
+    ZewifWallet,
+};
+use zip32::ChildIndex;
+
+use crate::{
+    address_encoding::{encode_sapling, encode_transparent},
+    annotate::Field,
+    hd_verification::coin_type,
+    note_decryption::ZMIGRATE_VENDOR,
+    seed_wallet::p2pkh_hash,
+};
+
+/// The newest `LightWallet` serialization version this parser knows.
+const LATEST_WALLET_VERSION: u64 = 25;
+
+/// The oldest `LightWallet` version whose keys are written as a separate
+/// `Keys` structure.
+const MIN_WALLET_VERSION: u64 = 15;
+
+/// The oldest `Keys` version that stores z-keys as `WalletZKey` records.
+const MIN_KEYS_VERSION: u64 = 7;
+
+/// The newest `Keys` version that stores t-keys as bare secrets followed by
+/// a list of addresses, rather than as `WalletTKey` records.
+const LAST_BARE_TKEYS_VERSION: u64 = 20;
+
+/// How a key came to be in the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZecwalletKeyType {
+    /// Derived from the wallet's seed at its `hdkey_num`.
+    Hd,
+    /// An imported spending key.
+    ImportedSpendingKey,
+    /// An imported Sapling viewing key, which cannot spend.
+    ImportedViewingKey,
+}
+
+/// A Sapling key from a Zecwallet Lite wallet.
+#[derive(Clone)]
+pub struct ZecwalletZKey {
+    keytype: ZecwalletKeyType,
+    extsk: Option<ExtendedSpendingKey>,
+    extfvk: ExtendedFullViewingKey,
+    hdkey_num: Option<u32>,
+    enc_key: Option<Vec<u8>>,
+    nonce: Option<Vec<u8>>,
+}
+
+impl ZecwalletZKey {
+    pub fn keytype(&self) -> ZecwalletKeyType {
+        self.keytype
+    }
+
+    /// The key's spending key, if it was stored unencrypted or has been
+    /// decrypted.
+    pub fn extsk(&self) -> Option<&ExtendedSpendingKey> {
+        self.extsk.as_ref()
+    }
+
+    pub fn extfvk(&self) -> &ExtendedFullViewingKey {
+        &self.extfvk
+    }
+
+    pub fn hdkey_num(&self) -> Option<u32> {
+        self.hdkey_num
+    }
+
+    /// The key's default address, which is the address Zecwallet shows.
+    pub fn address(&self) -> sapling::PaymentAddress {
+        self.extfvk.default_address().1
+    }
+}
+
+impl std::fmt::Debug for ZecwalletZKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZecwalletZKey")
+            .field("keytype", &self.keytype)
+            .field("address", &hex::encode(self.address().to_bytes()))
+            .field("hdkey_num", &self.hdkey_num)
+            .field("has_spending_key", &self.extsk.is_some())
+            .field("encrypted", &self.enc_key.is_some())
+            .finish()
+    }
+}
+
+/// A transparent key from a Zecwallet Lite wallet.
+#[derive(Clone)]
+pub struct ZecwalletTKey {
+    keytype: ZecwalletKeyType,
+    key: Option<Zeroizing<[u8; 32]>>,
+    address: String,
+    hdkey_num: Option<u32>,
+    enc_key: Option<Vec<u8>>,
+    nonce: Option<Vec<u8>>,
+}
+
+impl ZecwalletTKey {
+    pub fn keytype(&self) -> ZecwalletKeyType {
+        self.keytype
+    }
+
+    /// The key's secp256k1 secret, if it was stored unencrypted or has been
+    /// decrypted.
+    pub fn key(&self) -> Option<&[u8; 32]> {
+        self.key.as_deref()
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn hdkey_num(&self) -> Option<u32> {
+        self.hdkey_num
+    }
+}
+
+impl std::fmt::Debug for ZecwalletTKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZecwalletTKey")
+            .field("keytype", &self.keytype)
+            .field("address", &self.address)
+            .field("hdkey_num", &self.hdkey_num)
+            .field("has_spending_key", &self.key.is_some())
+            .field("encrypted", &self.enc_key.is_some())
+            .finish()
+    }
+}
+
+/// The keys of a Zecwallet Lite `zecwallet-light-wallet.dat` file.
+///
+/// Only the wallet's keys are read. The block and transaction data that
+/// follow them are a cache that a light wallet rebuilds by rescanning, and
+/// are counted but not parsed.
+pub struct ZecwalletWallet {
+    version: u64,
+    keys_version: u64,
+    encrypted: bool,
+    enc_seed: Vec<u8>,
+    nonce: Vec<u8>,
+    seed: Option<Zeroizing<[u8; 32]>>,
+    zkeys: Vec<ZecwalletZKey>,
+    tkeys: Vec<ZecwalletTKey>,
+    unparsed_len: usize,
+}
+
+impl std::fmt::Debug for ZecwalletWallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZecwalletWallet")
+            .field("version", &self.version)
+            .field("keys_version", &self.keys_version)
+            .field("encrypted", &self.encrypted)
+            .field("has_seed", &self.seed.is_some())
+            .field("zkeys", &self.zkeys)
+            .field("tkeys", &self.tkeys)
+            .field("unparsed_len", &self.unparsed_len)
+            .finish()
+    }
+}
+
+impl ZecwalletWallet {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.field("version", Reader::u64).context("Reading wallet version")?;
+        if version > LATEST_WALLET_VERSION {
+            bail!(
+                "Zecwallet wallet version {} is newer than the latest supported version {}",
+                version,
+                LATEST_WALLET_VERSION
+            );
+        }
+        if version < MIN_WALLET_VERSION {
+            bail!(
+                "Zecwallet wallet version {} predates the supported format; open and save it \
+                 with a current Zecwallet Lite first",
+                version
+            );
+        }
+
+        let keys_version = reader.field("keys_version", Reader::u64).context("Reading keys version")?;
+        if keys_version < MIN_KEYS_VERSION {
+            bail!("Zecwallet keys version {} is not supported", keys_version);
+        }
+        let encrypted = reader.field("encrypted", Reader::u8).context("Reading encryption flag")? > 0;
+        let enc_seed = reader.field("enc_seed", Reader::vector).context("Reading encrypted seed")?;
+        let nonce = reader.field("nonce", Reader::vector).context("Reading seed nonce")?;
+        let seed_bytes: [u8; 32] = reader.field("seed", Reader::array).context("Reading seed")?;
+        // A locked wallet writes its seed as zeros.
+        let seed = (!encrypted && seed_bytes != [0; 32]).then(|| Zeroizing::new(seed_bytes));
+
+        let zkeys = reader.field("zkeys", |reader| {
+            let zkey_count = reader.field("count", Reader::compact_size).context("Reading z-key count")?;
+            let mut zkeys = Vec::new();
+            for i in 0..zkey_count {
+                zkeys.push(
+                    reader.field(&i.to_string(), read_zkey).with_context(|| format!("Reading z-key {}", i))?,
+                );
+            }
+            Ok(zkeys)
+        })?;
+
+        let tkeys = reader.field("tkeys", |reader| {
+            if keys_version <= LAST_BARE_TKEYS_VERSION {
+                return read_bare_tkeys(reader).context("Reading t-keys");
+            }
+            let tkey_count = reader.field("count", Reader::compact_size).context("Reading t-key count")?;
+            let mut tkeys = Vec::new();
+            for i in 0..tkey_count {
+                tkeys.push(
+                    reader.field(&i.to_string(), read_tkey).with_context(|| format!("Reading t-key {}", i))?,
+                );
+            }
+            Ok(tkeys)
+        })?;
+
+        Ok(Self {
+            version,
+            keys_version,
+            encrypted,
+            enc_seed,
+            nonce,
+            seed,
+            zkeys,
+            tkeys,
+            unparsed_len: reader.remaining(),
+        })
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Whether the wallet's seed and keys are encrypted with a password.
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// The wallet's 32-byte seed, which is the entropy of its BIP-39
+    /// recovery phrase, if it was stored unencrypted or has been decrypted.
+    pub fn seed(&self) -> Option<&[u8; 32]> {
+        self.seed.as_deref()
+    }
+
+    pub fn zkeys(&self) -> &[ZecwalletZKey] {
+        &self.zkeys
+    }
+
+    pub fn tkeys(&self) -> &[ZecwalletTKey] {
+        &self.tkeys
+    }
+
+    /// The number of bytes of block and transaction data after the keys.
+    pub fn unparsed_len(&self) -> usize {
+        self.unparsed_len
+    }
+
+    /// The network of the wallet, from the encoding of its transparent
+    /// addresses. Zecwallet only records the chain name after its block and
+    /// transaction data, so a wallet without t-addresses has no hint.
+    pub fn network_hint(&self) -> Option<Network> {
+        self.tkeys.iter().find_map(|tkey| match tkey.address.get(..2) {
+            Some("t1" | "t3") => Some(Network::Main),
+            Some("tm" | "t2") => Some(Network::Test),
+            _ => None,
+        })
+    }
+
+    /// Decrypt the seed and keys of an encrypted wallet.
+    ///
+    /// Zecwallet seals each secret with `crypto_secretbox` (XSalsa20 and
+    /// Poly1305) under a key that is the double SHA-256 of the password, with
+    /// a random nonce stored beside each sealed secret.
+    pub fn decrypt(&mut self, password: &str) -> Result<()> {
+        if !self.encrypted {
+            return Ok(());
+        }
+        let key = Zeroizing::new(Sha256::digest(Sha256::digest(password.as_bytes())));
+        let cipher = XSalsa20Poly1305::new(Key::from_slice(&key));
+
+        let seed = open(&cipher, &self.enc_seed, &self.nonce)
+            .context("Incorrect password: the wallet seed could not be decrypted")?;
+        let seed: [u8; 32] = seed
+            .as_slice()
+            .try_into()
+            .context("Decrypted seed is not 32 bytes")?;
+        self.seed = Some(Zeroizing::new(seed));
+
+        for zkey in &mut self.zkeys {
+            let (Some(enc_key), Some(nonce)) = (&zkey.enc_key, &zkey.nonce) else {
+                continue;
+            };
+            let bytes = open(&cipher, enc_key, nonce).context("Decrypting Sapling spending key")?;
+            let extsk = ExtendedSpendingKey::read(bytes.as_slice())
+                .context("Reading decrypted Sapling spending key")?;
+            if extsk.default_address().1 != zkey.address() {
+                bail!("Decrypted Sapling spending key does not match its viewing key");
+            }
+            zkey.extsk = Some(extsk);
+        }
+
+        for tkey in &mut self.tkeys {
+            let (Some(enc_key), Some(nonce)) = (&tkey.enc_key, &tkey.nonce) else {
+                continue;
+            };
+            let bytes = open(&cipher, enc_key, nonce).context("Decrypting transparent key")?;
+            let key: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .context("Decrypted transparent key is not 32 bytes")?;
+            tkey.key = Some(Zeroizing::new(key));
+        }
+        Ok(())
+    }
+
+    /// The BIP-39 recovery phrase Zecwallet shows for the wallet's seed.
+    pub fn recovery_phrase(&self) -> Option<Result<String>> {
+        self.seed.as_ref().map(|seed| {
+            Mnemonic::<English>::from_entropy(seed.to_vec())
+                .map(|mnemonic| mnemonic.phrase().to_string())
+                .map_err(|e| anyhow::anyhow!("Seed is not valid BIP-39 entropy: {}", e))
+        })
+    }
+
+    /// Re-derive every HD key from the seed, to find keys that were not
+    /// derived where the seed says they should be.
+    ///
+    /// zecwallet-cli 1.0 derived the HD keys after the first incorrectly, for
+    /// both t- and z-addresses. Those keys cannot be restored from the seed
+    /// by any other wallet, so their funds must be swept before migration.
+    pub fn check_derivation(&self, network: Network) -> Result<Option<DerivationCheck>> {
+        let Some(phrase) = self.recovery_phrase().transpose()? else {
+            return Ok(None);
+        };
+        let bip39_seed = Mnemonic::<English>::from_phrase(phrase)
+            .map_err(|e| anyhow::anyhow!("Parsing recovery phrase: {}", e))?
+            .to_seed("");
+        let coin_type = coin_type(network);
+        let master = ExtendedSpendingKey::master(&bip39_seed);
+
+        let mut check = DerivationCheck::default();
+        for zkey in &self.zkeys {
+            let Some(num) = zkey.hdkey_num.filter(|_| zkey.keytype == ZecwalletKeyType::Hd)
+            else {
+                continue;
+            };
+            let expected = ExtendedSpendingKey::from_path(
+                &master,
+                &[ChildIndex::hardened(32), ChildIndex::hardened(coin_type), ChildIndex::hardened(num)],
+            );
+            check.checked += 1;
+            if expected.default_address().1 != zkey.address() {
+                check.mis_derived.push(MisDerivedKey {
+                    address: encode_sapling(network, &zkey.address()),
+                    hdkey_num: num,
+                    path: format!("m/32'/{}'/{}'", coin_type, num),
+                });
+            }
+        }
+        for tkey in &self.tkeys {
+            let Some(num) = tkey.hdkey_num.filter(|_| tkey.keytype == ZecwalletKeyType::Hd)
+            else {
+                continue;
+            };
+            let hash = p2pkh_hash(&bip39_seed, coin_type, 0, num)?;
+            let expected =
+                encode_transparent(network, &LegacyTransparentAddress::PublicKeyHash(hash));
+            check.checked += 1;
+            if expected != tkey.address {
+                check.mis_derived.push(MisDerivedKey {
+                    address: tkey.address.clone(),
+                    hdkey_num: num,
+                    path: format!("m/44'/{}'/0'/0/{}", coin_type, num),
+                });
+            }
+        }
+        Ok(Some(check))
+    }
+
+    /// Migrate the wallet's keys to ZeWIF.
+    ///
+    /// Each HD z-key is its own ZIP-32 account, as Zecwallet derives them at
+    /// `m/32'/coin'/n'`; HD t-keys belong to BIP-44 account 0. Mis-derived and
+    /// imported keys are kept in accounts of their own, since they cannot be
+    /// restored from the seed. Spending keys become the spending keys of
+    /// their addresses, and the path Zecwallet derived each HD key at is
+    /// attached to its address.
+    pub fn to_zewif(&self, network: Network) -> Result<ZewifTop> {
+        let mut zewif_wallet = ZewifWallet::new(network);
+        if let Some(phrase) = self.recovery_phrase().transpose()? {
+            zewif_wallet.set_seed_material(SeedMaterial::Bip39Mnemonic(Bip39Mnemonic::new(phrase)));
+        }
+        let mis_derived: Vec<String> = self
+            .check_derivation(network)?
+            .map(|check| check.mis_derived.into_iter().map(|key| key.address).collect())
+            .unwrap_or_default();
+
+        let coin_type = coin_type(network);
+        let mut groups: BTreeMap<AccountGroup, Vec<Address>> = BTreeMap::new();
+        for zkey in &self.zkeys {
+            let encoded = encode_sapling(network, &zkey.address());
+            let group = account_group(zkey.keytype, zkey.hdkey_num, mis_derived.contains(&encoded), true);
+            let mut shielded = ShieldedAddress::new(encoded);
+            if let Some(extsk) = &zkey.extsk {
+                shielded.set_spending_key(SpendingKey::Sapling(extsk.clone()));
+            }
+            let mut address = Address::new(ProtocolAddress::Shielded(shielded));
+            // ZeWIF has no field for the viewing key of an address that
+            // cannot spend, so an imported viewing key is attached.
+            if zkey.extsk.is_none() {
+                let envelope = Envelope::new("ViewingKey")
+                    .add_assertion("key", encode_sapling_extfvk(network, &zkey.extfvk)?);
+                address.add_attachment(envelope, ZMIGRATE_VENDOR, None);
+            }
+            if let Some(num) = zkey.hdkey_num {
+                let path = format!("m/32'/{}'/{}'", coin_type, num);
+                address.add_attachment(key_metadata(&path), ZMIGRATE_VENDOR, None);
+            }
+            groups.entry(group).or_default().push(address);
+        }
+        for tkey in &self.tkeys {
+            let group =
+                account_group(tkey.keytype, tkey.hdkey_num, mis_derived.contains(&tkey.address), false);
+            let mut transparent = TransparentAddress::new(tkey.address.clone());
+            if let Some(key) = &tkey.key {
+                transparent.set_spend_authority(TransparentSpendAuthority::SpendingKey(
+                    TransparentSpendingKey::new(**key),
+                ));
+            }
+            let mut address = Address::new(ProtocolAddress::Transparent(transparent));
+            if let Some(num) = tkey.hdkey_num {
+                let path = format!("m/44'/{}'/0'/0/{}", coin_type, num);
+                address.add_attachment(key_metadata(&path), ZMIGRATE_VENDOR, None);
+            }
+            groups.entry(group).or_default().push(address);
+        }
+
+        for (group, addresses) in groups {
+            let mut account = Account::new();
+            account.set_name(group.name());
+            if let AccountGroup::Zip32(account_id) = group {
+                account.set_zip32_account_id(account_id);
+            }
+            for address in addresses {
+                account.add_address(address);
+            }
+            zewif_wallet.add_account(account);
+        }
+
+        let mut zewif = ZewifTop::new();
+        zewif.add_wallet(zewif_wallet);
+        Ok(zewif)
+    }
+}
+
+/// A key whose address differs from the one its seed derives at its
+/// `hdkey_num`.
+#[derive(Debug, Clone)]
+pub struct MisDerivedKey {
+    address: String,
+    hdkey_num: u32,
+    path: String,
+}
+
+impl MisDerivedKey {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn hdkey_num(&self) -> u32 {
+        self.hdkey_num
+    }
+
+    /// The path the key should have been derived at.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// The result of re-deriving a Zecwallet wallet's HD keys from its seed.
+#[derive(Debug, Clone, Default)]
+pub struct DerivationCheck {
+    checked: usize,
+    mis_derived: Vec<MisDerivedKey>,
+}
+
+impl DerivationCheck {
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    pub fn mis_derived(&self) -> &[MisDerivedKey] {
+        &self.mis_derived
+    }
+
+    /// Append the check results to a migration report. Mis-derived keys are
+    /// highlighted in red, as their funds must be swept before migration.
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "- HD Path Verification:")?;
+        writeln!(
+            report,
+            "  * Keys reproduced from seed: {}/{}",
+            self.checked - self.mis_derived.len(),
+            self.checked
+        )?;
+        if self.mis_derived.is_empty() {
+            return Ok(());
+        }
+        let warning = "🔴 Mis-derived keys found, as written by zecwallet-cli 1.0. Other wallets \
+                       cannot restore these from the seed: sweep their funds to the first address \
+                       before migrating.";
+        writeln!(
+            report,
+            "  * {}",
+            warning.if_supports_color(Stream::Stdout, |text| text.red().bold())
+        )?;
+        for key in &self.mis_derived {
+            let line = format!("HD key #{} {} does not match {}", key.hdkey_num, key.address, key.path);
+            writeln!(report, "    {}", line.if_supports_color(Stream::Stdout, |text| text.red()))?;
+        }
+        Ok(())
+    }
+}
+
+/// How keys are grouped into rebuilt accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AccountGroup {
+    Zip32(u32),
+    MisDerived,
+    Imported,
+}
+
+impl AccountGroup {
+    fn name(&self) -> String {
+        match self {
+            AccountGroup::Zip32(account) => format!("Account #{}", account),
+            AccountGroup::MisDerived => "Mis-derived keys (zecwallet-cli 1.0)".to_string(),
+            AccountGroup::Imported => "Imported keys".to_string(),
+        }
+    }
+}
+
+/// Whether a wallet beginning with `version` is one this parser reads.
+pub(crate) fn supports_version(version: u64) -> bool {
+    (MIN_WALLET_VERSION..=LATEST_WALLET_VERSION).contains(&version)
+}
+
+fn account_group(
+    keytype: ZecwalletKeyType,
+    hdkey_num: Option<u32>,
+    mis_derived: bool,
+    sapling: bool,
+) -> AccountGroup {
+    match (keytype, hdkey_num) {
+        _ if mis_derived => AccountGroup::MisDerived,
+        (ZecwalletKeyType::Hd, Some(num)) if sapling => AccountGroup::Zip32(num),
+        (ZecwalletKeyType::Hd, Some(_)) => AccountGroup::Zip32(0),
+        _ => AccountGroup::Imported,
+    }
+}
+
+/// The HD path of a key, in the form the `exportwallet` front-end attaches
+/// it.
+fn key_metadata(hd_keypath: &str) -> Envelope {
+    Envelope::new("KeyMetadata").add_assertion("hdKeypath", hd_keypath.to_string())
+}
+
+/// Encode a Sapling extended full viewing key as a `zxview…` string.
+fn encode_sapling_extfvk(network: Network, extfvk: &ExtendedFullViewingKey) -> Result<String> {
+    let hrp = match network {
+        Network::Main => "zxviews",
+        Network::Test => "zxviewtestsapling",
+        Network::Regtest => "zxviewregtestsapling",
+    };
+    let mut bytes = Vec::new();
+    extfvk.write(&mut bytes)?;
+    bech32::encode::<Bech32>(Hrp::parse_unchecked(hrp), &bytes)
+        .context("Encoding Sapling extended full viewing key")
+}
+
+/// Open a `crypto_secretbox`, whose tag precedes the ciphertext.
+fn open(cipher: &XSalsa20Poly1305, sealed: &[u8], nonce: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if nonce.len() != 24 {
+        bail!("Invalid secretbox nonce length {}", nonce.len());
+    }
+    cipher
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow::anyhow!("Secretbox authentication failed"))
+}
+
+fn read_zkey(reader: &mut Reader) -> Result<ZecwalletZKey> {
+    let version = reader.field("version", Reader::u8).context("Reading version")?;
+    if version > 1 {
+        bail!("Unsupported WalletZKey version {}", version);
+    }
+    let keytype = match reader.field("keytype", Reader::u32).context("Reading key type")? {
+        0 => ZecwalletKeyType::Hd,
+        1 => ZecwalletKeyType::ImportedSpendingKey,
+        2 => ZecwalletKeyType::ImportedViewingKey,
+        other => bail!("Unknown z-key type {}", other),
+    };
+    let _locked = reader.field("locked", Reader::u8).context("Reading locked flag")?;
+    let extsk = reader
+        .field("extsk", |r| r.optional(|r| r.read_with(|bytes| ExtendedSpendingKey::read(bytes))))
+        .context("Reading extended spending key")?;
+    let extfvk = reader
+        .field("extfvk", |r| r.read_with(|bytes| ExtendedFullViewingKey::read(bytes)))
+        .context("Reading extended full viewing key")?;
+    let hdkey_num = reader.field("hdkey_num", |r| r.optional(Reader::u32)).context("Reading HD key number")?;
+    let enc_key = reader.field("enc_key", |r| r.optional(Reader::vector)).context("Reading encrypted key")?;
+    let nonce = reader.field("nonce", |r| r.optional(Reader::vector)).context("Reading key nonce")?;
+    Ok(ZecwalletZKey { keytype, extsk, extfvk, hdkey_num, enc_key, nonce })
+}
+
+fn read_tkey(reader: &mut Reader) -> Result<ZecwalletTKey> {
+    let version = reader.field("version", Reader::u8).context("Reading version")?;
+    if version > 1 {
+        bail!("Unsupported WalletTKey version {}", version);
+    }
+    let keytype = match reader.field("keytype", Reader::u32).context("Reading key type")? {
+        0 => ZecwalletKeyType::Hd,
+        1 => ZecwalletKeyType::ImportedSpendingKey,
+        other => bail!("Unknown t-key type {}", other),
+    };
+    let _locked = reader.field("locked", Reader::u8).context("Reading locked flag")?;
+    let key = reader
+        .field("key", |r| r.optional(|r| r.array().map(Zeroizing::new)))
+        .context("Reading secret key")?;
+    let address = reader.field("address", Reader::string).context("Reading address")?;
+    let hdkey_num = reader.field("hdkey_num", |r| r.optional(Reader::u32)).context("Reading HD key number")?;
+    let enc_key = reader.field("enc_key", |r| r.optional(Reader::vector)).context("Reading encrypted key")?;
+    let nonce = reader.field("nonce", |r| r.optional(Reader::vector)).context("Reading key nonce")?;
+    Ok(ZecwalletTKey { keytype, key, address, hdkey_num, enc_key, nonce })
+}
+
+/// Read t-keys in the older layout: a list of bare 32-byte secrets, then a
+/// list of their addresses. Every such key is an HD key at its position.
+fn read_bare_tkeys(reader: &mut Reader) -> Result<Vec<ZecwalletTKey>> {
+    let key_count = reader.field("count", Reader::compact_size).context("Reading t-key count")?;
+    let mut keys = Vec::new();
+    for i in 0..key_count {
+        let key: [u8; 32] =
+            reader.field(&format!("{}.key", i), Reader::array).with_context(|| format!("Reading t-key {}", i))?;
+        // A locked wallet writes its t-keys as zeros.
+        keys.push((key != [0; 32]).then(|| Zeroizing::new(key)));
+    }
+    let address_count =
+        reader.field("address_count", Reader::compact_size).context("Reading t-address count")?;
+    if address_count != key_count {
+        bail!("{} t-keys but {} t-addresses", key_count, address_count);
+    }
+    keys.into_iter()
+        .enumerate()
+        .map(|(num, key)| {
+            Ok(ZecwalletTKey {
+                keytype: ZecwalletKeyType::Hd,
+                key,
+                address: reader
+                    .field(&format!("{}.address", num), Reader::string)
+                    .with_context(|| format!("Reading t-address {}", num))?,
+                hdkey_num: Some(num as u32),
+                enc_key: None,
+                nonce: None,
+            })
+        })
+        .collect()
+}
+
+/// A cursor over the little-endian, CompactSize-prefixed serialization
+/// Zecwallet uses.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            bail!(
+                "Unexpected end of file at offset {}: needed {} bytes, {} remain",
+                self.pos,
+                len,
+                self.remaining()
+            );
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn compact_size(&mut self) -> Result<usize> {
+        let size = match self.u8()? {
+            0xfd => u16::from_le_bytes(self.array()?) as u64,
+            0xfe => u32::from_le_bytes(self.array()?) as u64,
+            0xff => self.u64()?,
+            size => size as u64,
+        };
+        // Every element takes at least one byte, so a count larger than the
+        // rest of the file is corrupt.
+        if size > self.remaining() as u64 {
+            bail!("Length {} at offset {} exceeds the rest of the file", size, self.pos);
+        }
+        Ok(size as usize)
+    }
+
+    fn vector(&mut self) -> Result<Vec<u8>> {
+        let len = self.compact_size()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// A UTF-8 string with a 64-bit length prefix.
+    fn string(&mut self) -> Result<String> {
+        let len = self.u64()?;
+        if len > self.remaining() as u64 {
+            bail!("String length {} at offset {} exceeds the rest of the file", len, self.pos);
+        }
+        String::from_utf8(self.take(len as usize)?.to_vec()).context("Invalid UTF-8 string")
+    }
+
+    /// Read a field named `name`, recording its span if spans are being
+    /// recorded.
+    fn field<T>(&mut self, name: &str, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let field = Field::begin(name);
+        let offset = self.pos;
+        let value = read(self)?;
+        field.end(offset, self.pos);
+        Ok(value)
+    }
+
+    fn optional<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<Option<T>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            other => bail!("Invalid optional flag {} at offset {}", other, self.pos - 1),
+        }
+    }
+
+    /// Read a value with a `std::io::Read`-based deserializer.
+    fn read_with<T>(&mut self, read: impl FnOnce(&mut &'a [u8]) -> std::io::Result<T>) -> Result<T> {
+        let mut rest = &self.bytes[self.pos..];
+        let before = rest.len();
+        let value = read(&mut rest)?;
+        self.pos += before - rest.len();
+        Ok(value)
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use zewif::Network;
+
+use crate::{
+    annotate::{AnnotationFormat, record_spans, write_annotations},
+    file_args::{FileArgs, FileArgsLike},
+    formats::{FrontendOptions, WalletFrontend, ZecwalletFrontend, dump_source_wallet},
+    from_seed_cmd::parse_network,
+    json_output::OutputFormat,
+    mapped_file::MappedFile,
+    zecwallet::ZecwalletWallet,
+};
+
+/// Process a Zecwallet Lite wallet file
+#[derive(Debug, Args)]
+#[group(skip)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    command: Option<ZecwalletCommands>,
+
+    /// The wallet file to process
+    #[arg(required = true)]
+    file: Option<PathBuf>,
+
+    /// The network the wallet is used on: main, test or regtest. Needed only
+    /// for wallets without transparent addresses to infer it from.
+    #[arg(long, value_parser = parse_network)]
+    network: Option<Network>,
+
+    /// Read the password of an encrypted wallet from this file instead of
+    /// prompting for it.
+    #[arg(long)]
+    password_file: Option<PathBuf>,
+
+    /// How to render the source and migrated wallets.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ZecwalletCommands {
+    /// Annotate the bytes of a Zecwallet Lite wallet file with the fields
+    /// parsed from them
+    Annotate(AnnotateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AnnotateArgs {
+    #[command(flatten)]
+    file_args: FileArgs,
+
+    /// How to write the annotated fields.
+    #[arg(long, value_enum, default_value = "hexdump")]
+    format: AnnotationFormat,
+}
+
+impl FileArgsLike for AnnotateArgs {
+    fn file(&self) -> &PathBuf {
+        &self.file_args.file
+    }
+}
+
+impl crate::exec::Exec for CommandArgs {
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        let file = match (&self.command, &self.file) {
+            (Some(ZecwalletCommands::Annotate(args)), _) => {
+                return annotate_wallet_to(args.file(), args.format, out);
+            }
+            (None, Some(file)) => file,
+            (None, None) => bail!("A wallet file is required"),
+        };
+        let options = FrontendOptions {
+            network: self.network,
+            password_file: self.password_file.clone(),
+            ..Default::default()
+        };
+        let wallet = ZecwalletFrontend.parse(file, &options)?;
+        dump_source_wallet(wallet.as_ref(), None, self.format, false, out)
+    }
+}
+
+/// Annotate a Zecwallet Lite wallet file with the span of every field parsed
+/// from it. If the parse fails, the fields parsed before the failure are
+/// written ahead of the error. An encrypted wallet's keys are annotated as
+/// sealed, as the wallet is not decrypted.
+pub fn annotate_wallet_to(file: &Path, format: AnnotationFormat, out: &mut impl Write) -> Result<()> {
+    let file_data = MappedFile::open(file)?;
+    let (result, spans) = record_spans(|| ZecwalletWallet::parse(&file_data))?;
+    let name = file.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    write_annotations(&name, &file_data, &spans, format, out)?;
+    result.context("Parsing Zecwallet wallet")?;
+    Ok(())
+}
@@ -1,42 +1,124 @@
-use std::path::{Path, PathBuf};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use clap::Args;
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
 use zewif_zingo::ZingoParser;
 
-use crate::file_args::{FileArgs, FileArgsLike};
+use crate::{
+    annotate::{AnnotationFormat, record_spans, write_annotations},
+    exec::{collect_output, end_section},
+    file_args::{FileArgs, FileArgsLike, read_data},
+    json_output::{JsonObjectWriter, OutputFormat, debug_to_json},
+    zingo_remainder::ZingoRemainder,
+};
 
 /// Process a zingo wallet file
 #[derive(Debug, Args)]
 #[group(skip)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CommandArgs {
+    #[command(subcommand)]
+    command: Option<ZingoCommands>,
+
+    /// The wallet file to process
+    #[arg(required = true)]
+    file: Option<PathBuf>,
+
+    /// How to render the parsed wallet.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ZingoCommands {
+    /// Annotate the bytes of a zingo wallet file with the fields parsed
+    /// from them
+    Annotate(AnnotateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AnnotateArgs {
     #[command(flatten)]
     file_args: FileArgs,
+
+    /// How to write the annotated fields.
+    #[arg(long, value_enum, default_value = "hexdump")]
+    format: AnnotationFormat,
 }
 
-impl FileArgsLike for CommandArgs {
+impl FileArgsLike for AnnotateArgs {
     fn file(&self) -> &PathBuf {
         &self.file_args.file
     }
 }
 
 impl crate::exec::Exec for CommandArgs {
-    fn exec(&self) -> Result<String> {
-        let file = self.file();
-        dump_wallet(file)
+    fn exec(&self, out: &mut impl Write) -> Result<()> {
+        match (&self.command, &self.file) {
+            (Some(ZingoCommands::Annotate(args)), _) => annotate_wallet_to(args.file(), args.format, out),
+            (None, Some(file)) => dump_wallet_to(file, self.format, out),
+            (None, None) => bail!("A wallet file is required"),
+        }
     }
 }
 
 pub fn dump_wallet(file: &Path) -> Result<String> {
-    let file_data = std::fs::read(file)?.into();
-    let mut parser = ZingoParser::new(&file_data);
-    let wallet = parser.parse()?;
-    let mut dump = format!("{:#?}", wallet);
-    let remaining = wallet.remaining();
-    if remaining == 0 {
-        dump.push_str("\n---\n✅ Success");
+    collect_output(|out| dump_wallet_to(file, OutputFormat::Text, out))
+}
+
+/// Dump a zingo wallet file to `out`. If bytes are left unparsed, where
+/// parsing stopped and what follows are reported.
+pub fn dump_wallet_to(file: &Path, format: OutputFormat, out: &mut impl Write) -> Result<()> {
+    if format == OutputFormat::CborDiag {
+        bail!("zingo wallets are parsed but not migrated, so there is no envelope to write");
+    }
+    let file_data = read_data(file)?;
+    // Spans are recorded as the wallet is parsed, when they can be, to find
+    // the last field before any bytes left over.
+    let (wallet, spans) = if cfg!(feature = "with-context") {
+        let (wallet, spans) = record_spans(|| ZingoParser::new(&file_data).parse())?;
+        (wallet?, spans)
     } else {
-        dump.push_str(&format!("\n---\n🛑 Unparsed bytes: {}", remaining))
+        (ZingoParser::new(&file_data).parse()?, Vec::new())
+    };
+    let remaining = wallet.remaining();
+    let remainder =
+        (remaining > 0).then(|| ZingoRemainder::new(file_data.as_ref(), remaining, &spans, &wallet));
+    if format == OutputFormat::Json {
+        let mut object = JsonObjectWriter::new(out)?;
+        object.field("wallet", &debug_to_json(&wallet).context("Rendering wallet")?)?;
+        object.field("unparsed_bytes", &remaining.into())?;
+        if let Some(remainder) = &remainder {
+            object.field("unparsed", &remainder.to_json())?;
+        }
+        object.field("success", &(remaining == 0).into())?;
+        return object.finish();
     }
-    Ok(dump)
+
+    writeln!(out, "{:#?}", wallet)?;
+    end_section(out)?;
+    match remainder {
+        None => writeln!(out, "✅ Success")?,
+        Some(remainder) => {
+            let mut report = String::new();
+            remainder.write_report(&mut report)?;
+            write!(out, "{}", report)?;
+        }
+    }
+    Ok(())
+}
+
+/// Annotate a zingo wallet file with the span of every field parsed from
+/// it. If the parse fails, the fields parsed before the failure are written
+/// ahead of the error.
+pub fn annotate_wallet_to(file: &Path, format: AnnotationFormat, out: &mut impl Write) -> Result<()> {
+    let file_data = read_data(file)?;
+    let (result, spans) = record_spans(|| ZingoParser::new(&file_data).parse())?;
+    let name = file.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    write_annotations(&name, file_data.as_ref(), &spans, format, out)?;
+    result.context("Parsing zingo wallet")?;
+    Ok(())
 }
@@ -0,0 +1,228 @@
+use std::fmt::{Debug, Write};
+
+use anyhow::Result;
+use serde_json::{Value, json};
+
+use crate::annotate::{FieldSpan, hex_lines};
+
+/// The trailing bytes kept and shown; a new structure announces itself well
+/// within them.
+pub const SHOWN_BYTES: usize = 256;
+
+/// The chain names zingo writes, each as a string with a `u64` length.
+const CHAIN_NAMES: &[&str] = &["main", "test", "regtest"];
+
+/// Where the parse of a zingo wallet stopped short of the end of the file,
+/// and what the bytes it left look like. Only the first `SHOWN_BYTES` of
+/// them are kept.
+#[derive(Debug, Clone)]
+pub struct ZingoRemainder {
+    offset: usize,
+    trailing_len: usize,
+    trailing: Vec<u8>,
+    last_field: Option<String>,
+}
+
+impl ZingoRemainder {
+    /// The remainder of `bytes` once all but the last `remaining` of them
+    /// have been parsed into `wallet`. `spans` are the fields recorded by the
+    /// parse, if it recorded any.
+    pub fn new(bytes: &[u8], remaining: usize, spans: &[FieldSpan], wallet: &dyn Debug) -> Self {
+        let offset = bytes.len().saturating_sub(remaining);
+        // Of the fields ending last, the innermost is the most specific.
+        let recorded_field = spans
+            .iter()
+            .filter(|span| span.len > 0 && span.end() <= offset)
+            .max_by_key(|span| (span.end(), std::cmp::Reverse(span.len)))
+            .map(|span| span.path.clone());
+        // Without spans, the parser read every field it knows, so the last
+        // field it read is the wallet's last.
+        let last_field = recorded_field.or_else(|| last_debug_path(&format!("{:#?}", wallet)));
+        let trailing = &bytes[offset..];
+        Self {
+            offset,
+            trailing_len: trailing.len(),
+            trailing: trailing[..trailing.len().min(SHOWN_BYTES)].to_vec(),
+            last_field,
+        }
+    }
+
+    /// The offset in the file of the first unparsed byte.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of bytes left unparsed.
+    pub fn trailing_len(&self) -> usize {
+        self.trailing_len
+    }
+
+    /// The first of the bytes left unparsed, up to `SHOWN_BYTES` of them.
+    pub fn trailing(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    /// The path of the last field parsed before the remainder: the innermost
+    /// field recorded as ending there, or else the last field of the parsed
+    /// wallet.
+    pub fn last_field(&self) -> Option<&str> {
+        self.last_field.as_deref()
+    }
+
+    /// Readings of the remainder's first bytes as the structures zingo
+    /// writes, most specific first.
+    pub fn guesses(&self) -> Vec<String> {
+        let bytes = &self.trailing;
+        let mut guesses = Vec::new();
+
+        if let Some(name) = chain_name_at(bytes, 0) {
+            guesses.push(format!(
+                "The chain name {:?}, which zingo writes after the wallet's transactions",
+                name
+            ));
+        } else if let Some(at) = (1..bytes.len()).find(|&at| chain_name_at(bytes, at).is_some()) {
+            guesses.push(format!(
+                "The chain name {:?} at offset {:#x}, so the {} bytes before it likely belong to the \
+                 blocks or transactions that precede it",
+                chain_name_at(bytes, at).unwrap(),
+                self.offset + at,
+                at
+            ));
+        }
+
+        if bytes.iter().all(|&b| b == 0) {
+            guesses
+                .push("Only zero bytes: padding, or empty optional values and vectors".to_string());
+            return guesses;
+        }
+
+        if let Some(version) = read_u64(bytes).filter(|version| (1..=64).contains(version)) {
+            guesses.push(format!(
+                "A u64 version marker ({}), as opens each of zingo's versioned structures, \
+                 such as the wallet options, price info and transaction metadata",
+                version
+            ));
+        }
+
+        // Only a count the remaining bytes could hold is worth suggesting.
+        let count = read_compact_size(bytes)
+            .filter(|&(count, len)| count > 0 && count <= (bytes.len() - len) as u64);
+        if let Some((count, _)) = count {
+            guesses.push(format!(
+                "A CompactSize count ({}), as opens each of zingo's vectors",
+                count
+            ));
+        }
+
+        guesses
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "offset": self.offset,
+            "last_field": self.last_field,
+            "trailing_bytes": hex::encode(&self.trailing),
+            "guesses": self.guesses(),
+        })
+    }
+
+    pub fn write_report(&self, report: &mut String) -> Result<()> {
+        writeln!(report, "🛑 Unparsed bytes: {}", self.trailing_len)?;
+        writeln!(
+            report,
+            "- Parsing stopped at offset {:#x} ({})",
+            self.offset, self.offset
+        )?;
+        if let Some(field) = &self.last_field {
+            writeln!(report, "- Last parsed field: {}", field)?;
+        }
+        let guesses = self.guesses();
+        if guesses.is_empty() {
+            writeln!(report, "- Next structure: no known version marker")?;
+        } else {
+            writeln!(report, "- Next structure, possibly:")?;
+            for guess in guesses {
+                writeln!(report, "  * {}", guess)?;
+            }
+        }
+        writeln!(report, "- Trailing bytes:")?;
+        for line in hex_lines(&self.trailing, self.offset) {
+            writeln!(report, "  {}", line)?;
+        }
+        if self.trailing_len > self.trailing.len() {
+            writeln!(
+                report,
+                "  … {} more bytes",
+                self.trailing_len - self.trailing.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The path of the last value in a pretty-printed `Debug` tree: its field
+/// names, map keys and vector indexes, outermost first and separated by
+/// dots. Values wrapped in a tuple, such as `Some(x)`, take their wrapper's
+/// place.
+fn last_debug_path(text: &str) -> Option<String> {
+    struct Group {
+        opener: char,
+        items: usize,
+        /// The name of the group's latest item, if it has one.
+        segment: Option<String>,
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut last = None;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if matches!(line.trim_end_matches(','), "}" | "]" | ")") {
+            groups.pop();
+            continue;
+        }
+        if let Some(group) = groups.last_mut() {
+            let index = group.items.to_string();
+            group.segment = match group.opener {
+                '(' => None,
+                '[' => Some(index),
+                _ => Some(line.split_once(": ").map_or(index, |(name, _)| name.trim_matches('"').to_string())),
+            };
+            group.items += 1;
+            let path: Vec<&str> = groups.iter().filter_map(|group| group.segment.as_deref()).collect();
+            last = Some(path.join("."));
+        }
+        if let Some(opener) = line.chars().last().filter(|c| matches!(c, '{' | '[' | '(')) {
+            groups.push(Group { opener, items: 0, segment: None });
+        }
+    }
+    last.filter(|path| !path.is_empty())
+}
+
+fn read_u64(bytes: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(..8)?.try_into().unwrap()))
+}
+
+/// A Bitcoin CompactSize integer and the number of bytes it takes.
+fn read_compact_size(bytes: &[u8]) -> Option<(u64, usize)> {
+    match *bytes.first()? {
+        0xfd => Some((
+            u16::from_le_bytes(bytes.get(1..3)?.try_into().unwrap()) as u64,
+            3,
+        )),
+        0xfe => Some((
+            u32::from_le_bytes(bytes.get(1..5)?.try_into().unwrap()) as u64,
+            5,
+        )),
+        0xff => Some((read_u64(&bytes[1..])?, 9)),
+        n => Some((n as u64, 1)),
+    }
+}
+
+/// The chain name written at `at`, if there is one.
+fn chain_name_at(bytes: &[u8], at: usize) -> Option<&'static str> {
+    let len = read_u64(bytes.get(at..)?)? as usize;
+    let text = bytes.get(at + 8..)?.get(..len)?;
+    CHAIN_NAMES
+        .iter()
+        .find(|name| name.as_bytes() == text)
+        .copied()
+}
@@ -0,0 +1,198 @@
+//! # Test Suite: Field Annotation Tests
+//!
+//! These tests verify the output of the `annotate` commands for a set of
+//! recorded field spans, and the spans recorded from a real wallet:
+//! 1. The hexdump lists every field, and marks the bytes no field covers
+//! 2. ImHex and Kaitai maps place each innermost field at its offset
+//! 3. With `with-context`, parsing a Zecwallet fixture records its fields
+//! 4. With `with-context`, the fields the `zewif` parsers read from a zingo
+//!    fixture and from zcashd records are recorded
+
+use anyhow::Result;
+use zmigrate::annotate::{AnnotationFormat, FieldSpan, describe_value, write_annotations};
+
+// Import shared test utilities
+#[cfg(feature = "with-context")]
+mod test_utils;
+#[cfg(feature = "with-context")]
+use test_utils::fixtures_path;
+
+fn span(offset: usize, len: usize, path: &str) -> FieldSpan {
+    FieldSpan { offset, len, path: path.to_string() }
+}
+
+/// A version, a length-prefixed name, and two trailing bytes no field
+/// covers.
+const BYTES: &[u8] = b"\x1c\x00\x00\x00\x00\x00\x00\x00\x05hello\xde\xad";
+
+fn spans() -> Vec<FieldSpan> {
+    vec![
+        span(9, 5, "name.text"),
+        span(0, 8, "version"),
+        span(8, 6, "name"),
+        span(8, 1, "name.length"),
+    ]
+}
+
+fn annotate(format: AnnotationFormat) -> Result<String> {
+    let mut out = Vec::new();
+    write_annotations("test wallet", BYTES, &spans(), format, &mut out)?;
+    Ok(String::from_utf8(out)?)
+}
+
+#[test]
+fn test_hexdump() -> Result<()> {
+    let hexdump = annotate(AnnotationFormat::Hexdump)?;
+    let fields: Vec<(&str, &str)> = hexdump
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            (columns[0], columns[2])
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("00000000", "version"),
+            ("00000008", "name"),
+            ("00000008", "name.length"),
+            ("00000009", "name.text"),
+            ("0000000e", "(unparsed)"),
+        ]
+    );
+    assert!(hexdump.contains("1c00000000000000 (28)"));
+    assert!(hexdump.contains("68656c6c6f \"hello\""));
+    assert!(hexdump.lines().last().unwrap().ends_with("dead (44510)"));
+    Ok(())
+}
+
+#[test]
+fn test_imhex() -> Result<()> {
+    let pattern = annotate(AnnotationFormat::Imhex)?;
+    let fields: Vec<&str> = pattern.lines().filter(|line| line.starts_with("u8 ")).collect();
+    assert_eq!(
+        fields,
+        [
+            "u8 version[8] @ 0x0; // version",
+            "u8 name_length[1] @ 0x8; // name.length",
+            "u8 name_text[5] @ 0x9; // name.text",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_kaitai() -> Result<()> {
+    let ksy = annotate(AnnotationFormat::Kaitai)?;
+    assert!(ksy.starts_with("meta:\n  id: test_wallet\n  endian: le\ninstances:\n"));
+    assert!(ksy.contains("  name_text:\n    doc: \"name.text\"\n    pos: 0x9\n    size: 5\n"));
+    assert!(!ksy.contains("  name:\n"));
+    Ok(())
+}
+
+#[test]
+fn test_describe_value() {
+    assert_eq!(describe_value(&[0x2a]), "2a (42)");
+    assert_eq!(describe_value(&[0x01, 0x02, 0x03]), "010203");
+    assert_eq!(describe_value(&[0xab; 40]), format!("{}…", "ab".repeat(32)));
+}
+
+#[cfg(feature = "with-context")]
+#[test]
+fn test_annotate_zecwallet_fixture() -> Result<()> {
+    let path = fixtures_path(&["zecwallet", "plain-zecwallet-light-wallet.dat"]);
+    let mut out = Vec::new();
+    zmigrate::zecwallet_cmd::annotate_wallet_to(&path, AnnotationFormat::Hexdump, &mut out)?;
+    let hexdump = String::from_utf8(out)?;
+    let fields: Vec<(usize, usize, &str)> = hexdump
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            (usize::from_str_radix(columns[0], 16).unwrap(), columns[1].parse().unwrap(), columns[2])
+        })
+        .collect();
+
+    assert_eq!(fields[0], (0, 8, "version"));
+    assert!(hexdump.lines().nth(1).unwrap().ends_with("1900000000000000 (25)"));
+    assert_eq!(fields[1], (8, 8, "keys_version"));
+    for path in ["zkeys.count", "zkeys.0.extfvk", "zkeys.1.hdkey_num", "tkeys.0.key", "tkeys.1.address"] {
+        assert!(fields.iter().any(|&(_, _, field)| field == path), "no {} field", path);
+    }
+    // Only the trailer after the keys is left unparsed.
+    let unparsed: Vec<_> = fields.iter().filter(|&&(_, _, field)| field == "(unparsed)").collect();
+    assert_eq!(unparsed.len(), 1);
+    let (offset, len, _) = *unparsed[0];
+    assert_eq!(offset + len, std::fs::metadata(&path)?.len() as usize);
+    assert_eq!(len, 40);
+    Ok(())
+}
+
+/// The offset, length, path and value of each line of a hexdump.
+#[cfg(feature = "with-context")]
+fn hexdump_fields(hexdump: &str) -> Vec<(usize, usize, String, String)> {
+    hexdump
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let offset = usize::from_str_radix(columns.next()?, 16).ok()?;
+            let len = columns.next()?.parse().ok()?;
+            let field = columns.next()?.to_string();
+            Some((offset, len, field, columns.collect::<Vec<_>>().join(" ")))
+        })
+        .collect()
+}
+
+#[cfg(feature = "with-context")]
+#[test]
+fn test_annotate_zingo_fixture() -> Result<()> {
+    let path = fixtures_path(&["zingo", "regtest", "aaaaaaaaaaaaaaaaaaaaaaaa-v26.dat"]);
+    let mut out = Vec::new();
+    zmigrate::zingo_cmd::annotate_wallet_to(&path, AnnotationFormat::Hexdump, &mut out)?;
+    let fields = hexdump_fields(&String::from_utf8(out)?);
+
+    // The wallet opens with its u64 version, 26, and every byte after it is
+    // read by some field, as the whole wallet parses.
+    let (offset, len, field, value) = &fields[0];
+    assert_eq!((*offset, *len), (0, 8));
+    assert_ne!(field, "(unparsed)");
+    assert_eq!(value, "1a00000000000000 (26)");
+    assert!(fields.len() > 1);
+    assert!(fields.iter().all(|(_, _, field, _)| !field.is_empty() && field != "(unparsed)"));
+    let end = fields.iter().map(|(offset, len, _, _)| offset + len).max().unwrap();
+    assert_eq!(end, std::fs::metadata(&path)?.len() as usize);
+    Ok(())
+}
+
+#[cfg(feature = "with-context")]
+#[test]
+fn test_annotate_zcashd_records() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+
+    // The client version, an i32: 5050150 for zcashd 5.5.1.
+    let mut out = Vec::new();
+    zmigrate::zcashd_cmd::annotate_records_to(&path, "version", AnnotationFormat::Hexdump, &mut out)?;
+    let fields = hexdump_fields(&String::from_utf8(out)?);
+    assert_eq!(fields.len(), 1, "{:?}", fields);
+    let (offset, len, field, value) = &fields[0];
+    assert_eq!((*offset, *len), (0, 4));
+    assert_ne!(field, "(unparsed)");
+    assert!(value.starts_with("260f4d00"), "{}", value);
+
+    // An address purpose, the length-prefixed string "receive".
+    let mut out = Vec::new();
+    zmigrate::zcashd_cmd::annotate_records_to(&path, "purpose", AnnotationFormat::Hexdump, &mut out)?;
+    let fields = hexdump_fields(&String::from_utf8(out)?);
+    assert!(!fields.is_empty());
+    assert!(fields.iter().all(|(_, _, field, _)| field != "(unparsed)"), "{:?}", fields);
+    assert_eq!(fields.iter().map(|(offset, len, _, _)| offset + len).max(), Some(8));
+    Ok(())
+}
+
+#[cfg(not(feature = "with-context"))]
+#[test]
+fn test_spans_need_with_context() {
+    let error = zmigrate::annotate::record_spans(|| Ok(())).unwrap_err();
+    assert!(error.to_string().contains("--features with-context"));
+}
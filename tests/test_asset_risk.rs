@@ -0,0 +1,115 @@
+//! # Test Suite: Asset Risk Tests
+//!
+//! These tests verify the asset-risk analysis run during migration:
+//! 1. Sprout keys are flagged, and are critical for targets without Sprout support
+//! 2. A zcashd target loses nothing that zmigrate can read, and the Sapling
+//!    addresses of unified accounts are not taken for non-HD keys
+//! 3. `--fail-on-risk` refuses a migration only when funds are at risk
+//! 4. Reports are colored only when colors are enabled
+
+use anyhow::{Context, Result};
+use zmigrate::{
+    asset_risk::{AssetRisk, RiskKind, Severity, Target},
+    exec::ColorChoice,
+    zcashd_cmd::{MigrationOptions, dump_wallet_with_options},
+};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, ZcashdWallet};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn load_zcashd_wallet(path_elements: &[&str]) -> Result<ZcashdWallet> {
+    let path = fixtures_path(path_elements);
+    let db_dump = BDBDump::from_file(&path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, _) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    Ok(zcashd_wallet)
+}
+
+#[test]
+fn test_sprout_keys_are_flagged() -> Result<()> {
+    let wallet = load_zcashd_wallet(&["zcashd", "sprout", "node0_wallet.dat"])?;
+
+    let untargeted = AssetRisk::run(&wallet, &[], None)?;
+    let sprout: Vec<_> = untargeted
+        .findings()
+        .iter()
+        .filter(|f| f.kind() == RiskKind::SproutKey)
+        .collect();
+    assert!(!sprout.is_empty(), "Sprout wallet has no Sprout key findings");
+    assert!(sprout.iter().all(|f| !f.lost_at_import() && f.severity() == Severity::Warning));
+    assert!(!untargeted.has_risk());
+
+    let zingo = AssetRisk::run(&wallet, &[], Some(Target::Zingo))?;
+    assert_eq!(zingo.max_severity(), Some(Severity::Critical));
+    assert!(zingo.has_risk());
+
+    let mut report = String::new();
+    zingo.write_report(&mut report)?;
+    assert!(report.contains("Asset Risks (target: zingo)"));
+    assert!(report.contains("will be lost at import"));
+
+    Ok(())
+}
+
+#[test]
+fn test_zcashd_target_loses_nothing() -> Result<()> {
+    let wallet = load_zcashd_wallet(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"])?;
+    let risk = AssetRisk::run(&wallet, &[], Some(Target::Zcashd))?;
+    assert!(risk.findings().iter().all(|f| !f.lost_at_import()));
+    // node0's only path-less keys are its accounts' internal Sapling addresses.
+    assert!(
+        risk.findings().iter().all(|f| f.kind() != RiskKind::NonHdKey),
+        "Unexpected non-HD keys: {:?}",
+        risk.findings()
+    );
+
+    let unrecognized = AssetRisk::run(&wallet, &["mystery".to_string()], Some(Target::Zcashd))?;
+    assert!(
+        unrecognized
+            .findings()
+            .iter()
+            .any(|f| f.kind() == RiskKind::UnrecognizedRecord && f.lost_at_import())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fail_on_risk() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let options = MigrationOptions {
+        target: Some(Target::Zingo),
+        fail_on_risk: true,
+        ..Default::default()
+    };
+    let error = dump_wallet_with_options(&path, &options).unwrap_err();
+    assert!(error.to_string().contains("--fail-on-risk"));
+
+    let options = MigrationOptions { target: Some(Target::Zingo), ..Default::default() };
+    let output = dump_wallet_with_options(&path, &options)?;
+    assert!(output.contains("Asset Risks"));
+
+    // Without a target, Sprout and non-HD keys are only warnings.
+    let options = MigrationOptions { fail_on_risk: true, ..Default::default() };
+    dump_wallet_with_options(&path, &options)?;
+    Ok(())
+}
+
+#[test]
+fn test_report_colors() -> Result<()> {
+    let wallet = load_zcashd_wallet(&["zcashd", "sprout", "node0_wallet.dat"])?;
+    let risk = AssetRisk::run(&wallet, &[], Some(Target::Zingo))?;
+    let report = |choice: ColorChoice| -> Result<String> {
+        choice.apply();
+        let mut report = String::new();
+        risk.write_report(&mut report)?;
+        Ok(report)
+    };
+
+    assert!(!report(ColorChoice::Never)?.contains('\x1b'));
+    assert!(report(ColorChoice::Always)?.contains('\x1b'));
+    ColorChoice::Auto.apply();
+    Ok(())
+}
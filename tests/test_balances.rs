@@ -0,0 +1,139 @@
+//! # Test Suite: Offline Balance Computation Tests
+//!
+//! These tests verify that spentness and balances are computed from the
+//! wallet's own data:
+//! 1. Every received note is assigned a spent/unspent flag where a nullifier is known
+//! 2. Per-account and per-pool balances only count unspent notes
+//! 3. Transparent outputs paid to the wallet's keys are found, including
+//!    keypool and change addresses without an address book name
+//! 4. Address book entries count as the wallet's only with the "receive" purpose
+//! 5. The balances appear in the migration report when requested
+
+use anyhow::{Context, Result};
+use zmigrate::{
+    balances::{Balances, ValuePool, receiving_addresses},
+    note_decryption::NoteDecryption,
+    viewing_keys::WalletIvks,
+    zcashd_cmd::{self, MigrationOptions},
+};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, ZcashdWallet};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn load_zcashd_wallet(path_elements: &[&str]) -> Result<ZcashdWallet> {
+    let path = fixtures_path(path_elements);
+    let db_dump = BDBDump::from_file(&path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, _) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    Ok(zcashd_wallet)
+}
+
+/// Tests that balances are consistent with the notes they are computed from.
+#[test]
+fn test_balances_are_consistent() -> Result<()> {
+    let wallet_paths = [
+        &["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        &["zcashd", "golden-v5.6.0", "node2_wallet.dat"],
+        &["zcashd", "wallet5.dat"][..],
+    ];
+
+    for path_elements in wallet_paths {
+        let zcashd_wallet = load_zcashd_wallet(path_elements)?;
+        let zewif_top = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)?;
+        let ivks = WalletIvks::from_zcashd_wallet(&zcashd_wallet)?;
+        let decryption = NoteDecryption::run(&zewif_top, &ivks);
+
+        let balances = Balances::run(&zcashd_wallet, &zewif_top, &decryption)?;
+        assert!(balances.notes().len() >= decryption.notes().len());
+
+        let mut total = 0;
+        for pool in [ValuePool::Transparent, ValuePool::Sapling, ValuePool::Orchard] {
+            let received: u64 = balances
+                .notes()
+                .iter()
+                .filter(|n| n.pool() == pool)
+                .map(|n| n.value())
+                .sum();
+            assert!(balances.pool_balance(pool) <= received);
+            total += balances.pool_balance(pool);
+        }
+
+        let account_total: u64 = balances
+            .account_balances()
+            .values()
+            .flat_map(|pools| pools.values())
+            .sum();
+        assert_eq!(account_total, total, "Account balances don't add up for {:?}", path_elements);
+    }
+
+    Ok(())
+}
+
+/// Tests the transparent balances of fixtures whose coinbase outputs pay
+/// keypool keys that have no address book name. The expected values were
+/// computed from the wallets' `key` and `tx` records.
+#[test]
+fn test_transparent_balances() -> Result<()> {
+    let expected: [(&[&str], usize, u64); 3] = [
+        (&["zcashd", "golden-v5.6.0", "node0_wallet.dat"], 127, 74_062_560_000),
+        (&["zcashd", "golden-v5.6.0", "node2_wallet.dat"], 126, 63_375_115_000),
+        (&["zcashd", "wallet0.dat"], 50, 50_000_000_000),
+    ];
+
+    for (path_elements, received, unspent) in expected {
+        let zcashd_wallet = load_zcashd_wallet(path_elements)?;
+        let zewif_top = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)?;
+        let decryption = NoteDecryption::run(&zewif_top, &WalletIvks::from_zcashd_wallet(&zcashd_wallet)?);
+        let balances = Balances::run(&zcashd_wallet, &zewif_top, &decryption)?;
+
+        let transparent = balances.notes().iter().filter(|n| n.pool() == ValuePool::Transparent).count();
+        assert_eq!(transparent, received, "Transparent receives in {:?}", path_elements);
+        assert_eq!(
+            balances.pool_balance(ValuePool::Transparent),
+            unspent,
+            "Transparent balance of {:?}",
+            path_elements
+        );
+    }
+
+    Ok(())
+}
+
+/// Tests that "send" address book entries, which zcashd writes for the
+/// addresses the wallet paid, are not taken as the wallet's own, while
+/// "receive" entries without a key, such as imported watch-only scripts, are.
+#[test]
+fn test_receiving_addresses() {
+    let addresses = receiving_addresses(
+        ["tmKeyAddress".to_string()],
+        [
+            ("tmKeyAddress".to_string(), "receive"),
+            ("t2WatchOnlyScript".to_string(), "receive"),
+            ("tmPaidAddress".to_string(), "send"),
+        ],
+    );
+    assert!(addresses.contains("tmKeyAddress"));
+    assert!(addresses.contains("t2WatchOnlyScript"));
+    assert!(!addresses.contains("tmPaidAddress"));
+    assert_eq!(addresses.len(), 2);
+}
+
+/// Tests that the balances section is added to the migration report.
+#[test]
+fn test_balances_report() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let options = MigrationOptions {
+        balances: true,
+        ..Default::default()
+    };
+    let output = zcashd_cmd::dump_wallet_with_options(&path, &options)?;
+    assert!(output.contains("Balances (from wallet data only)"));
+    assert!(output.contains("Transparent:"));
+    assert!(!output.contains("Memo Decryption"));
+    assert!(output.lines().last().unwrap().contains("Success"));
+
+    Ok(())
+}
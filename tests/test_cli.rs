@@ -0,0 +1,41 @@
+//! # Test Suite: Command Line Parsing Tests
+//!
+//! These tests verify how the command line is parsed:
+//! 1. `--fail-on-risk` is refused without a `--target` to judge risks against
+//! 2. `--salvage` takes its mode only after `=`, so it never swallows the
+//!    wallet file that follows it
+
+use std::path::PathBuf;
+
+use zmigrate::{cli, formats::Registry, salvage::SalvageMode};
+
+fn parse(args: &[&str]) -> Result<clap::ArgMatches, clap::Error> {
+    cli::command(&Registry::with_builtin()).try_get_matches_from(args)
+}
+
+/// Tests that `--fail-on-risk` needs `--target`, since without a target no
+/// risk is critical and the flag would silently do nothing.
+#[test]
+fn test_fail_on_risk_requires_target() {
+    let error = parse(&["zmigrate", "zcashd", "--fail-on-risk", "wallet.dat"]).unwrap_err();
+    assert_eq!(error.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+
+    parse(&["zmigrate", "zcashd", "--fail-on-risk", "--target", "zingo", "wallet.dat"]).unwrap();
+    parse(&["zmigrate", "migrate", "--fail-on-risk", "--target", "zingo", "wallet.dat"]).unwrap();
+}
+
+/// Tests that `--salvage` followed by the wallet file salvages in the normal
+/// mode, rather than reading the file name as a mode.
+#[test]
+fn test_salvage_mode_requires_equals() {
+    for command in ["zcashd", "migrate"] {
+        let matches = parse(&["zmigrate", command, "--salvage", "wallet.dat"]).unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        assert_eq!(matches.get_one::<SalvageMode>("salvage"), Some(&SalvageMode::Normal));
+        assert_eq!(matches.get_one::<PathBuf>("file"), Some(&PathBuf::from("wallet.dat")));
+
+        let matches = parse(&["zmigrate", command, "--salvage=aggressive", "wallet.dat"]).unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        assert_eq!(matches.get_one::<SalvageMode>("salvage"), Some(&SalvageMode::Aggressive));
+    }
+}
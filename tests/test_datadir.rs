@@ -0,0 +1,83 @@
+//! # Test Suite: Data Directory Discovery Tests
+//!
+//! These tests verify that wallets are found in a zcashd-style data directory:
+//! 1. Every `nodeN_wallet.dat` in a fixture chain cache layout is discovered,
+//!    along with the chain cache
+//! 2. The network is taken from the `regtest` or `testnet3` subdirectory a
+//!    wallet is found in
+//! 3. The discovered wallets can be migrated into a single combined ZeWIF
+//! 4. Transactions held by more than one wallet are reported when combined
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use zewif::Network;
+use zmigrate::{
+    datadir::discover_wallets,
+    zcashd_cmd::{MigrationOptions, dump_datadir},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_discover_fixture_wallets() -> Result<()> {
+    let datadir = fixtures_path(&["zcashd", "golden-v5.6.0"]);
+    let wallets = discover_wallets(&datadir)?;
+
+    let names: Vec<_> = wallets
+        .iter()
+        .map(|w| w.path().file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        ["node0_wallet.dat", "node1_wallet.dat", "node2_wallet.dat", "node3_wallet.dat"]
+    );
+
+    let chain_cache = datadir.join("chain_cache");
+    assert!(wallets.iter().all(|w| w.chain_cache() == Some(chain_cache.as_path())));
+
+    assert!(discover_wallets(&fixtures_path(&["zingo", "testnet"])).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_network_from_subdirectory() -> Result<()> {
+    let datadir = tempfile::tempdir()?;
+    let fixture = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    std::fs::create_dir_all(datadir.path().join("regtest").join("wallets"))?;
+    std::fs::create_dir(datadir.path().join("testnet3"))?;
+    std::fs::copy(&fixture, datadir.path().join("wallet.dat"))?;
+    std::fs::copy(&fixture, datadir.path().join("regtest").join("wallets").join("extra.dat"))?;
+    std::fs::copy(&fixture, datadir.path().join("testnet3").join("wallet.dat"))?;
+
+    let wallets = discover_wallets(datadir.path())?;
+    let found: Vec<_> = wallets
+        .iter()
+        .map(|w| (w.path().strip_prefix(datadir.path()).unwrap().to_path_buf(), w.network()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (PathBuf::from("wallet.dat"), Network::Main),
+            (PathBuf::from("testnet3/wallet.dat"), Network::Test),
+            (PathBuf::from("regtest/wallets/extra.dat"), Network::Regtest),
+        ]
+    );
+    assert!(wallets.iter().all(|w| w.chain_cache().is_none()));
+    Ok(())
+}
+
+#[test]
+fn test_combined_datadir_migration() -> Result<()> {
+    let datadir = fixtures_path(&["zcashd", "golden-v5.6.0"]);
+    let output = dump_datadir(&datadir, true, &MigrationOptions::default())?;
+    assert_eq!(output.matches("Migration Quality Report").count(), 4);
+    // The four nodes hold 377 transaction records between them, of 365
+    // distinct transactions.
+    assert!(output.contains("⚠️ 12 transactions are held by more than one wallet"));
+    assert_eq!(output.matches("kept from").count(), 12);
+    assert!(output.lines().last().unwrap().contains("Success"));
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! # Test Suite: Deterministic Output Tests
+//!
+//! These tests verify that deterministic mode gives the same output for the
+//! same wallet:
+//! 1. Wallet and account IDs are derived from the wallet's contents
+//! 2. Maps and sets are printed sorted
+//! 3. Written times honor `SOURCE_DATE_EPOCH`
+//! 4. Migrating the same wallet twice serializes to the same ZeWIF bytes
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use bc_envelope::prelude::*;
+use zewif::{Network, ZewifTop};
+use zmigrate::{
+    deterministic::{canonicalize, format_utc, parse_source_date_epoch, sorted_debug},
+    formats::{FrontendOptions, Registry},
+    keydump::write_keydump_at,
+    seed_wallet::{SeedWalletOptions, build_seed_wallet},
+    zcashd_cmd::{MigrationOptions, dump_wallet_with_options},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Labels {
+    name: &'static str,
+    labels: HashMap<String, Vec<u32>>,
+    tags: HashSet<u32>,
+}
+
+#[test]
+fn test_sorted_debug() {
+    let forward = Labels {
+        name: "forward",
+        labels: (0..50).map(|i| (format!("label {}", i), vec![i, i + 1])).collect(),
+        tags: (0..50).collect(),
+    };
+    let backward = Labels {
+        name: "forward",
+        labels: (0..50).rev().map(|i| (format!("label {}", i), vec![i, i + 1])).collect(),
+        tags: (0..50).rev().collect(),
+    };
+    let sorted = sorted_debug(&forward);
+    assert_eq!(sorted, sorted_debug(&backward));
+
+    // Struct fields keep their order, and lists their own order. Entries
+    // are sorted by their text.
+    assert!(sorted.starts_with("Labels {\n    name: \"forward\",\n    labels: {\n        \"label 0\": [\n"));
+    assert!(sorted.contains("\"label 1\": [\n            1,\n            2,\n        ],"));
+    assert!(sorted.ends_with("        8,\n        9,\n    },\n}"));
+}
+
+#[test]
+fn test_format_utc() {
+    assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+    assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+    assert_eq!(format_utc(1_687_297_211), "2023-06-20T21:40:11Z");
+    assert_eq!(format_utc(4_102_444_799), "2099-12-31T23:59:59Z");
+}
+
+#[test]
+fn test_canonical_seed_wallet() -> Result<()> {
+    let options = SeedWalletOptions {
+        network: Network::Test,
+        birthday: 419_200,
+        accounts: 2,
+        transparent_gap_limit: 3,
+    };
+    let first = canonicalize(&build_seed_wallet(PHRASE, &options)?)?;
+    let second = canonicalize(&build_seed_wallet(PHRASE, &options)?)?;
+    assert_eq!(sorted_debug(&first), sorted_debug(&second));
+
+    let wallet = first.wallets().values().next().unwrap();
+    let account_ids: HashSet<_> = wallet.accounts().keys().collect();
+    assert_eq!(account_ids.len(), 2);
+
+    // A different seed gives different IDs.
+    let other_phrase = "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong";
+    let other = canonicalize(&build_seed_wallet(other_phrase, &options)?)?;
+    assert!(other.wallets().keys().all(|id| !first.wallets().contains_key(id)));
+    Ok(())
+}
+
+#[test]
+fn test_deterministic_zcashd_dump() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let options = MigrationOptions { deterministic: true, ..Default::default() };
+    let first = dump_wallet_with_options(&path, &options)?;
+    assert_eq!(first, dump_wallet_with_options(&path, &options)?);
+    Ok(())
+}
+
+fn zewif_bytes(zewif: &ZewifTop) -> Vec<u8> {
+    Envelope::from(zewif.clone()).to_cbor_data()
+}
+
+#[test]
+fn test_deterministic_zewif_bytes() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let registry = Registry::with_builtin();
+    let frontend = registry.resolve("zcashd", &path)?;
+    let options = FrontendOptions {
+        zcashd: MigrationOptions { deterministic: true, ..Default::default() },
+        ..Default::default()
+    };
+    let first = frontend.migrate(&path, &options)?;
+    let second = frontend.migrate(&path, &options)?;
+    assert_eq!(first.id(), second.id());
+    assert_eq!(zewif_bytes(&first), zewif_bytes(&second));
+
+    // Canonicalizing is idempotent, and replaces the random top-level ID.
+    assert_eq!(zewif_bytes(&canonicalize(&first)?), zewif_bytes(&first));
+    let random = frontend.migrate(&path, &FrontendOptions::default())?;
+    assert_ne!(random.id(), first.id());
+    assert_eq!(zewif_bytes(&canonicalize(&random)?), zewif_bytes(&first));
+    Ok(())
+}
+
+#[test]
+fn test_keydump_honors_source_date_epoch() -> Result<()> {
+    let options = SeedWalletOptions {
+        network: Network::Main,
+        birthday: 419_200,
+        accounts: 1,
+        transparent_gap_limit: 5,
+    };
+    let zewif = canonicalize(&build_seed_wallet(PHRASE, &options)?)?;
+
+    // The value is passed in rather than set in the environment, which the
+    // other tests in this binary read concurrently.
+    let created = parse_source_date_epoch(" 1687297211\n")?;
+    let dump = write_keydump_at(&zewif, true, created)?;
+    assert!(dump.contains("# * Created on 2023-06-20T21:40:11Z\n"));
+    assert_eq!(dump, write_keydump_at(&zewif, true, created)?);
+    assert!(parse_source_date_epoch("yesterday").is_err());
+    Ok(())
+}
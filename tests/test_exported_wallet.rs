@@ -0,0 +1,176 @@
+//! # Test Suite: Wallet Export Tests
+//!
+//! These tests verify migration of zcashd `z_exportwallet` text files:
+//! 1. Header, seed and key lines are parsed, including percent-encoded labels
+//! 2. Accounts are rebuilt from the keys' HD paths, leaving out zcashd's
+//!    legacy account and Sapling keys derived from the legacy HD seed
+//! 3. Seeds and keys are migrated into ZeWIF's typed fields and come back
+//!    out of a key dump unchanged
+
+use anyhow::Result;
+use zmigrate::{
+    exported_wallet::{ExportedKeyKind, ExportedWallet},
+    formats::{FrontendOptions, Registry},
+    keydump::write_keydump,
+};
+use zewif::{Network, SeedMaterial};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+const EXPORT: &str = r#"# Wallet dump created by Zcash v5.6.0
+# * Created on 2023-06-20T21:40:11Z
+# * Best block at time of backup was 200 (0a1b2c),
+#   mined on 2023-06-20T21:35:23Z
+
+# Emergency Recovery Information:
+# - recovery_phrase="abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+# - language=English
+# - fingerprint=zip32seedfp1example
+
+# HDSeed=000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f fingerprint=abcdef
+
+cNSJ7QpVDXqwHxy4JwCZg86nUcwhLPnTJEER7L3r929NLyM5aDsY 2023-06-20T21:35:23Z hdkeypath=m/44'/1'/0'/0/0 # addr=tmHdAddress0
+cTmP2wYjZU3r4dWgLZgVUi3USznP7Mp2DiAgbucprwCSbsJmQHKP 2023-06-20T21:35:24Z change=1 # addr=tmChangeAddress
+cQgFHNcvQuheHh46znVdbGbK711yFPMgXiFxWU3uLy7ydthze2vS 2023-06-20T21:35:25Z label=Savings%20Jar # addr=tmImportedAddress
+
+# Zkeys
+
+# Sapling keys
+
+secret-extended-key-test1qqqqqqqqqqqqqqrceqfuyt204p5x9nzg5ks7sjptsec5cey6pmt4wz8swfs4srm309hx74z8uwrqju9qyufhsnz9gncqt37z4n6u99xgumhfnpn96lzq6sgnfm32vzdetfd24f89u89s30qtgzwjzjr38rxn2zkrtaygndsyrwgsjmkfktzl7zd3yuw0krr8w6zqlfza7lwmqaxwxw2hyznfyzvmwdzeg0yaandgcaca2h26xwznj50m93sqdtg7zl2slzygm2h6kpqmm8k8d 2023-06-20T21:35:26Z # zaddr=ztestsapling1account0 hdkeypath=m/32'/1'/0'
+secret-extended-key-test1qqqqqqqqqqqqqqrk8k0v9m8phmvjykyyh7rgc7qwrwvvn4vw7xyg86axnvxzwuer7rkzllc6mvl84zu8n4klm8zyfmzqtw8qccgg03yrncdp5a555eqqkgylwcgw6qf0gmqdx95h2awqekgwarf6c8yc8sqc9vxy986m4eg94cmw2efzmp5ad4xxwkpzr406rk8kdzqwm3q8rmjvjgwxlcg09t8qc09a358y5chdrxezxehhjrchqg7d6s7x9kecy9dagf2d6datj3q33y6v9 2023-06-20T21:35:27Z # zaddr=ztestsapling1account1 hdkeypath=m/32'/1'/1'
+
+# End of dump
+"#;
+
+#[test]
+fn test_parse_export() -> Result<()> {
+    let wallet = ExportedWallet::parse(EXPORT)?;
+    assert_eq!(wallet.created_by(), Some("Zcash v5.6.0"));
+    assert_eq!(wallet.best_block(), Some("200 (0a1b2c)"));
+    assert!(wallet.recovery_phrase().unwrap().ends_with("about"));
+    assert_eq!(
+        wallet.hd_seed(),
+        Some("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+    );
+    assert_eq!(wallet.network(), Network::Test);
+
+    let keys = wallet.keys();
+    assert_eq!(keys.len(), 5);
+    assert_eq!(keys[1].purpose(), Some("change"));
+    assert_eq!(keys[2].label(), Some("Savings Jar"));
+    assert_eq!(keys[2].hd_keypath(), None);
+    assert_eq!(keys[3].kind(), ExportedKeyKind::Sapling);
+    assert_eq!(keys[3].address(), "ztestsapling1account0");
+    Ok(())
+}
+
+#[test]
+fn test_accounts_rebuilt_from_hd_paths() -> Result<()> {
+    let zewif = ExportedWallet::parse(EXPORT)?.to_zewif()?;
+    let wallet = zewif.wallets().values().next().unwrap();
+
+    let mut accounts: Vec<_> = wallet
+        .accounts()
+        .values()
+        .map(|account| (account.name().to_string(), account.addresses().len()))
+        .collect();
+    accounts.sort();
+    assert_eq!(
+        accounts,
+        [
+            ("Account #0".to_string(), 2),
+            ("Account #1".to_string(), 1),
+            ("Imported keys".to_string(), 2),
+        ]
+    );
+    Ok(())
+}
+
+/// An export of a wallet upgraded from a legacy HD seed to a mnemonic: a
+/// transparent key of zcashd's legacy account, a Sapling key derived from
+/// the legacy HD seed at its own `m/32'/1'/5'`, and a key of ZIP-32 account
+/// 0 whose seed fingerprint is that of the recovery phrase.
+const UPGRADED_EXPORT: &str = r#"# Wallet dump created by Zcash v5.6.0
+# - recovery_phrase="abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+# HDSeed=000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f fingerprint=abcdef
+
+cNSJ7QpVDXqwHxy4JwCZg86nUcwhLPnTJEER7L3r929NLyM5aDsY 2023-06-20T21:35:23Z hdkeypath=m/44'/1'/2147483647'/0/0 # addr=tmLegacyAccount
+secret-extended-key-test1qqqqqqqqqqqqqqrceqfuyt204p5x9nzg5ks7sjptsec5cey6pmt4wz8swfs4srm309hx74z8uwrqju9qyufhsnz9gncqt37z4n6u99xgumhfnpn96lzq6sgnfm32vzdetfd24f89u89s30qtgzwjzjr38rxn2zkrtaygndsyrwgsjmkfktzl7zd3yuw0krr8w6zqlfza7lwmqaxwxw2hyznfyzvmwdzeg0yaandgcaca2h26xwznj50m93sqdtg7zl2slzygm2h6kpqmm8k8d 2023-06-20T21:35:26Z # zaddr=ztestsapling1account0 hdkeypath=m/32'/1'/0' seedFp=1578d2cc93a77d6138294b2d7809cb48800408644ab512e07fe3c782783ded21
+secret-extended-key-test1qqqqqqqqqqqqqqrk8k0v9m8phmvjykyyh7rgc7qwrwvvn4vw7xyg86axnvxzwuer7rkzllc6mvl84zu8n4klm8zyfmzqtw8qccgg03yrncdp5a555eqqkgylwcgw6qf0gmqdx95h2awqekgwarf6c8yc8sqc9vxy986m4eg94cmw2efzmp5ad4xxwkpzr406rk8kdzqwm3q8rmjvjgwxlcg09t8qc09a358y5chdrxezxehhjrchqg7d6s7x9kecy9dagf2d6datj3q33y6v9 2023-06-20T21:35:27Z # zaddr=ztestsapling1legacyhd hdkeypath=m/32'/1'/5' seedFp=abcdef0000000000000000000000000000000000000000000000000000000000
+
+# End of dump
+"#;
+
+#[test]
+fn test_legacy_keys_are_not_zip32_accounts() -> Result<()> {
+    let zewif = ExportedWallet::parse(UPGRADED_EXPORT)?.to_zewif()?;
+    let wallet = zewif.wallets().values().next().unwrap();
+
+    let mut accounts: Vec<_> = wallet
+        .accounts()
+        .values()
+        .map(|account| {
+            let mut addresses: Vec<_> =
+                account.addresses().values().map(|address| address.as_string()).collect();
+            addresses.sort();
+            (account.name().to_string(), addresses)
+        })
+        .collect();
+    accounts.sort();
+    assert_eq!(
+        accounts,
+        [
+            ("Account #0".to_string(), vec!["ztestsapling1account0".to_string()]),
+            (
+                "Legacy HD keys".to_string(),
+                vec!["tmLegacyAccount".to_string(), "ztestsapling1legacyhd".to_string()]
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_seed_and_keys_are_typed() -> Result<()> {
+    let zewif = ExportedWallet::parse(EXPORT)?.to_zewif()?;
+    let wallet = zewif.wallets().values().next().unwrap();
+    match wallet.seed_material() {
+        Some(SeedMaterial::Bip39Mnemonic(mnemonic)) => assert!(mnemonic.mnemonic().ends_with("about")),
+        other => panic!("Expected the recovery phrase as seed material, found {:?}", other),
+    }
+
+    // Every exported key comes back out of a key dump of the migrated wallet,
+    // with the time it was created.
+    let dump = write_keydump(&zewif, true)?;
+    for key in ExportedWallet::parse(EXPORT)?.keys() {
+        assert!(
+            dump.contains(&format!("{} {} ", key.key(), key.created())),
+            "Key for {} missing from the dump",
+            key.address()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_keydump_round_trip() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = Registry::with_builtin()
+        .frontend("zcashd")?
+        .migrate(&path, &FrontendOptions::default())?;
+    let dump = write_keydump(&zewif, true)?;
+
+    let exported = ExportedWallet::parse(&dump)?;
+    assert_eq!(exported.keys().len(), dump.matches("addr=").count());
+    assert!(!exported.keys().is_empty());
+    let round_trip = write_keydump(&exported.to_zewif()?, true)?;
+
+    // Apart from the header, which records when each dump was written, the
+    // dump of the re-imported wallet is the same.
+    let body = |dump: &str| dump.lines().skip(2).map(str::to_string).collect::<Vec<_>>();
+    assert_eq!(body(&round_trip), body(&dump));
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! # Test Suite: Fixture Manifest Tests
+//!
+//! This test checks every wallet in `tests/fixtures` against the results
+//! recorded for it in `tests/fixtures/manifest.json`:
+//! 1. zcashd wallets: accounts, addresses, transactions, spending keys per
+//!    pool, unparsed keys and Orchard note commitment tree size
+//! 2. zingo wallets: unparsed bytes
+//! 3. Zecwallet Lite wallets: encryption, keys per pool and unparsed bytes
+//!
+//! Each difference is reported as the fixture, the field, and the recorded
+//! and actual values. A field missing from an entry is a difference too, so
+//! that every result is recorded, and a wallet that fails to parse is
+//! recorded by its error.
+//!
+//! After a change that is meant to alter these results, record the new ones
+//! with:
+//!
+//! ```sh
+//! cargo test --test test_fixture_manifest -- --bless
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
+use zewif::{ProtocolAddress, SpendingKey, ZewifTop};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser};
+use zewif_zingo::ZingoParser;
+use zmigrate::zecwallet::ZecwalletWallet;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::{fixtures_dir, fixtures_path};
+
+fn manifest_path() -> PathBuf {
+    fixtures_dir().join("manifest.json")
+}
+
+/// The wallet files under `dir`, as paths relative to the fixtures
+/// directory with `/` separators.
+fn fixture_wallets(dir: &Path, wallets: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            // Node data directories hold chain data, not wallets.
+            if path.file_name().is_some_and(|name| name != "chain_cache") {
+                fixture_wallets(&path, wallets)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "dat") {
+            let relative = path.strip_prefix(fixtures_dir())?;
+            let components: Vec<_> = relative.iter().map(|c| c.to_string_lossy()).collect();
+            wallets.push(components.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// The spending keys of a migrated wallet, counted by pool.
+fn keys_per_pool(zewif: &ZewifTop) -> BTreeMap<&'static str, u64> {
+    let mut keys = BTreeMap::new();
+    let addresses = zewif
+        .wallets()
+        .values()
+        .flat_map(|w| w.accounts().values())
+        .flat_map(|a| a.addresses().values());
+    for address in addresses {
+        let pool = match address.address() {
+            ProtocolAddress::Transparent(transparent) => {
+                transparent.spend_authority().is_some().then_some("transparent")
+            }
+            // Shielded addresses outside unified addresses are Sapling or
+            // Sprout.
+            ProtocolAddress::Shielded(shielded) => match shielded.spending_key() {
+                Some(SpendingKey::Sapling(_)) => Some("sapling"),
+                Some(_) => Some("sprout"),
+                None => None,
+            },
+            ProtocolAddress::Unified(unified) => unified
+                .sapling_component()
+                .and_then(|component| component.spending_key())
+                .map(|_| "sapling"),
+        };
+        if let Some(pool) = pool {
+            *keys.entry(pool).or_default() += 1;
+        }
+    }
+    keys
+}
+
+fn zcashd_results(path: &Path) -> Result<Value> {
+    let db_dump = BDBDump::from_file(path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, unparsed_keys) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    let zewif = zewif_zcashd::migrate_to_zewif(&zcashd_wallet).context("Migrating to ZeWIF")?;
+
+    let accounts = zewif.wallets().values().flat_map(|w| w.accounts().values());
+    Ok(json!({
+        "format": "zcashd",
+        "accounts": accounts.clone().count(),
+        "addresses": accounts.flat_map(|a| a.addresses()).count(),
+        "transactions": zewif.transactions().len(),
+        "keys": keys_per_pool(&zewif),
+        "unparsed_keys": unparsed_keys.len(),
+        "tree_size": zcashd_wallet.orchard_note_commitment_tree().tree_size(),
+    }))
+}
+
+fn zingo_results(path: &Path) -> Result<Value> {
+    let file_data = std::fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+    let wallet = ZingoParser::new(&file_data.into()).parse().context("Parsing zingo wallet")?;
+    Ok(json!({
+        "format": "zingo",
+        "unparsed_bytes": wallet.remaining(),
+    }))
+}
+
+fn zecwallet_results(path: &Path) -> Result<Value> {
+    let file_data = std::fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+    let wallet = ZecwalletWallet::parse(&file_data).context("Parsing Zecwallet wallet")?;
+    Ok(json!({
+        "format": "zecwallet",
+        "encrypted": wallet.is_encrypted(),
+        "keys": { "sapling": wallet.zkeys().len(), "transparent": wallet.tkeys().len() },
+        "unparsed_bytes": wallet.unparsed_len(),
+    }))
+}
+
+/// The results for a fixture, or its error if it cannot be read.
+fn fixture_results(fixture: &str) -> Value {
+    let path = fixtures_path(&[fixture]);
+    let results = if fixture.starts_with("zcashd/") {
+        zcashd_results(&path)
+    } else if fixture.starts_with("zingo/") {
+        zingo_results(&path)
+    } else if fixture.starts_with("zecwallet/") {
+        zecwallet_results(&path)
+    } else {
+        Err(anyhow::anyhow!("No parser for fixtures in this directory"))
+    };
+    results.unwrap_or_else(|e| json!({ "error": format!("{:#}", e) }))
+}
+
+/// Compare `expected` with `actual`, descending into nested objects, and
+/// describe each difference, including each field that was not recorded.
+fn compare(fixture: &str, field: &str, expected: &Value, actual: &Value, failures: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let path = |key: &str| if field.is_empty() { key.to_string() } else { format!("{}.{}", field, key) };
+            for (key, expected) in expected {
+                compare(fixture, &path(key), expected, actual.get(key).unwrap_or(&Value::Null), failures);
+            }
+            for (key, actual) in actual.iter().filter(|(key, _)| !expected.contains_key(*key)) {
+                failures.push(format!("{}: {}: not recorded, got {}", fixture, path(key), actual));
+            }
+        }
+        _ if expected != actual => {
+            failures.push(format!("{}: {}: recorded {}, got {}", fixture, field, expected, actual));
+        }
+        _ => {}
+    }
+}
+
+fn main() -> Result<()> {
+    // Other arguments are those of the standard test harness, and are
+    // ignored.
+    let bless = std::env::args().any(|arg| arg == "--bless");
+
+    let mut fixtures = Vec::new();
+    fixture_wallets(&fixtures_dir(), &mut fixtures)?;
+    fixtures.sort();
+
+    if bless {
+        let manifest: Map<String, Value> =
+            fixtures.iter().map(|fixture| (fixture.clone(), fixture_results(fixture))).collect();
+        let text = serde_json::to_string_pretty(&manifest)? + "\n";
+        std::fs::write(manifest_path(), text)
+            .with_context(|| format!("Writing {}", manifest_path().display()))?;
+        println!("Recorded results for {} fixtures in {}", fixtures.len(), manifest_path().display());
+        return Ok(());
+    }
+
+    let text = std::fs::read_to_string(manifest_path())
+        .with_context(|| format!("Reading {}", manifest_path().display()))?;
+    let manifest: Map<String, Value> =
+        serde_json::from_str(&text).with_context(|| format!("Parsing {}", manifest_path().display()))?;
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        match manifest.get(fixture) {
+            Some(expected) => compare(fixture, "", expected, &fixture_results(fixture), &mut failures),
+            None => failures.push(format!("{}: not in the manifest", fixture)),
+        }
+    }
+    for fixture in manifest.keys().filter(|fixture| !fixtures.contains(fixture)) {
+        failures.push(format!("{}: in the manifest but not found", fixture));
+    }
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("{}", failure);
+        }
+        bail!(
+            "{} differences from {}; if they are intended, run \
+             `cargo test --test test_fixture_manifest -- --bless`",
+            failures.len(),
+            manifest_path().display()
+        );
+    }
+    println!("{} fixtures match the manifest", fixtures.len());
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! # Test Suite: Wallet Format Registry Tests
+//!
+//! These tests verify the registry of wallet front-ends and back-ends that
+//! the generic `list-formats`, `migrate` and `export` commands use:
+//! 1. Built-in formats are registered and looked up by name
+//! 2. Each fixture's format is detected, and unknown files are rejected
+//! 3. The zcashd options given to the generic commands reach the zcashd
+//!    front-end
+//! 4. Front-ends and back-ends registered by other crates take part in
+//!    detection and export alongside the built-in ones, and built-in
+//!    front-ends bring their own subcommands
+
+use std::path::Path;
+
+use anyhow::Result;
+use zewif::ZewifTop;
+use zmigrate::{
+    formats::{
+        BackendCapabilities, BackendOptions, FrontendOptions, Registry, SourceWallet, WalletBackend,
+        WalletFrontend,
+    },
+    zcashd_cmd::MigrationOptions,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_builtin_formats() -> Result<()> {
+    let registry = Registry::with_builtin();
+    let frontends: Vec<_> = registry.frontends().map(|f| f.name()).collect();
+    assert_eq!(frontends, ["zcashd", "zingo", "exportwallet", "zecwallet", "sqlite", "ywallet"]);
+    let backends: Vec<_> = registry.backends().map(|b| b.name()).collect();
+    assert_eq!(backends, ["keydump", "sqlite"]);
+
+    assert!(registry.backend("keydump")?.capabilities().spending_keys);
+    assert!(registry.frontend("nonesuch").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_detect_fixture_formats() -> Result<()> {
+    let registry = Registry::with_builtin();
+    let cases = [
+        (fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]), "zcashd"),
+        (fixtures_path(&["zcashd", "wallet0.dat"]), "zcashd"),
+        (fixtures_path(&["zingo", "testnet", "v28.dat"]), "zingo"),
+        (fixtures_path(&["zingo", "mainnet", "hhcclaltpcckcsslpcnetblr-latest.dat"]), "zingo"),
+    ];
+    for (path, expected) in &cases {
+        assert_eq!(registry.detect(path)?.name(), *expected, "{}", path.display());
+        assert_eq!(registry.resolve("auto", path)?.name(), *expected);
+    }
+
+    let dir = tempfile::tempdir()?;
+    let export = dir.path().join("export.txt");
+    std::fs::write(&export, "# Wallet dump created by Zcash v5.6.0\n# * Created on 2024-01-01T00:00:00Z\n")?;
+    assert_eq!(registry.detect(&export)?.name(), "exportwallet");
+
+    let unknown = dir.path().join("unknown.bin");
+    std::fs::write(&unknown, b"not a wallet")?;
+    assert!(registry.detect(&unknown).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_migrate_through_registry() -> Result<()> {
+    let registry = Registry::with_builtin();
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let (zewif, report) = registry
+        .detect(&path)?
+        .parse(&path, &FrontendOptions::default())?
+        .migrate()?;
+    assert!(!zewif.wallets().is_empty());
+    assert!(report.starts_with("Migration Quality Report"));
+    assert!(!report.contains("HD Path Verification"));
+    Ok(())
+}
+
+#[test]
+fn test_zcashd_options_through_registry() -> Result<()> {
+    let registry = Registry::with_builtin();
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let options = FrontendOptions {
+        zcashd: MigrationOptions { verify_hd: true, ..Default::default() },
+        ..Default::default()
+    };
+    let (_, report) = registry.frontend("zcashd")?.parse(&path, &options)?.migrate()?;
+    assert!(report.contains("- HD Path Verification:"), "{}", report);
+    Ok(())
+}
+
+#[test]
+fn test_frontend_subcommands() {
+    let registry = Registry::with_builtin();
+    for frontend in registry.frontends() {
+        let command = frontend.command().expect("built-in front-ends have a subcommand");
+        assert_eq!(command.get_name(), frontend.name());
+    }
+
+    // Third-party front-ends are read with `migrate --from` unless they add
+    // a subcommand.
+    assert!(TestFrontend.command().is_none());
+}
+
+/// A front-end for a made-up format: files beginning with `TESTWALLET`,
+/// which migrate to an empty ZeWIF.
+struct TestFrontend;
+
+#[derive(Debug)]
+struct TestWallet;
+
+impl SourceWallet for TestWallet {
+    fn migrate(&self) -> Result<(ZewifTop, String)> {
+        Ok((ZewifTop::new(), "Migration Quality Report\n".to_string()))
+    }
+}
+
+impl WalletFrontend for TestFrontend {
+    fn name(&self) -> &str {
+        "test"
+    }
+
+    fn description(&self) -> &str {
+        "Test wallet"
+    }
+
+    fn detect(&self, path: &Path) -> Result<bool> {
+        Ok(std::fs::read(path)?.starts_with(b"TESTWALLET"))
+    }
+
+    fn parse(&self, _path: &Path, _options: &FrontendOptions) -> Result<Box<dyn SourceWallet>> {
+        Ok(Box::new(TestWallet))
+    }
+}
+
+/// A back-end that reports how many wallets it was given.
+struct CountingBackend;
+
+impl WalletBackend for CountingBackend {
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn description(&self) -> &str {
+        "Wallet count"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::default()
+    }
+
+    fn write(&self, zewif: &ZewifTop, _output: Option<&Path>, _options: &BackendOptions) -> Result<String> {
+        Ok(format!("{} wallets", zewif.wallets().len()))
+    }
+}
+
+#[test]
+fn test_third_party_formats() -> Result<()> {
+    let mut registry = Registry::with_builtin();
+    registry.register_frontend(TestFrontend).register_backend(CountingBackend);
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("wallet.test");
+    std::fs::write(&path, b"TESTWALLET")?;
+    let frontend = registry.detect(&path)?;
+    assert_eq!(frontend.name(), "test");
+
+    let zewif = frontend.migrate(&path, &FrontendOptions::default())?;
+    let written = registry.backend("count")?.write(&zewif, None, &BackendOptions::default())?;
+    assert_eq!(written, "0 wallets");
+
+    // Registering under an existing name replaces the earlier format.
+    registry.register_frontend(TestFrontend);
+    assert_eq!(registry.frontends().filter(|f| f.name() == "test").count(), 1);
+    Ok(())
+}
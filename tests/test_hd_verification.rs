@@ -0,0 +1,94 @@
+//! # Test Suite: HD Path Verification Tests
+//!
+//! These tests verify that stored keys are re-derived from the wallet's seeds:
+//! 1. Keys generated by zcashd from its seed are reproduced at their recorded paths
+//! 2. Keys that cannot be reproduced are flagged for individual export
+//! 3. The counts of reproduced and unreproducible keys of each kind match
+//!    the fixtures' key records
+
+use anyhow::{Context, Result};
+use zmigrate::hd_verification::{CheckedKey, DerivationStatus, HdVerification, KeyDerivationCheck};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, ZcashdWallet};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn load_zcashd_wallet(path_elements: &[&str]) -> Result<ZcashdWallet> {
+    let path = fixtures_path(path_elements);
+    let db_dump = BDBDump::from_file(&path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, _) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    Ok(zcashd_wallet)
+}
+
+/// The number of checks of `kind` with each outcome: reproduced, mismatched
+/// and without an HD path.
+fn outcome_counts(checks: &[KeyDerivationCheck], kind: CheckedKey) -> (usize, usize, usize) {
+    let mut counts = (0, 0, 0);
+    for check in checks.iter().filter(|c| c.kind() == kind) {
+        match check.status() {
+            DerivationStatus::Reproduced { .. } => counts.0 += 1,
+            DerivationStatus::Mismatch => counts.1 += 1,
+            DerivationStatus::NotHd => counts.2 += 1,
+        }
+    }
+    counts
+}
+
+/// Tests that the golden wallets, whose keys were all generated by zcashd,
+/// have every HD key reproduced from a seed.
+///
+/// node0 holds 59 transparent keys at `m/44'/1'/2147483647'/...`, two
+/// unified accounts with Sapling and Orchard keys, and the internal (change)
+/// Sapling addresses of those accounts, whose key metadata records no path
+/// and which are checked against their account's key. node1 holds only 52
+/// transparent keys.
+#[test]
+fn test_golden_wallet_keys_are_reproducible() -> Result<()> {
+    let expected: [(&[&str], [(CheckedKey, (usize, usize, usize)); 4]); 2] = [
+        (
+            &["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+            [
+                (CheckedKey::Transparent, (59, 0, 0)),
+                (CheckedKey::Sapling, (2, 0, 0)),
+                (CheckedKey::UnifiedSapling, (2, 0, 0)),
+                (CheckedKey::UnifiedOrchard, (2, 0, 0)),
+            ],
+        ),
+        (
+            &["zcashd", "golden-v5.6.0", "node1_wallet.dat"],
+            [
+                (CheckedKey::Transparent, (52, 0, 0)),
+                (CheckedKey::Sapling, (0, 0, 0)),
+                (CheckedKey::UnifiedSapling, (0, 0, 0)),
+                (CheckedKey::UnifiedOrchard, (0, 0, 0)),
+            ],
+        ),
+    ];
+
+    for (path_elements, kinds) in expected {
+        let wallet = load_zcashd_wallet(path_elements)?;
+        let verification = HdVerification::run(&wallet)?;
+
+        for (kind, counts) in kinds {
+            assert_eq!(
+                outcome_counts(verification.checks(), kind),
+                counts,
+                "(reproduced, mismatched, not HD) {}s in {:?}",
+                kind,
+                path_elements
+            );
+        }
+        let unreproducible: usize = kinds.iter().map(|(_, counts)| counts.1 + counts.2).sum();
+        assert_eq!(verification.unreproducible().count(), unreproducible);
+
+        let mut report = String::new();
+        verification.write_report(&mut report)?;
+        assert!(report.contains("HD Path Verification"));
+        assert_eq!(report.matches("sweep or export it individually").count(), unreproducible);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! # Test Suite: JSON Output Tests
+//!
+//! These tests verify `--format json` rendering of parsed and migrated
+//! wallets:
+//! 1. Debug trees convert to JSON by the rules in `docs/json-output.md`
+//! 2. Map keys are sorted, so the same value always gives the same JSON
+//! 3. zcashd and zingo dumps are single valid JSON objects
+//! 4. Migrated wallets are rendered from their ZeWIF envelope, as JSON or
+//!    in CBOR diagnostic notation
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde_json::{Value, json};
+use zmigrate::{
+    json_output::{OutputFormat, debug_to_json, report_to_json},
+    zcashd_cmd::{MigrationOptions, dump_wallet_to},
+    zingo_cmd,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[derive(Debug)]
+struct Hash([u8; 4]);
+
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Receiver {
+    Transparent(String),
+    Shielded { diversifier: u32 },
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Account {
+    name: Option<String>,
+    birthday: Option<u32>,
+    id: Hash,
+    receivers: Vec<Receiver>,
+    labels: HashMap<String, i64>,
+    tags: HashSet<&'static str>,
+    pair: (u8, char),
+    unit: (),
+}
+
+#[test]
+fn test_debug_to_json() -> Result<()> {
+    let account = Account {
+        name: Some("Savings \"main\"".to_string()),
+        birthday: None,
+        id: Hash([0, 1, 2, 3]),
+        receivers: vec![Receiver::Transparent("t1abc".to_string()), Receiver::Shielded { diversifier: 7 }],
+        labels: HashMap::from([("b".to_string(), -2), ("a".to_string(), 1)]),
+        tags: HashSet::from(["z", "y"]),
+        pair: (5, 'x'),
+        unit: (),
+    };
+    let expected = json!({
+        "name": "Savings \"main\"",
+        "birthday": null,
+        "id": { "Hash": [0, 1, 2, 3] },
+        "receivers": ["t1abc", { "diversifier": 7 }],
+        "labels": { "a": 1, "b": -2 },
+        "tags": ["y", "z"],
+        "pair": [5, "x"],
+        "unit": null,
+    });
+    assert_eq!(debug_to_json(&account)?, expected);
+    Ok(())
+}
+
+/// Written the way a type named `name` writes a single word.
+struct Wrapped(&'static str, &'static str);
+
+impl std::fmt::Debug for Wrapped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.0, self.1)
+    }
+}
+
+#[test]
+fn test_scalars() -> Result<()> {
+    let height = |word| Wrapped("BlockHeight", word);
+    assert_eq!(debug_to_json(&height("2000000"))?, json!(2000000));
+    assert_eq!(debug_to_json(&height("-5"))?, json!(-5));
+    assert_eq!(debug_to_json(&height("0123"))?, json!("0123"));
+    assert_eq!(debug_to_json(&height("12345678901234567890123"))?, json!("12345678901234567890123"));
+    assert_eq!(debug_to_json(&vec![height("1.5 ZEC")])?, json!(["1.5 ZEC"]));
+    assert_eq!(debug_to_json(&Some(true))?, json!(true));
+
+    // Values above `i64::MAX` are still numbers.
+    assert_eq!(debug_to_json(&u64::MAX)?, json!(u64::MAX));
+    assert_eq!(debug_to_json(&(i64::MAX as u64 + 1))?, json!(i64::MAX as u64 + 1));
+    Ok(())
+}
+
+#[test]
+fn test_byte_types_are_strings() -> Result<()> {
+    // Hex whose digits are all decimal is still hex.
+    assert_eq!(debug_to_json(&Wrapped("Data", "2000000"))?, json!("2000000"));
+    assert_eq!(debug_to_json(&Wrapped("Blob<4>", "12345678"))?, json!("12345678"));
+    assert_eq!(debug_to_json(&Wrapped("TxId", "0123"))?, json!("0123"));
+    assert_eq!(debug_to_json(&Wrapped("u256", "00ab"))?, json!("00ab"));
+    Ok(())
+}
+
+#[test]
+fn test_deterministic_key_order() -> Result<()> {
+    let map: HashMap<String, u32> = (0..100).map(|i| (format!("key{}", i), i)).collect();
+    let first = serde_json::to_string(&debug_to_json(&map)?)?;
+    let reordered: HashMap<String, u32> = (0..100).rev().map(|i| (format!("key{}", i), i)).collect();
+    assert_eq!(first, serde_json::to_string(&debug_to_json(&reordered)?)?);
+    Ok(())
+}
+
+#[test]
+fn test_report_to_json() {
+    let report = "Migration Quality Report\n- Addresses: 3/3 preserved\n  * \x1b[31mLost\x1b[39m\n";
+    assert_eq!(
+        report_to_json(report),
+        json!(["Migration Quality Report", "- Addresses: 3/3 preserved", "  * Lost"])
+    );
+}
+
+#[test]
+fn test_zcashd_json_dump() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut output = Vec::new();
+    dump_wallet_to(&path, &MigrationOptions::default(), OutputFormat::Json, &mut output)?;
+    let dump: Value = serde_json::from_slice(&output)?;
+    assert!(dump["source_wallet"].is_object());
+    // Converted from the envelope, whose nodes hold their subject.
+    assert!(dump["migrated_wallet"].get("subject").is_some());
+    let report = dump["report"].as_array().expect("report lines");
+    assert!(report.iter().any(|line| line == "Migration Quality Report"));
+    assert_eq!(dump["success"], true);
+    Ok(())
+}
+
+#[test]
+fn test_zcashd_json_dump_failed_migration() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let options = MigrationOptions { require_no_sprout: true, ..Default::default() };
+    let mut output = Vec::new();
+    let error = dump_wallet_to(&path, &options, OutputFormat::Json, &mut output).unwrap_err();
+    let dump: Value = serde_json::from_slice(&output)?;
+    assert!(dump["source_wallet"].is_object());
+    assert!(dump.get("migrated_wallet").is_none());
+    assert_eq!(dump["error"], format!("{:#}", error));
+    assert_eq!(dump["success"], false);
+    Ok(())
+}
+
+#[test]
+fn test_zingo_json_dump() -> Result<()> {
+    let path = fixtures_path(&["zingo", "testnet", "v27.dat"]);
+    let mut output = Vec::new();
+    zingo_cmd::dump_wallet_to(&path, OutputFormat::Json, &mut output)?;
+    let dump: Value = serde_json::from_slice(&output)?;
+    assert!(dump["wallet"].is_object());
+    assert_eq!(dump["success"], dump["unparsed_bytes"] == 0);
+    Ok(())
+}
+
+#[test]
+fn test_zcashd_cbor_diag_dump() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut output = Vec::new();
+    dump_wallet_to(&path, &MigrationOptions::default(), OutputFormat::CborDiag, &mut output)?;
+    let output = String::from_utf8(output)?;
+    // The source wallet is still debug text, and the migrated wallet is a
+    // tagged envelope.
+    assert!(output.contains("Source wallet:\n"));
+    assert!(output.contains("Migrated wallet:\n200("));
+    assert!(output.contains("Migration Quality Report"));
+    Ok(())
+}
+
+#[test]
+fn test_zingo_cbor_diag_rejected() {
+    let path = fixtures_path(&["zingo", "testnet", "v27.dat"]);
+    let mut output = Vec::new();
+    let error = zingo_cmd::dump_wallet_to(&path, OutputFormat::CborDiag, &mut output).unwrap_err();
+    assert!(error.to_string().contains("not migrated"));
+    assert!(output.is_empty());
+}
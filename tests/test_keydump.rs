@@ -0,0 +1,71 @@
+//! # Test Suite: Key Dump Export Tests
+//!
+//! These tests verify `export keydump` output from migrated wallets:
+//! 1. The dump is in the layout `z_importwallet` reads
+//! 2. Seeds are redacted unless `--include-seed` is given
+//! 3. Keys without a recorded creation time are dated to the genesis rescan
+//! 4. Sprout keys are written, and labels are percent-encoded as zcashd does
+
+use anyhow::Result;
+use zmigrate::{
+    exported_wallet::ExportedWallet,
+    formats::{FrontendOptions, Registry},
+    keydump::write_keydump,
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_keydump_from_zcashd_wallet() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let zewif = Registry::with_builtin()
+        .frontend("zcashd")?
+        .migrate(&path, &FrontendOptions::default())?;
+
+    let redacted = write_keydump(&zewif, false)?;
+    assert!(redacted.starts_with("# Wallet dump created by zmigrate"));
+    assert!(redacted.trim_end().ends_with("# End of dump"));
+    assert!(redacted.contains("# Sapling keys"));
+    assert!(!redacted.contains("recovery_phrase=\""));
+
+    let with_seed = write_keydump(&zewif, true)?;
+    if redacted.contains("recovery_phrase=REDACTED") {
+        assert!(with_seed.contains("recovery_phrase=\""));
+    }
+
+    // Every key line has a key, a timestamp and an address comment. The
+    // migrated wallet records no key creation times, so each key is dated
+    // to force a rescan from genesis.
+    for line in with_seed.lines().filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        assert!(fields.len() >= 4, "Malformed key line: {}", line);
+        assert_eq!(fields[1], "1970-01-01T00:00:01Z");
+        assert!(line.contains("# addr=") || line.contains("# zaddr="));
+    }
+    Ok(())
+}
+
+/// Tests that Sprout spending keys are written under `# Zkeys`, and that
+/// labels are escaped in lowercase hex like zcashd's `EncodeDumpString`.
+#[test]
+fn test_keydump_sprout_keys_and_labels() -> Result<()> {
+    const SPROUT_KEY: &str = "ST17Tt7j2RqQ85NnUt4VqeEMKLeXkUeA8pGpE9aEY9NCydzHP55H";
+    let export = format!(
+        "cQgFHNcvQuheHh46znVdbGbK711yFPMgXiFxWU3uLy7ydthze2vS 2023-06-20T21:35:25Z \
+         label=Caf%c3%a9%20No.1%25 # addr=tmImportedAddress\n\
+         {} 2023-06-20T21:35:26Z # zaddr=ztSproutAddress\n",
+        SPROUT_KEY
+    );
+    let zewif = ExportedWallet::parse(&export)?.to_zewif()?;
+    let dump = write_keydump(&zewif, false)?;
+
+    assert!(dump.contains("label=Caf%c3%a9%20No.1%25 # addr=tmImportedAddress"));
+    let zkeys = dump.find("# Zkeys").expect("No Zkeys section");
+    let sprout = dump
+        .find(&format!("{} 2023-06-20T21:35:26Z # zaddr=ztSproutAddress", SPROUT_KEY))
+        .expect("Sprout key missing from the dump");
+    assert!(zkeys < sprout && sprout < dump.find("# Sapling keys").unwrap());
+    Ok(())
+}
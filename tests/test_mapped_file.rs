@@ -0,0 +1,34 @@
+//! # Test Suite: Memory-Mapped Input Tests
+//!
+//! These tests verify reading wallet files through a memory map:
+//! 1. A mapped file has the same bytes as one read into a buffer
+//! 2. Empty files, which cannot be mapped, read as empty
+
+use anyhow::Result;
+use zmigrate::mapped_file::MappedFile;
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_mapped_file_matches_read() -> Result<()> {
+    for path_elements in [
+        &["zcashd", "wallet0.dat"][..],
+        &["zingo", "testnet", "v28.dat"][..],
+    ] {
+        let path = fixtures_path(path_elements);
+        let mapped = MappedFile::open(&path)?;
+        assert_eq!(mapped.as_bytes(), std::fs::read(&path)?.as_slice());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_empty_file() -> Result<()> {
+    let file = tempfile::NamedTempFile::new()?;
+    let mapped = MappedFile::open(file.path())?;
+    assert!(mapped.is_empty());
+    assert!(MappedFile::open(&file.path().join("missing")).is_err());
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! # Test Suite: Offline Note Decryption Tests
+//!
+//! These tests verify the optional decryption passes:
+//! 1. Wallet IVKs are collected from legacy Sapling addresses and unified accounts
+//! 2. Outputs addressed to the wallet are decrypted, and the counts are consistent
+//! 3. Sent outputs are recovered with the wallet's OVKs and filled into accounts
+//! 4. The decryption statistics appear in the migration report
+
+use anyhow::{Context, Result};
+use zmigrate::{
+    note_decryption::{NoteDecryption, Pool},
+    sent_outputs::SentOutputRecovery,
+    viewing_keys::WalletIvks,
+    zcashd_cmd::{self, MigrationOptions},
+};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser, ZcashdWallet};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+fn load_zcashd_wallet(path_elements: &[&str]) -> Result<ZcashdWallet> {
+    let path = fixtures_path(path_elements);
+    let db_dump = BDBDump::from_file(&path).context("Parsing BerkeleyDB file")?;
+    let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+    let (zcashd_wallet, _) =
+        ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+    Ok(zcashd_wallet)
+}
+
+/// Tests that the golden wallets, which received shielded funds, decrypt at
+/// least one of their own outputs, and never report more successes than outputs.
+#[test]
+fn test_trial_decryption_counts() -> Result<()> {
+    let wallet_paths = [
+        &["zcashd", "golden-v5.6.0", "node0_wallet.dat"],
+        &["zcashd", "golden-v5.6.0", "node2_wallet.dat"],
+    ];
+
+    for path_elements in wallet_paths {
+        let zcashd_wallet = load_zcashd_wallet(path_elements)?;
+        let zewif_top = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)?;
+
+        let ivks = WalletIvks::from_zcashd_wallet(&zcashd_wallet)?;
+        assert!(!ivks.is_empty(), "No IVKs found in {:?}", path_elements);
+
+        let decryption = NoteDecryption::run(&zewif_top, &ivks);
+        assert!(
+            decryption.undecodable_transactions().is_empty(),
+            "Undecodable transactions in {:?}: {:?}",
+            path_elements,
+            decryption.undecodable_transactions()
+        );
+
+        for pool in [Pool::Sapling, Pool::Orchard] {
+            assert!(decryption.decrypted_count(pool) <= decryption.output_count(pool));
+        }
+        assert!(
+            !decryption.notes().is_empty(),
+            "No outputs decrypted in {:?}",
+            path_elements
+        );
+    }
+
+    Ok(())
+}
+
+/// Tests that the decryption statistics are included in the migration report
+/// only when the pass is requested.
+#[test]
+fn test_decryption_report() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+
+    let output = zcashd_cmd::dump_wallet(&path)?;
+    assert!(!output.contains("Memo Decryption"));
+
+    let options = MigrationOptions {
+        decrypt_memos: true,
+        ..Default::default()
+    };
+    let output = zcashd_cmd::dump_wallet_with_options(&path, &options)?;
+    assert!(output.contains("Memo Decryption"));
+    assert!(output.contains("Sapling outputs:"));
+    assert!(output.contains("Orchard outputs:"));
+    assert!(output.lines().last().unwrap().contains("Success"));
+
+    Ok(())
+}
+
+/// Tests sent-output recovery on golden node0, whose 12 Orchard sends are all
+/// recoverable with its unified accounts' OVKs (it has no Sapling outputs):
+/// every recovered output is filled into its sending account, zcashd's
+/// recipient mapping agrees, and the report covers both pools.
+#[test]
+fn test_sent_output_recovery() -> Result<()> {
+    let zcashd_wallet = load_zcashd_wallet(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"])?;
+    let mut zewif_top = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)?;
+
+    let mut recovery = SentOutputRecovery::run(&zcashd_wallet, &zewif_top)?;
+    assert_eq!(recovery.outputs().len(), 12);
+    for output in recovery.outputs() {
+        assert_eq!(output.pool(), Pool::Orchard);
+        assert!(zewif_top.transactions().contains_key(output.txid()));
+        assert!(!output.recipient().is_empty());
+    }
+    assert!(
+        recovery.mismatches().is_empty(),
+        "Recipient mapping mismatches: {:?}",
+        recovery.mismatches()
+    );
+
+    recovery.fill_sent_outputs(&mut zewif_top)?;
+    assert_eq!(recovery.unassigned_outputs().count(), 0);
+    let (sapling_sent, orchard_sent) = zewif_top
+        .wallets()
+        .values()
+        .flat_map(|w| w.accounts().values())
+        .fold((0, 0), |(sapling, orchard), a| {
+            (sapling + a.sapling_sent_outputs().len(), orchard + a.orchard_sent_outputs().len())
+        });
+    assert_eq!((sapling_sent, orchard_sent), (0, 12));
+
+    let mut report = String::new();
+    recovery.write_report(&mut report)?;
+    assert!(report.contains("Sapling outputs: 0/0 recovered"));
+    assert!(report.contains("Orchard outputs: 12/34 recovered"));
+    assert!(report.contains("Recipient mapping: consistent"));
+    assert!(!report.contains("no migrated sending account"));
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! # Test Suite: Wallet Salvage Tests
+//!
+//! These tests verify salvage of damaged zcashd wallet files:
+//! 1. An undamaged wallet salvages with no lost pages
+//! 2. Damaged pages are reported, and the readable records are still recovered
+//! 3. Truncated files and corrupt metadata are scanned without panicking
+
+use anyhow::Result;
+use zmigrate::salvage::{SalvageMode, salvage_wallet, scan_pages};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+const PAGE_SIZE: usize = 4096;
+
+#[test]
+fn test_salvage_undamaged_wallet() -> Result<()> {
+    let wallet = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let (_, report) = salvage_wallet(&wallet, SalvageMode::Normal)?;
+    assert!(report.lost_pages().is_empty());
+    assert!(report.recovered().contains_key("tx"));
+    Ok(())
+}
+
+#[test]
+fn test_salvage_damaged_wallet() -> Result<()> {
+    let original = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut bytes = std::fs::read(&original)?;
+    let page_count = bytes.len() / PAGE_SIZE;
+    assert!(page_count > 10);
+
+    // Overwrite the headers of two adjacent pages near the end of the file.
+    let damaged = [page_count as u32 - 3, page_count as u32 - 2];
+    for pgno in damaged {
+        let start = pgno as usize * PAGE_SIZE;
+        bytes[start..start + 32].fill(0xff);
+    }
+    let dir = tempfile::tempdir()?;
+    let wallet = dir.path().join("wallet.dat");
+    std::fs::write(&wallet, &bytes)?;
+
+    let (_, report) = salvage_wallet(&wallet, SalvageMode::Normal)?;
+    assert_eq!(report.lost_pages(), &[damaged[0]..=damaged[1]]);
+    assert!(report.recovered().values().sum::<usize>() > 0);
+
+    let mut text = String::new();
+    report.write_report(&mut text)?;
+    assert!(text.contains("Lost pages: 2"));
+    Ok(())
+}
+
+#[test]
+fn test_scan_corrupt_metadata() {
+    // An empty file has no pages to lose.
+    assert_eq!(scan_pages(&[]), (0, vec![], Default::default()));
+
+    // Inputs too short to hold a metadata page.
+    assert_eq!(scan_pages(&[0xff; 35]).1, vec![0..=0]);
+
+    // A metadata page claiming the largest possible page count is not
+    // trusted to visit every page.
+    let mut meta = vec![0; 512];
+    meta[12..16].copy_from_slice(&[0x62, 0x31, 0x05, 0x00]);
+    meta[20..24].copy_from_slice(&512u32.to_le_bytes());
+    meta[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+    let (page_count, lost_pages, _) = scan_pages(&meta);
+    assert_eq!(page_count, u32::MAX);
+    assert_eq!(lost_pages, vec![1..=u32::MAX - 1]);
+}
@@ -0,0 +1,169 @@
+//! # Test Suite: Seed Phrase Wallet Tests
+//!
+//! These tests verify building a fresh wallet from a BIP-39 recovery phrase:
+//! 1. Phrases with bad checksums or non-English words are rejected, and
+//!    phrases in another BIP-39 language are reported as unsupported
+//! 2. Each requested ZIP-32 account gets a default unified address and its
+//!    transparent gap-limit addresses
+//! 3. The addresses are those the phrase derives to under ZIP-32 and BIP-44
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use zewif::{Network, ProtocolAddress};
+use zmigrate::seed_wallet::{SeedWalletOptions, build_seed_wallet, validate_phrase};
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+#[test]
+fn test_phrase_validation() {
+    assert!(validate_phrase(PHRASE).is_ok());
+    // Extra whitespace, as from a pasted phrase or a file, is accepted.
+    assert!(validate_phrase(&format!("  {}\n", PHRASE.replace(' ', "  "))).is_ok());
+    // The last word carries the checksum.
+    assert!(validate_phrase(&PHRASE.replace("about", "abandon")).is_err());
+    // Words outside the English word list are rejected.
+    let error = validate_phrase(&PHRASE.replace("about", "abeja")).unwrap_err();
+    assert!(error.to_string().starts_with("Invalid English BIP-39 recovery phrase"));
+    // A valid phrase in another language is named as such.
+    let italian = "abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abaco abete";
+    let error = validate_phrase(italian).unwrap_err();
+    assert!(error.to_string().contains("valid Italian BIP-39 phrase"), "{}", error);
+}
+
+#[test]
+fn test_seed_wallet_accounts() -> Result<()> {
+    let options = SeedWalletOptions {
+        network: Network::Test,
+        birthday: 419_200,
+        accounts: 2,
+        transparent_gap_limit: 3,
+    };
+    let zewif = build_seed_wallet(PHRASE, &options)?;
+    let wallet = zewif.wallets().values().next().unwrap();
+    assert_eq!(wallet.accounts().len(), 2);
+
+    let mut account_ids = Vec::new();
+    for account in wallet.accounts().values() {
+        account_ids.push(account.zip32_account_id());
+        let addresses: Vec<_> = account.addresses().values().collect();
+        assert_eq!(addresses.len(), 4);
+        let unified = addresses
+            .iter()
+            .filter(|a| matches!(a.address(), ProtocolAddress::Unified(_)))
+            .count();
+        assert_eq!(unified, 1);
+        for address in addresses {
+            let encoded = address.as_string();
+            assert!(encoded.starts_with("utest1") || encoded.starts_with("tm"), "{}", encoded);
+        }
+    }
+    account_ids.sort();
+    assert_eq!(account_ids, [Some(0), Some(1)]);
+    Ok(())
+}
+
+/// Each account's unified address and transparent addresses, by ZIP-32
+/// account index.
+fn derived_addresses(network: Network) -> Result<BTreeMap<u32, (String, Vec<String>)>> {
+    let options = SeedWalletOptions {
+        network,
+        birthday: 419_200,
+        accounts: 2,
+        transparent_gap_limit: 3,
+    };
+    let zewif = build_seed_wallet(PHRASE, &options)?;
+    let wallet = zewif.wallets().values().next().unwrap();
+
+    let mut accounts = BTreeMap::new();
+    for account in wallet.accounts().values() {
+        let mut unified = String::new();
+        let mut transparent = Vec::new();
+        for address in account.addresses().values() {
+            match address.address() {
+                ProtocolAddress::Unified(_) => unified = address.as_string(),
+                _ => transparent.push(address.as_string()),
+            }
+        }
+        transparent.sort();
+        accounts.insert(account.zip32_account_id().unwrap(), (unified, transparent));
+    }
+    Ok(accounts)
+}
+
+fn sorted(addresses: [&str; 3]) -> Vec<String> {
+    let mut addresses: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+    addresses.sort();
+    addresses
+}
+
+#[test]
+fn test_mainnet_derivation_vectors() -> Result<()> {
+    let accounts = derived_addresses(Network::Main)?;
+
+    // Account 0's Sapling default address is at diversifier index 0, so its
+    // unified address holds the first BIP-44 address, t1XVXWCv….
+    let (unified, transparent) = &accounts[&0];
+    assert_eq!(
+        unified,
+        "u1vu0zkreyef83369fn3dr94zkg2djqlcgtghxf2jmdcdmxp2z4l70dlh6yfdf5v33cteezva2c528syqtmu7uxgzss6fumh3tzjumnqh45z3yewkqplrfdz4f68aahg8x35yknyf7vjh4wy5sn4cc8edkxlef2p4q8ez6e0xuzrmr4rzff54m5t5whyzdgfg2cujtjdjlx2zqu46h7ty"
+    );
+    assert_eq!(
+        transparent,
+        &sorted([
+            "t1XVXWCvpMgBvUaed4XDqWtgQgJSu1Ghz7F",
+            "t1aQ2b1XszNVo15BguYLbQGqETBL9QZA8Jq",
+            "t1gLHX58QpcZr2SfaMhc5XbxabE455KZhoA",
+        ])
+    );
+
+    // Account 1's is at diversifier index 3, past the gap-limit addresses.
+    let (unified, transparent) = &accounts[&1];
+    assert_eq!(
+        unified,
+        "u1tcavyntv7v0gtkq7uzfnzcvfsx42ay8c6vettpcdv2p0x7ht9esc93gpk9khth43mez38yd5l03lp5jgjgj9079mvud8jv85rk8d5d7nzj2u0n7z8s3wg2rsp2nekjqge83rnjn6mnzghxxhrc6u29lu6rpwyjavy9rzsp2a0ra3lhmypkzj8nty65uupql8ke5gsrqz4nr6xv75l6y"
+    );
+    assert_eq!(
+        transparent,
+        &sorted([
+            "t1Hxm2pmTLYuKhyLeZoSPjsHPFLWePSTDka",
+            "t1dePtWrPKGnsip6VvGuz2WheuhuW1gyGds",
+            "t1fwN1xkdWgMYDm6KnbxQUiPW9Jm5Hm8w2b",
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_testnet_derivation_vectors() -> Result<()> {
+    let accounts = derived_addresses(Network::Test)?;
+
+    let (unified, transparent) = &accounts[&0];
+    assert_eq!(
+        unified,
+        "utest1jede2g2usu3wh6v7r52k649aj2esp6tf24k7qn73jh0t8zy2lr7qw6caal28jah2tjwkcqycnuzcrx42mh5z78wrlw9hjtsl30x4f0fwff6smm6z3fulh8kht6ml562l3tr8jhqg2kf6sx5cwg0zvukxlmqnyjxjmkc6avyhmtehxlpjy7n0nn40a8nt8sx9m2vvd8cgut0vwyuc8s4"
+    );
+    assert_eq!(
+        transparent,
+        &sorted([
+            "tmF1xjfhsSzhy55dmhorzTnKjtHhZmPKzts",
+            "tmV1zYhR2xisn6VWdCNKHpeD4S7L1U1nPH6",
+            "tmGf9zpCKEuyJqLcxX1kt4GdS4d5fMoLkyj",
+        ])
+    );
+
+    let (unified, transparent) = &accounts[&1];
+    assert_eq!(
+        unified,
+        "utest1naclhuvzegh5h80mzuv56y5yvhhsx3djkxzwter7gag58v0vdl2huqpj9k7qgnvx9sequqvp4vruhvd6xh2vh7ynr4tk25rvfdntjf86fq6kpewe5rea7050d9asewnlqagxqynj3tvy96ee8n9mgqrd7mrdy9h98qz3srhmtyhs7xwf34mky2e8kxaw5qydvh8uznqzrtv35ntpkef"
+    );
+    assert_eq!(
+        transparent,
+        &sorted([
+            "tmWgoFsWH9MuH6iADAcDqp8boc4MgaP2SfK",
+            "tmG8SYpP2RiH5LsYE9T2Vv89Xbqtc9sGJ7a",
+            "tmShH4FzWRwpXnYH6NcNd4YhD633DJU643Y",
+        ])
+    );
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! # Test Suite: Sprout Fund Tests
+//!
+//! These tests verify Sprout fund detection in the `sprout` fixture wallets,
+//! each of which holds a single 50 ZEC Sprout note:
+//! 1. Sprout keys and notes are found and their value totaled
+//! 2. `--require-no-sprout` refuses to migrate a wallet with Sprout value
+
+use anyhow::{Context, Result};
+use zmigrate::{
+    sprout::SproutFunds,
+    zcashd_cmd::{MigrationOptions, dump_wallet_with_options},
+};
+use zewif_zcashd::{BDBDump, ZcashdDump, ZcashdParser};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+const SPROUT_WALLETS: [&[&str]; 4] = [
+    &["zcashd", "sprout", "node0_wallet.dat"],
+    &["zcashd", "sprout", "node1_wallet.dat"],
+    &["zcashd", "sprout", "node2_wallet.dat"],
+    &["zcashd", "sprout", "node3_wallet.dat"],
+];
+
+#[test]
+fn test_sprout_funds_are_found() -> Result<()> {
+    for path_elements in SPROUT_WALLETS {
+        let path = fixtures_path(path_elements);
+        let db_dump = BDBDump::from_file(&path).context("Parsing BerkeleyDB file")?;
+        let zcashd_dump = ZcashdDump::from_bdb_dump(&db_dump).context("Parsing Zcashd dump")?;
+        let (zcashd_wallet, _) =
+            ZcashdParser::parse_dump(&zcashd_dump).context("Parsing Zcashd dump")?;
+        let zewif = zewif_zcashd::migrate_to_zewif(&zcashd_wallet)?;
+
+        let sprout_funds = SproutFunds::run(&zcashd_wallet, &zewif);
+        assert!(!sprout_funds.addresses().is_empty(), "No Sprout keys in {:?}", path_elements);
+        assert_eq!(
+            sprout_funds.unspent_value(),
+            50 * 100_000_000,
+            "Unexpected Sprout value in {:?}",
+            path_elements
+        );
+
+        let mut report = String::new();
+        sprout_funds.write_report(&mut report)?;
+        assert!(report.contains("50.00000000 ZEC"));
+        assert!(report.contains("ZIP-308"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_require_no_sprout() -> Result<()> {
+    let path = fixtures_path(SPROUT_WALLETS[0]);
+    let options = MigrationOptions { require_no_sprout: true, ..Default::default() };
+    let error = dump_wallet_with_options(&path, &options).unwrap_err();
+    assert!(error.to_string().contains("--require-no-sprout"));
+
+    let golden = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let output = dump_wallet_with_options(&golden, &options)?;
+    assert!(output.lines().last().unwrap().contains("Success"));
+    Ok(())
+}
@@ -0,0 +1,320 @@
+//! # Test Suite: zcash_client_sqlite Export Tests
+//!
+//! These tests verify writing a ZeWIF wallet to a new `zcash_client_sqlite`
+//! wallet database:
+//! 1. The database opens with `zcash_client_sqlite` and holds every account
+//! 2. Each account is re-derived from the seed at its ZIP-32 index, with the
+//!    same default address as the source wallet
+//! 3. A wallet transaction is decrypted with the new accounts' keys, which
+//!    records the note it received and the output it sent
+//! 4. The note commitment trees start from a lightwalletd tree state, and
+//!    accounts' own birthdays that are lost to it are reported
+//! 5. Accounts with only a UFVK are imported view-only
+//! 6. Existing files are never overwritten
+
+use anyhow::Result;
+use bip0039::{English, Mnemonic};
+use rand_core::OsRng;
+use sapling::{
+    Note, PaymentAddress, Rseed,
+    note_encryption::{SaplingDomain, sapling_note_encryption},
+    value::{NoteValue, ValueCommitTrapdoor, ValueCommitment},
+    zip32::ExtendedSpendingKey,
+};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_client_sqlite::{WalletDb, util::SystemClock};
+use zcash_keys::{encoding::encode_payment_address, keys::UnifiedSpendingKey};
+use zcash_note_encryption::Domain;
+use zcash_primitives::transaction::Transaction as ChainTransaction;
+use zcash_protocol::consensus::{BranchId, Network as ConsensusNetwork, NetworkConstants};
+use zewif::{BlockHeight, Network, ProtocolAddress, Transaction, TxId};
+use zmigrate::{
+    note_decryption::Pool,
+    seed_wallet::{SeedWalletOptions, build_seed_wallet},
+    sqlite_export::{parse_tree_state, write_sqlite_wallet},
+    sqlite_wallet::{SqliteAccountKind, SqliteWallet},
+};
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+fn seed_wallet() -> Result<zewif::ZewifTop> {
+    let options = SeedWalletOptions {
+        network: Network::Test,
+        birthday: 2_000_000,
+        accounts: 2,
+        transparent_gap_limit: 0,
+    };
+    build_seed_wallet(PHRASE, &options)
+}
+
+const RECEIVED: u64 = 50_000;
+const SENT: u64 = 20_000;
+const MINED_HEIGHT: u32 = 2_000_010;
+
+/// An empty note commitment tree, as lightwalletd encodes it.
+const EMPTY_TREE: &str = "000000";
+
+const TREE_STATE: &str = r#"{
+  "network": "test",
+  "height": "2000000",
+  "hash": "0000000000000000000000000000000000000000000000000000000000000000",
+  "time": 1660000000,
+  "saplingTree": "000000",
+  "orchardTree": "000000"
+}"#;
+
+/// The Sapling address an unrelated wallet would give out.
+fn external_address() -> PaymentAddress {
+    ExtendedSpendingKey::master(&[7; 32]).default_address().1
+}
+
+/// A Sapling output of `value` to `recipient`, in the v5 encoding, and its
+/// proof, which the wallet never checks.
+fn sapling_output(
+    recipient: PaymentAddress,
+    value: u64,
+    ovk: Option<sapling::keys::OutgoingViewingKey>,
+    rseed: u8,
+) -> Vec<u8> {
+    let note = Note::from_parts(recipient, NoteValue::from_raw(value), Rseed::AfterZip212([rseed; 32]));
+    let cv = ValueCommitment::derive(note.value(), ValueCommitTrapdoor::random(OsRng));
+    let cmu = note.cmu();
+    let encryption = sapling_note_encryption(ovk, note, [0xf6; 512], &mut OsRng);
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&cv.to_bytes());
+    bytes.extend_from_slice(&cmu.to_bytes());
+    bytes.extend_from_slice(&SaplingDomain::epk_bytes(encryption.epk()).0);
+    bytes.extend_from_slice(&encryption.encrypt_note_plaintext());
+    bytes.extend_from_slice(&encryption.encrypt_outgoing_plaintext(&cv, &cmu, &mut OsRng));
+    bytes
+}
+
+/// A v5 transaction in which account 0 of the seed wallet receives
+/// `RECEIVED` and sends `SENT` to an external address, its funds coming from
+/// outside the wallet.
+fn wallet_transaction() -> Result<Transaction> {
+    let seed = Mnemonic::<English>::from_phrase(PHRASE)?.to_seed("");
+    let usk = UnifiedSpendingKey::from_seed(&ConsensusNetwork::TestNetwork, &seed, zip32::AccountId::ZERO)
+        .map_err(|e| anyhow::anyhow!("Deriving account 0: {:?}", e))?;
+    let dfvk = usk.sapling().to_diversifiable_full_viewing_key();
+    let outputs = [
+        sapling_output(dfvk.default_address().1, RECEIVED, None, 1),
+        sapling_output(external_address(), SENT, Some(dfvk.to_ovk(zip32::Scope::External)), 2),
+    ];
+
+    let mut raw = Vec::new();
+    raw.extend_from_slice(&(5 | 1 << 31u32).to_le_bytes());
+    raw.extend_from_slice(&0x26a7270au32.to_le_bytes());
+    raw.extend_from_slice(&u32::from(BranchId::Nu5).to_le_bytes());
+    raw.extend_from_slice(&0u32.to_le_bytes());
+    raw.extend_from_slice(&(MINED_HEIGHT + 40).to_le_bytes());
+    // No transparent inputs or outputs, and no Sapling spends.
+    raw.extend_from_slice(&[0, 0, 0]);
+    raw.push(outputs.len() as u8);
+    for output in &outputs {
+        raw.extend_from_slice(output);
+    }
+    raw.extend_from_slice(&(-((RECEIVED + SENT) as i64)).to_le_bytes());
+    raw.extend_from_slice(&[0; 192 * 2]);
+    raw.extend_from_slice(&[0; 64]);
+    // No Orchard actions.
+    raw.push(0);
+
+    let txid = ChainTransaction::read(raw.as_slice(), BranchId::Nu5)?.txid();
+    let mut transaction = Transaction::new(TxId::from_bytes(*txid.as_ref()));
+    transaction.set_raw(raw.into());
+    transaction.set_mined_height(BlockHeight::from(MINED_HEIGHT));
+    Ok(transaction)
+}
+
+/// The seed wallet, with a transaction account 0 received and sent in.
+fn wallet_with_transaction() -> Result<zewif::ZewifTop> {
+    let mut zewif = seed_wallet()?;
+    let transaction = wallet_transaction()?;
+    zewif.transactions_mut().insert(transaction.txid(), transaction);
+    Ok(zewif)
+}
+
+/// Check the wallet database holds the note account 0 received and the
+/// output it sent.
+fn assert_transaction_stored(wallet: &SqliteWallet) {
+    let account_0 = wallet.accounts().iter().min_by_key(|a| a.id()).unwrap();
+    assert_eq!(wallet.transactions().len(), 1);
+    assert_eq!(wallet.transactions()[0].mined_height(), Some(MINED_HEIGHT));
+
+    let received = wallet.received_notes();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].pool(), Pool::Sapling);
+    assert_eq!(received[0].account_id(), account_0.id());
+    assert_eq!(received[0].value(), RECEIVED);
+    assert!(!received[0].is_change());
+
+    let external = encode_payment_address(
+        ConsensusNetwork::TestNetwork.hrp_sapling_payment_address(),
+        &external_address(),
+    );
+    let sent = wallet.sent_notes();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].from_account_id(), account_0.id());
+    assert_eq!(sent[0].value(), SENT);
+    assert_eq!(sent[0].to_address(), Some(external.as_str()));
+}
+
+#[test]
+fn test_export_opens_with_zcash_client_sqlite() -> Result<()> {
+    let zewif = seed_wallet()?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+
+    let report = write_sqlite_wallet(&zewif, &path, None)?;
+    assert_eq!(report.derived_accounts(), 2);
+    assert_eq!(report.imported_accounts(), 0);
+    assert!(report.skipped().is_empty(), "Skipped: {:?}", report.skipped());
+
+    let db = WalletDb::for_path(&path, ConsensusNetwork::TestNetwork, SystemClock, OsRng)?;
+    assert_eq!(db.get_account_ids()?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_export_preserves_default_addresses() -> Result<()> {
+    let zewif = seed_wallet()?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+    write_sqlite_wallet(&zewif, &path, None)?;
+
+    let exported = SqliteWallet::open(&path)?;
+    let mut indices: Vec<_> = exported.accounts().iter().map(|a| a.hd_account_index()).collect();
+    indices.sort();
+    assert_eq!(indices, vec![Some(0), Some(1)]);
+
+    let exported_addresses: Vec<_> = exported.addresses().iter().map(|a| a.address()).collect();
+    let source_addresses = zewif
+        .wallets()
+        .values()
+        .flat_map(|w| w.accounts().values())
+        .flat_map(|a| a.addresses().values())
+        .filter(|a| matches!(a.address(), ProtocolAddress::Unified(_)))
+        .map(|a| a.as_string());
+    for address in source_addresses {
+        assert!(exported_addresses.contains(&address.as_str()), "Missing {}", address);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_export_stores_transactions() -> Result<()> {
+    let zewif = wallet_with_transaction()?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+
+    let report = write_sqlite_wallet(&zewif, &path, None)?;
+    assert_eq!(report.transactions(), 1);
+    assert!(report.skipped().is_empty(), "Skipped: {:?}", report.skipped());
+
+    assert_transaction_stored(&SqliteWallet::open(&path)?);
+    Ok(())
+}
+
+#[test]
+fn test_export_from_tree_state() -> Result<()> {
+    let zewif = wallet_with_transaction()?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+
+    let tree_state = parse_tree_state(TREE_STATE)?;
+    assert_eq!(tree_state.height, 2_000_000);
+    assert_eq!(tree_state.sapling_tree, EMPTY_TREE);
+    let report = write_sqlite_wallet(&zewif, &path, Some(&tree_state))?;
+    assert!(report.from_tree_state());
+    assert_eq!(report.birthday(), 2_000_000);
+
+    // Accounts are born in the block after the tree state.
+    let exported = SqliteWallet::open(&path)?;
+    assert_eq!(exported.accounts().len(), 2);
+    for account in exported.accounts() {
+        assert_eq!(account.birthday_height(), 2_000_001);
+    }
+    assert_transaction_stored(&exported);
+
+    // The accounts were born at the tree state's height.
+    assert_eq!(report.accounts_born_before_tree_state().count(), 0);
+
+    // Without a tree state, the trees start at Sapling activation and the
+    // accounts' birthdays are discarded.
+    let path = dir.path().join("activation.sqlite");
+    let report = write_sqlite_wallet(&zewif, &path, None)?;
+    assert!(!report.from_tree_state());
+    assert_eq!(report.birthday(), 280_000);
+    assert_eq!(report.account_birthdays().len(), 2);
+    let mut text = String::new();
+    report.write_report(&mut text)?;
+    assert!(text.contains("account Account #0: recorded birthday 2000000 discarded"));
+    Ok(())
+}
+
+#[test]
+fn test_export_reports_accounts_born_before_tree_state() -> Result<()> {
+    let options = SeedWalletOptions {
+        network: Network::Test,
+        birthday: 1_999_000,
+        accounts: 2,
+        transparent_gap_limit: 0,
+    };
+    let zewif = build_seed_wallet(PHRASE, &options)?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+
+    let report = write_sqlite_wallet(&zewif, &path, Some(&parse_tree_state(TREE_STATE)?))?;
+    let early: Vec<_> = report.accounts_born_before_tree_state().collect();
+    assert_eq!(
+        early,
+        [&("Account #0".to_string(), 1_999_000), &("Account #1".to_string(), 1_999_000)]
+    );
+    let mut text = String::new();
+    report.write_report(&mut text)?;
+    assert!(text.contains("account Account #1 was born at height 1999000, before the tree state"));
+    Ok(())
+}
+
+#[test]
+fn test_export_imports_ufvk_accounts() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+    write_sqlite_wallet(&wallet_with_transaction()?, &path, None)?;
+
+    // A zcash_client_sqlite wallet keeps no seed, so its migrated accounts
+    // carry only their UFVKs.
+    let migrated = SqliteWallet::open(&path)?.to_zewif()?;
+    assert!(migrated.wallets().values().all(|w| w.seed_material().is_none()));
+
+    let path = dir.path().join("imported.sqlite");
+    let report = write_sqlite_wallet(&migrated, &path, None)?;
+    assert_eq!(report.derived_accounts(), 0);
+    assert_eq!(report.imported_accounts(), 2);
+    assert_eq!(report.transactions(), 1);
+    assert!(report.skipped().is_empty(), "Skipped: {:?}", report.skipped());
+
+    let imported = SqliteWallet::open(&path)?;
+    assert_eq!(imported.accounts().len(), 2);
+    for account in imported.accounts() {
+        assert_eq!(account.kind(), SqliteAccountKind::Imported);
+        assert!(!account.has_spend_key());
+        assert!(account.ufvk().is_some());
+    }
+    // The viewing keys still find the received note and the sent output.
+    assert_transaction_stored(&imported);
+    Ok(())
+}
+
+#[test]
+fn test_export_refuses_existing_file() -> Result<()> {
+    let zewif = seed_wallet()?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+    std::fs::write(&path, b"existing")?;
+    assert!(write_sqlite_wallet(&zewif, &path, None).is_err());
+    assert_eq!(std::fs::read(&path)?, b"existing");
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! # Test Suite: zcash_client_sqlite Wallet Tests
+//!
+//! These tests verify offline reading of `zcash_client_sqlite` wallet
+//! databases, using a database with the tables and columns zmigrate reads:
+//! 1. Accounts, addresses, transactions, notes and shardtrees are read
+//! 2. The migrated wallet preserves accounts, addresses and transactions
+
+use anyhow::Result;
+use rusqlite::Connection;
+use zewif::Network;
+use zmigrate::{
+    note_decryption::Pool,
+    sqlite_wallet::{SentPool, SqliteAccountKind, SqliteWallet},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE schemer_migrations (id BLOB PRIMARY KEY);
+CREATE TABLE accounts (
+    id INTEGER PRIMARY KEY, name TEXT, uuid BLOB, account_kind INTEGER NOT NULL,
+    hd_seed_fingerprint BLOB, hd_account_index INTEGER, ufvk TEXT, uivk TEXT NOT NULL,
+    birthday_height INTEGER NOT NULL, recover_until_height INTEGER, has_spend_key INTEGER
+);
+CREATE TABLE addresses (
+    account_id INTEGER NOT NULL, diversifier_index_be BLOB NOT NULL, address TEXT NOT NULL,
+    cached_transparent_receiver_address TEXT
+);
+CREATE TABLE transactions (
+    id_tx INTEGER PRIMARY KEY, txid BLOB NOT NULL, created TEXT, block INTEGER,
+    mined_height INTEGER, tx_index INTEGER, expiry_height INTEGER, raw BLOB, fee INTEGER
+);
+CREATE TABLE sapling_received_notes (
+    id INTEGER PRIMARY KEY, tx INTEGER NOT NULL, output_index INTEGER NOT NULL,
+    account_id INTEGER NOT NULL, value INTEGER NOT NULL, is_change INTEGER NOT NULL,
+    memo BLOB, nf BLOB, commitment_tree_position INTEGER
+);
+CREATE TABLE orchard_received_notes (
+    id INTEGER PRIMARY KEY, tx INTEGER NOT NULL, action_index INTEGER NOT NULL,
+    account_id INTEGER NOT NULL, value INTEGER NOT NULL, is_change INTEGER NOT NULL,
+    memo BLOB, nf BLOB, commitment_tree_position INTEGER
+);
+CREATE TABLE sent_notes (
+    id INTEGER PRIMARY KEY, tx INTEGER NOT NULL, output_pool INTEGER NOT NULL,
+    output_index INTEGER NOT NULL, from_account_id INTEGER NOT NULL, to_address TEXT,
+    to_account_id INTEGER, value INTEGER NOT NULL, memo BLOB
+);
+CREATE TABLE sapling_tree_shards (
+    shard_index INTEGER PRIMARY KEY, subtree_end_height INTEGER, root_hash BLOB,
+    shard_data BLOB, contains_marked INTEGER
+);
+CREATE TABLE sapling_tree_cap (cap_id INTEGER PRIMARY KEY, cap_data BLOB NOT NULL);
+CREATE TABLE sapling_tree_checkpoints (checkpoint_id INTEGER PRIMARY KEY, position INTEGER);
+";
+
+const DATA: &str = "
+INSERT INTO schemer_migrations VALUES (x'01'), (x'02');
+INSERT INTO accounts VALUES
+    (1, 'Spending', x'00112233445566778899aabbccddeeff', 0, x'abcd', 0, 'uviewtest1example',
+     'uivktest1example', 2000000, NULL, 1),
+    (2, NULL, NULL, 1, NULL, NULL, NULL, 'uivktest1watchonly', 2100000, NULL, 0);
+INSERT INTO addresses VALUES
+    (1, x'0000000000000000000000', 'utest1default', 'tmDefault'),
+    (1, x'0000000000000000000005', 'utest1diversified', NULL),
+    (2, x'0000000000000000000000', 'utest1watchonly', NULL);
+INSERT INTO transactions VALUES
+    (1, zeroblob(32), '2024-01-01', 2000010, 2000010, 3, 2000050, x'050000', 10000),
+    (2, x'0101010101010101010101010101010101010101010101010101010101010101',
+     NULL, NULL, NULL, NULL, NULL, NULL, NULL);
+INSERT INTO sapling_received_notes VALUES (1, 1, 0, 1, 50000, 0, NULL, x'aa', 7);
+INSERT INTO orchard_received_notes VALUES (1, 2, 1, 2, 20000, 0, NULL, NULL, NULL);
+INSERT INTO sent_notes VALUES (1, 1, 2, 1, 1, 'ztestsapling1recipient', NULL, 40000, NULL);
+INSERT INTO sapling_tree_shards VALUES (0, 2000100, x'00', x'0102', 1), (1, NULL, NULL, x'03', 0);
+INSERT INTO sapling_tree_cap VALUES (0, x'04');
+INSERT INTO sapling_tree_checkpoints VALUES (2000010, 7), (2000020, NULL);
+";
+
+fn create_wallet() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    let conn = Connection::open(dir.path().join("data.sqlite"))?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute_batch(DATA)?;
+    Ok(dir)
+}
+
+#[test]
+fn test_read_sqlite_wallet() -> Result<()> {
+    let dir = create_wallet()?;
+    let wallet = SqliteWallet::open(&dir.path().join("data.sqlite"))?;
+    assert_eq!(wallet.network()?, Network::Test);
+
+    let accounts = wallet.accounts();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].name(), Some("Spending"));
+    assert_eq!(accounts[0].kind(), SqliteAccountKind::Derived);
+    assert_eq!(accounts[0].hd_account_index(), Some(0));
+    assert!(accounts[0].has_spend_key());
+    assert_eq!(accounts[1].kind(), SqliteAccountKind::Imported);
+    assert_eq!(accounts[1].ufvk(), None);
+
+    assert_eq!(wallet.addresses().len(), 3);
+    assert_eq!(wallet.addresses()[0].transparent_address(), Some("tmDefault"));
+    assert_eq!(wallet.transactions().len(), 2);
+    assert_eq!(wallet.transactions()[0].fee(), Some(10000));
+
+    let notes = wallet.received_notes();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].pool(), Pool::Sapling);
+    assert_eq!(notes[0].commitment_tree_position(), Some(7));
+    assert_eq!(notes[1].pool(), Pool::Orchard);
+    assert_eq!(wallet.sent_notes()[0].pool(), SentPool::Sapling);
+
+    let trees = wallet.trees();
+    assert_eq!(trees.len(), 1);
+    assert_eq!(trees[0].shard_count(), 2);
+    assert_eq!(trees[0].marked_shard_count(), 1);
+    assert_eq!(trees[0].checkpoints(), &[(2000010, Some(7)), (2000020, None)]);
+    Ok(())
+}
+
+#[test]
+fn test_migrate_sqlite_wallet() -> Result<()> {
+    let dir = create_wallet()?;
+    let wallet = SqliteWallet::open(&dir.path().join("data.sqlite"))?;
+    let zewif = wallet.to_zewif()?;
+    assert_eq!(zewif.transactions().len(), 2);
+
+    let zewif_wallet = zewif.wallets().values().next().unwrap();
+    let mut accounts: Vec<_> = zewif_wallet
+        .accounts()
+        .values()
+        .map(|account| {
+            (
+                account.name().to_string(),
+                account.addresses().len(),
+                account.relevant_transactions().len(),
+            )
+        })
+        .collect();
+    accounts.sort();
+    assert_eq!(
+        accounts,
+        vec![("Account #2".to_string(), 1, 1), ("Spending".to_string(), 2, 1)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_reject_non_wallet_database() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("other.sqlite");
+    Connection::open(&path)?.execute_batch("CREATE TABLE other (id INTEGER);")?;
+    assert!(SqliteWallet::open(&path).is_err());
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! # Test Suite: Streaming Output Tests
+//!
+//! These tests verify that commands write their output to a sink section by
+//! section rather than building it in memory:
+//! 1. Each section is flushed as soon as it is complete
+//! 2. Output written before an error is kept in the sink
+
+use std::io::{self, Write};
+
+use anyhow::Result;
+use zmigrate::{
+    json_output::OutputFormat,
+    zcashd_cmd::{MigrationOptions, dump_wallet, dump_wallet_to},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+/// A sink that records how much had been written at each flush.
+#[derive(Default)]
+struct FlushRecorder {
+    written: Vec<u8>,
+    flushed_at: Vec<usize>,
+}
+
+impl Write for FlushRecorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed_at.push(self.written.len());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sections_are_flushed_as_written() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]);
+    let mut sink = FlushRecorder::default();
+    dump_wallet_to(&path, &MigrationOptions::default(), OutputFormat::Text, &mut sink)?;
+
+    let output = String::from_utf8(sink.written.clone())?;
+    let source_end = output.find("Migrated wallet:").unwrap();
+    assert!(output.starts_with("Source wallet:"));
+    // The source wallet was flushed before the migration began.
+    assert_eq!(sink.flushed_at.first(), Some(&source_end));
+    assert!(sink.flushed_at.len() >= 3);
+
+    // The collected form is the same output.
+    assert_eq!(dump_wallet(&path)?, output);
+    Ok(())
+}
+
+#[test]
+fn test_partial_output_before_error() -> Result<()> {
+    let path = fixtures_path(&["zcashd", "sprout", "node0_wallet.dat"]);
+    let options = MigrationOptions { require_no_sprout: true, ..Default::default() };
+    let mut sink = FlushRecorder::default();
+    let error = dump_wallet_to(&path, &options, OutputFormat::Text, &mut sink).unwrap_err();
+    assert!(error.to_string().contains("--require-no-sprout"));
+
+    // The source wallet was written and flushed before migration failed.
+    let output = String::from_utf8(sink.written)?;
+    assert!(output.starts_with("Source wallet:"));
+    assert!(!output.contains("Migrated wallet:"));
+    assert_eq!(sink.flushed_at.last(), Some(&output.len()));
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! # Test Suite: Wallet Access Tests
+//!
+//! These tests verify that wallets zcashd may still be using are not read in place:
+//! 1. A stale, unlocked `.lock` file left by a stopped zcashd does not block reading
+//! 2. Environment log files in `database/` cause the wallet to be refused
+//! 3. `--snapshot` reads a private copy of the wallet instead
+//! 4. A wallet under `wallets/` is checked against the data directory above it
+
+use anyhow::Result;
+use zmigrate::{
+    wallet_access::{WalletInUse, check_datadir_in_use, wallet_datadir},
+    zcashd_cmd::{MigrationOptions, dump_wallet_with_options},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+#[test]
+fn test_wallet_in_use_detection() -> Result<()> {
+    let datadir = tempfile::tempdir()?;
+    let wallet = datadir.path().join("wallet.dat");
+    std::fs::copy(fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]), &wallet)?;
+
+    // A cleanly stopped zcashd leaves its lock file behind, unlocked.
+    // It is inspected read-only, so a lock file we may not write is no obstacle.
+    let lock_file = datadir.path().join(".lock");
+    std::fs::write(&lock_file, [])?;
+    let mut permissions = std::fs::metadata(&lock_file)?.permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&lock_file, permissions)?;
+    assert_eq!(check_datadir_in_use(datadir.path())?, None);
+    let output = dump_wallet_with_options(&wallet, &MigrationOptions::default())?;
+    assert!(output.lines().last().unwrap().contains("Success"));
+
+    let log_dir = datadir.path().join("database");
+    std::fs::create_dir(&log_dir)?;
+    std::fs::write(log_dir.join("log.0000000001"), [0u8; 16])?;
+    assert_eq!(
+        check_datadir_in_use(datadir.path())?,
+        Some(WalletInUse::UncheckpointedLog { log_dir })
+    );
+
+    let error = dump_wallet_with_options(&wallet, &MigrationOptions::default()).unwrap_err();
+    assert!(format!("{:#}", error).contains("z_exportwallet"));
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_of_unused_wallet() -> Result<()> {
+    let wallet = fixtures_path(&["zcashd", "golden-v5.6.0", "node1_wallet.dat"]);
+    let options = MigrationOptions { snapshot: true, ..Default::default() };
+    let output = dump_wallet_with_options(&wallet, &options)?;
+    assert!(output.lines().last().unwrap().contains("Success"));
+    Ok(())
+}
+
+#[test]
+fn test_wallets_subdirectory() -> Result<()> {
+    let datadir = tempfile::tempdir()?;
+    let wallets_dir = datadir.path().join("wallets");
+    std::fs::create_dir(&wallets_dir)?;
+    let wallet = wallets_dir.join("wallet.dat");
+    std::fs::copy(fixtures_path(&["zcashd", "golden-v5.6.0", "node0_wallet.dat"]), &wallet)?;
+    assert_eq!(wallet_datadir(&wallet), Some(datadir.path()));
+
+    // The environment log lives in the data directory, not beside the wallet.
+    let log_dir = datadir.path().join("database");
+    std::fs::create_dir(&log_dir)?;
+    std::fs::write(log_dir.join("log.0000000001"), [0u8; 16])?;
+    let error = dump_wallet_with_options(&wallet, &MigrationOptions::default()).unwrap_err();
+    assert!(format!("{:#}", error).contains("z_exportwallet"));
+
+    // An explicit data directory takes precedence over the inferred one.
+    let elsewhere = tempfile::tempdir()?;
+    let options = MigrationOptions {
+        datadir: Some(elsewhere.path().to_path_buf()),
+        ..Default::default()
+    };
+    let output = dump_wallet_with_options(&wallet, &options)?;
+    assert!(output.lines().last().unwrap().contains("Success"));
+    Ok(())
+}
@@ -1,6 +1,13 @@
+//! # Test Suite: Witness Data Migration Tests
+//!
+//! These tests verify that note witnesses and memos survive migration from
+//! zcashd to ZeWIF:
+//! 1. Witness data in the source wallet appears in the migrated wallet
+//! 2. Sapling outputs keep their memo fields
+
 use anyhow::Result;
-use zmigrate::{zcashd_cmd, zingo_cmd};
 use regex::Regex;
+use zmigrate::{zcashd_cmd, zingo_cmd};
 
 // Import shared test utilities
 mod test_utils;
@@ -48,28 +55,23 @@ fn test_witness_data_migration() {
         let zcashd_section = sections[1].trim();
         let zewif_section = sections[2].trim();
 
-        // Check for witness data entries in the output
-        let has_witness_in_source = has_witness_data(zcashd_section);
-        let witness_count_in_dest = count_witness_entries(zewif_section);
-
-        println!("\nWitness Data & Memo Migration for {:?}:", path);
-        println!("- Source has witness data: {}", has_witness_in_source);
-        println!("- Destination witness entries: {}", witness_count_in_dest);
-        
-        // Check for memo field entries in the output
-        let memo_count_in_dest = count_memo_entries(zewif_section);
-        println!("- Destination memo entries: {}", memo_count_in_dest);
+        // Witness data in the source must be carried over. Some wallets
+        // legitimately have none, and then there is nothing to carry.
+        if has_witness_data(zcashd_section) {
+            assert!(
+                count_witness_entries(zewif_section) > 0,
+                "Witness data of {:?} was not migrated",
+                path
+            );
+        }
 
-        // Note: Transaction time is noted in the code but not yet stored
-        // This will be implemented in the "Extract Transaction Metadata" subtask
-        
-        // We don't want to strictly assert witness data exists because some wallets
-        // may legitimately not have any. Instead, we just log the information.
-        
-        // But we can check that memo field support is working by verifying that we have some memo entries
-        // Only assert if we have sapling outputs, which should have memo fields
+        // Sapling outputs carry memo fields, which must be preserved.
         if zewif_section.contains("SaplingOutputDescription") {
-            assert!(memo_count_in_dest > 0, "Memo fields should be present in Sapling outputs");
+            assert!(
+                count_memo_entries(zewif_section) > 0,
+                "Memo fields of {:?} should be present in Sapling outputs",
+                path
+            );
         }
     }
 }
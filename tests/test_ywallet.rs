@@ -0,0 +1,301 @@
+//! # Test Suite: YWallet Database Tests
+//!
+//! These tests verify migration of YWallet databases, using a database with
+//! the tables and columns zmigrate reads:
+//! 1. Accounts, diversified addresses, notes, messages and contacts are read
+//! 2. Accounts are grouped into one ZeWIF wallet per seed
+//! 3. Spending keys are migrated to ZeWIF's typed fields, transparent keys
+//!    from the hex zcash-sync stores or from WIF, and the Orchard spending
+//!    key, which has none, is attached to its address
+//! 4. YWallet settings are kept as attachments, contacts and properties once
+//!    for the whole database
+
+use anyhow::Result;
+use bech32::{Bech32, Hrp};
+use orchard::keys::{FullViewingKey as OrchardFullViewingKey, SpendingKey as OrchardSpendingKey};
+use rusqlite::{Connection, params};
+use sapling::zip32::ExtendedSpendingKey;
+use zewif::{Network, ProtocolAddress, SpendingKey, TransparentSpendAuthority};
+use zmigrate::ywallet::YwalletDatabase;
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+const SCHEMA: &str = "
+CREATE TABLE schema_version (id INTEGER PRIMARY KEY, version INTEGER NOT NULL);
+CREATE TABLE accounts (
+    id_account INTEGER PRIMARY KEY, name TEXT NOT NULL, seed TEXT, aindex INTEGER,
+    sk TEXT, ivk TEXT NOT NULL UNIQUE, address TEXT NOT NULL
+);
+CREATE TABLE taddrs (account INTEGER PRIMARY KEY, sk TEXT, address TEXT NOT NULL);
+CREATE TABLE orchard_addrs (account INTEGER PRIMARY KEY, sk BLOB, fvk BLOB NOT NULL);
+CREATE TABLE ua_settings (
+    account INTEGER PRIMARY KEY, transparent BOOL, sapling BOOL, orchard BOOL
+);
+CREATE TABLE diversifiers (account INTEGER PRIMARY KEY, diversifier_index BLOB NOT NULL);
+CREATE TABLE transactions (
+    id_tx INTEGER PRIMARY KEY, account INTEGER NOT NULL, txid BLOB NOT NULL, height INTEGER,
+    timestamp INTEGER, value INTEGER NOT NULL, address TEXT, memo TEXT, tx_index INTEGER
+);
+CREATE TABLE received_notes (
+    id_note INTEGER PRIMARY KEY, account INTEGER NOT NULL, position INTEGER, tx INTEGER NOT NULL,
+    height INTEGER, output_index INTEGER NOT NULL, diversifier BLOB, value INTEGER NOT NULL,
+    rcm BLOB, nf BLOB, rho BLOB, orchard BOOL, spent INTEGER, excluded BOOL
+);
+CREATE TABLE messages (
+    id INTEGER PRIMARY KEY, account INTEGER NOT NULL, id_tx INTEGER, sender TEXT,
+    recipient TEXT, subject TEXT, body TEXT, timestamp INTEGER, height INTEGER,
+    incoming BOOL, read BOOL
+);
+CREATE TABLE contacts (id INTEGER PRIMARY KEY, name TEXT NOT NULL, address TEXT NOT NULL, dirty BOOL);
+CREATE TABLE properties (name TEXT PRIMARY KEY, value TEXT NOT NULL);
+";
+
+/// The transparent key of the first account, stored in hex as zcash-sync
+/// writes it.
+const TRANSPARENT_SK: [u8; 32] = [0x11; 32];
+
+/// The transparent key of the second account, stored in WIF.
+const WIF_TRANSPARENT_SK: [u8; 32] = [0x22; 32];
+
+struct TestAccount {
+    sk: String,
+    fvk: String,
+    address: String,
+    orchard_sk: [u8; 32],
+    orchard_fvk: Vec<u8>,
+}
+
+fn test_account(seed: &[u8], account: u32) -> TestAccount {
+    let extsk = ExtendedSpendingKey::from_path(
+        &ExtendedSpendingKey::master(seed),
+        &[
+            zip32::ChildIndex::hardened(32),
+            zip32::ChildIndex::hardened(133),
+            zip32::ChildIndex::hardened(account),
+        ],
+    );
+    #[allow(deprecated)]
+    let extfvk = extsk.to_extended_full_viewing_key();
+    let mut fvk_bytes = Vec::new();
+    extfvk.write(&mut fvk_bytes).unwrap();
+    let fvk = bech32::encode::<Bech32>(Hrp::parse_unchecked("zxviews"), &fvk_bytes).unwrap();
+    let sk = bech32::encode::<Bech32>(Hrp::parse_unchecked("secret-extended-key-main"), &extsk.to_bytes())
+        .unwrap();
+    let address = zmigrate::address_encoding::encode_sapling(Network::Main, &extfvk.default_address().1);
+
+    let orchard_sk =
+        OrchardSpendingKey::from_zip32_seed(seed, 133, zip32::AccountId::try_from(account).unwrap())
+            .unwrap();
+    let orchard_fvk = OrchardFullViewingKey::from(&orchard_sk).to_bytes().to_vec();
+    TestAccount { sk, fvk, address, orchard_sk: *orchard_sk.to_bytes(), orchard_fvk }
+}
+
+/// A transparent secret key in compressed-key WIF.
+fn wif(secret: &[u8; 32]) -> String {
+    let mut payload = vec![0x80];
+    payload.extend_from_slice(secret);
+    payload.push(0x01);
+    bs58::encode(payload).with_check().into_string()
+}
+
+/// A database with two accounts from one seed, advanced to diversifier index
+/// 3 on the first, which holds its spending keys, and a third account
+/// imported from a viewing key.
+fn create_database() -> Result<tempfile::TempDir> {
+    let seed = bip0039::Mnemonic::<bip0039::English>::from_phrase(PHRASE)
+        .unwrap()
+        .to_seed("");
+    let accounts = [test_account(&seed, 0), test_account(&seed, 1), test_account(&[9; 32], 0)];
+
+    let dir = tempfile::tempdir()?;
+    let conn = Connection::open(dir.path().join("zec.db"))?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute("INSERT INTO schema_version VALUES (1, 7)", [])?;
+    for (i, account) in accounts.iter().enumerate() {
+        let id = i as i64 + 1;
+        let (seed, aindex) = if i < 2 { (Some(PHRASE), Some(i as i64)) } else { (None, None) };
+        // Only the first account keeps its spending keys.
+        let (sk, orchard_sk) = if i == 0 { (Some(&account.sk), Some(&account.orchard_sk[..])) } else { (None, None) };
+        conn.execute(
+            "INSERT INTO accounts VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, format!("Account {}", id), seed, aindex, sk, account.fvk, account.address],
+        )?;
+        conn.execute(
+            "INSERT INTO orchard_addrs VALUES (?1, ?2, ?3)",
+            params![id, orchard_sk, account.orchard_fvk],
+        )?;
+    }
+    conn.execute(
+        "INSERT INTO taddrs VALUES (1, ?1, 't1exampleaddress')",
+        params![hex::encode(TRANSPARENT_SK)],
+    )?;
+    conn.execute(
+        "INSERT INTO taddrs VALUES (2, ?1, 't1secondaddress')",
+        params![wif(&WIF_TRANSPARENT_SK)],
+    )?;
+    conn.execute_batch(
+        "
+        INSERT INTO ua_settings VALUES (1, 1, 1, 1);
+        INSERT INTO diversifiers VALUES (1, x'0300000000000000000000');
+        INSERT INTO transactions VALUES
+            (1, 1, zeroblob(32), 2000000, 1700000000, 150000, NULL, 'hello', 0),
+            (2, 2, zeroblob(32), 2000000, 1700000000, -50000, 'zs1recipient', NULL, 0);
+        INSERT INTO received_notes VALUES
+            (1, 1, 10, 1, 2000000, 0, NULL, 150000, NULL, x'aa', NULL, 0, NULL, 0),
+            (2, 1, 11, 1, 2000000, 1, NULL, 20000, NULL, x'bb', NULL, 1, 2000100, 0);
+        INSERT INTO messages VALUES
+            (1, 1, 1, 'zs1sender', NULL, 'Hi', 'hello', 1700000000, 2000000, 1, 0);
+        INSERT INTO contacts VALUES (1, 'Alice', 'zs1alice', 0);
+        INSERT INTO properties VALUES ('db_version', '7');
+        ",
+    )?;
+    Ok(dir)
+}
+
+#[test]
+fn test_read_ywallet_database() -> Result<()> {
+    let dir = create_database()?;
+    let db = YwalletDatabase::open(&dir.path().join("zec.db"))?;
+    assert_eq!(db.network()?, Network::Main);
+
+    let accounts = db.accounts();
+    assert_eq!(accounts.len(), 3);
+    assert_eq!(accounts[0].seed(), Some(PHRASE));
+    assert_eq!(accounts[1].aindex(), 1);
+    assert_eq!(accounts[0].transparent_address(), Some("t1exampleaddress"));
+    assert_eq!(accounts[1].transparent_address(), Some("t1secondaddress"));
+    assert!(accounts[0].can_spend());
+    assert!(accounts[0].orchard_spending_key()?.is_some());
+    assert!(accounts[1].orchard_spending_key()?.is_none());
+    assert!(!accounts[2].can_spend());
+
+    // Diversifier index 3 means indices 0 to 2 were considered, of which
+    // only the valid ones yield addresses; the default always does.
+    let addresses = accounts[0].diversified_addresses(Network::Main)?;
+    assert!(!addresses.is_empty() && addresses.len() <= 3);
+    assert_eq!(addresses[0], accounts[0].sapling_address());
+    assert_eq!(accounts[1].diversified_addresses(Network::Main)?.len(), 1);
+    assert!(accounts[0].orchard_address(Network::Main)?.unwrap().starts_with("u1"));
+
+    assert_eq!(db.transactions().len(), 2);
+    assert_eq!(db.notes().len(), 2);
+    assert_eq!(db.notes()[1].spent(), Some(2000100));
+    assert!(db.notes()[1].is_orchard());
+    assert_eq!(db.contacts()[0].name(), "Alice");
+    Ok(())
+}
+
+#[test]
+fn test_migrate_ywallet_database() -> Result<()> {
+    let dir = create_database()?;
+    let db = YwalletDatabase::open(&dir.path().join("zec.db"))?;
+    let zewif = db.to_zewif()?;
+
+    // The two transactions share a txid, recorded once per account.
+    assert_eq!(zewif.transactions().len(), 1);
+
+    // One wallet for the seed, and one for the imported account.
+    let mut wallets: Vec<_> = zewif
+        .wallets()
+        .values()
+        .map(|wallet| (wallet.seed_material().is_some(), wallet.accounts().len()))
+        .collect();
+    wallets.sort();
+    assert_eq!(wallets, vec![(false, 1), (true, 2)]);
+
+    // Contacts and properties are attached once, for the whole database.
+    assert_eq!(zewif.attachments().envelopes().len(), 2);
+    assert!(zewif.wallets().values().all(|wallet| wallet.attachments().envelopes().is_empty()));
+    Ok(())
+}
+
+/// The `key` assertion of an address's attachments, if it has one.
+fn attached_key(address: &zewif::Address) -> Option<String> {
+    address
+        .attachments()
+        .envelopes()
+        .iter()
+        .filter_map(|attachment| attachment.attachment_payload().ok())
+        .find_map(|payload| payload.extract_object_for_predicate::<String>("key").ok())
+}
+
+fn find_account<'a>(zewif: &'a zewif::ZewifTop, name: &str) -> &'a zewif::Account {
+    zewif
+        .wallets()
+        .values()
+        .flat_map(|wallet| wallet.accounts().values())
+        .find(|account| account.name() == name)
+        .unwrap()
+}
+
+#[test]
+fn test_migrate_ywallet_keys() -> Result<()> {
+    let dir = create_database()?;
+    let db = YwalletDatabase::open(&dir.path().join("zec.db"))?;
+    let zewif = db.to_zewif()?;
+    let seed = bip0039::Mnemonic::<bip0039::English>::from_phrase(PHRASE)
+        .unwrap()
+        .to_seed("");
+    let expected = test_account(&seed, 0);
+
+    let mut orchard_sk = None;
+    for address in find_account(&zewif, "Account 1").addresses().values() {
+        match address.address() {
+            ProtocolAddress::Shielded(shielded) => {
+                let Some(SpendingKey::Sapling(extsk)) = shielded.spending_key() else {
+                    panic!("{} has no Sapling spending key", shielded.address());
+                };
+                let encoded = bech32::encode::<Bech32>(
+                    Hrp::parse_unchecked("secret-extended-key-main"),
+                    &extsk.to_bytes(),
+                )?;
+                assert_eq!(encoded, expected.sk);
+            }
+            ProtocolAddress::Transparent(transparent) => {
+                let Some(TransparentSpendAuthority::SpendingKey(key)) = transparent.spend_authority() else {
+                    panic!("{} has no transparent spending key", transparent.address());
+                };
+                assert_eq!(key.as_ref(), &TRANSPARENT_SK[..]);
+            }
+            ProtocolAddress::Unified(_) => orchard_sk = attached_key(address),
+        }
+    }
+    assert_eq!(orchard_sk, Some(hex::encode(expected.orchard_sk)));
+
+    // The account imported from a viewing key keeps it on its default
+    // address, and has no spending key.
+    let imported = test_account(&[9; 32], 0);
+    for address in find_account(&zewif, "Account 3").addresses().values() {
+        if let ProtocolAddress::Shielded(shielded) = address.address() {
+            assert!(shielded.spending_key().is_none());
+            assert_eq!(attached_key(address), Some(imported.fvk.clone()));
+        }
+    }
+
+    // A transparent key stored in WIF rather than hex is read too.
+    let transparent = find_account(&zewif, "Account 2")
+        .addresses()
+        .values()
+        .find_map(|address| match address.address() {
+            ProtocolAddress::Transparent(transparent) => Some(transparent),
+            _ => None,
+        })
+        .unwrap();
+    let Some(TransparentSpendAuthority::SpendingKey(key)) = transparent.spend_authority() else {
+        panic!("{} has no transparent spending key", transparent.address());
+    };
+    assert_eq!(key.as_ref(), &WIF_TRANSPARENT_SK[..]);
+    Ok(())
+}
+
+#[test]
+fn test_reject_other_databases() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("data.sqlite");
+    Connection::open(&path)?.execute_batch(
+        "CREATE TABLE accounts (account INTEGER PRIMARY KEY, ufvk TEXT);
+         CREATE TABLE received_notes (id_note INTEGER PRIMARY KEY);",
+    )?;
+    assert!(YwalletDatabase::open(&path).is_err());
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! # Test Suite: Zecwallet Lite Wallet Tests
+//!
+//! These tests verify migration of Zecwallet Lite wallet files. The fixtures
+//! in `tests/fixtures/zecwallet` are a testnet wallet, saved once without and
+//! once with a password, that were written outside this crate in the layout
+//! of zecwallet-light-cli's `LightWallet::write`:
+//! 1. Plain and encrypted wallets are read, and encrypted seeds and keys are
+//!    decrypted with the wallet password
+//! 2. HD keys not derived at their recorded position are reported
+//! 3. Keys are grouped into accounts in the migrated wallet, with their
+//!    spending keys in ZeWIF's typed fields
+//! 4. A truncated wallet fails with an error naming what was being read
+
+use anyhow::Result;
+use zewif::Network;
+use zmigrate::{keydump::write_keydump, zecwallet::ZecwalletWallet};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+const PASSWORD: &str = "correct horse battery staple";
+
+/// The wallet's seed, which is the entropy of its recovery phrase.
+const ENTROPY: [u8; 32] = [0x80; 32];
+
+const PHRASE: &str = "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd \
+                      amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless";
+
+/// The wallet's keys, as the keydump back-end writes them.
+const EXTSK_0: &str = "secret-extended-key-test1q0qknnulqqqqpqqw5auqnjn8ktncuwed42wy6u9zknm2rmzg6sc2573mhm9ghxyc7auwjckh8eaxp8nzjla88994km6kgzuy2xyte2eug36k8hcy6acqvdmtda6hk0wm4lgpd6arrfmyplc4eszj8v6fm3rv0v6zs3cy8ssfy7ehfa8w0vm5f8xyn35q7nxq8qh2unuj9ehchy9f92qcegh2wq8q3u2dx9egutslslg2zgm3dqfqxtg8dcd36rzhjdls3f5ze7fjzdcxtlpu5";
+const WIFS: [&str; 2] = [
+    "cURt3p15bnroswoHid8LQeivyJV4JhLuM7a7b1ZtB9VWX4X9DWFB",
+    "cMnna9DTiJo9bEgfz63GUtRcJFD48eXco63j1v1chytFJamJsMvr",
+];
+
+/// The bytes after the keys: empty block and transaction lists, the chain
+/// name, the wallet options and the birthday.
+const TRAILER_LEN: usize = 40;
+
+fn read_fixture(name: &str) -> Result<ZecwalletWallet> {
+    let path = fixtures_path(&["zecwallet", &format!("{}-zecwallet-light-wallet.dat", name)]);
+    ZecwalletWallet::parse(&std::fs::read(path)?)
+}
+
+#[test]
+fn test_plain_zecwallet() -> Result<()> {
+    let wallet = read_fixture("plain")?;
+    assert_eq!(wallet.version(), 25);
+    assert!(!wallet.is_encrypted());
+    assert_eq!(wallet.seed(), Some(&ENTROPY));
+    assert_eq!(wallet.recovery_phrase().transpose()?.as_deref(), Some(PHRASE));
+    assert_eq!(wallet.zkeys().len(), 2);
+    assert_eq!(wallet.tkeys().len(), 2);
+    assert_eq!(wallet.unparsed_len(), TRAILER_LEN);
+    assert_eq!(wallet.network_hint(), Some(Network::Test));
+    assert!(wallet.zkeys().iter().all(|zkey| zkey.extsk().is_some()));
+    assert!(wallet.tkeys().iter().all(|tkey| tkey.key().is_some()));
+    assert_eq!(wallet.tkeys()[0].address(), "tmJ4AJYG1JBjCzcygeYgASxrLzBxCQhWHoP");
+    Ok(())
+}
+
+#[test]
+fn test_truncated_zecwallet() -> Result<()> {
+    let path = fixtures_path(&["zecwallet", "plain-zecwallet-light-wallet.dat"]);
+    let bytes = std::fs::read(path)?;
+    let keys_end = bytes.len() - TRAILER_LEN;
+    for len in 0..keys_end {
+        let error = ZecwalletWallet::parse(&bytes[..len]).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.starts_with("Reading "), "{} bytes: {}", len, message);
+    }
+    // The keys are all that is parsed; the trailer after them is not.
+    assert_eq!(ZecwalletWallet::parse(&bytes[..keys_end])?.unparsed_len(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_decrypt_zecwallet() -> Result<()> {
+    let mut wallet = read_fixture("encrypted")?;
+    assert!(wallet.is_encrypted());
+    assert_eq!(wallet.seed(), None);
+    assert_eq!(wallet.zkeys().len(), 2);
+    assert_eq!(wallet.tkeys().len(), 2);
+    assert_eq!(wallet.unparsed_len(), TRAILER_LEN);
+    assert!(wallet.zkeys().iter().all(|zkey| zkey.extsk().is_none()));
+    assert!(wallet.tkeys().iter().all(|tkey| tkey.key().is_none()));
+
+    assert!(wallet.decrypt("wrong password").is_err());
+    wallet.decrypt(PASSWORD)?;
+    assert_eq!(wallet.seed(), Some(&ENTROPY));
+
+    // The decrypted keys are those of the wallet saved without a password.
+    let plain = read_fixture("plain")?;
+    for (zkey, plain_zkey) in wallet.zkeys().iter().zip(plain.zkeys()) {
+        assert_eq!(
+            zkey.extsk().map(|extsk| extsk.to_bytes()),
+            plain_zkey.extsk().map(|extsk| extsk.to_bytes())
+        );
+    }
+    for (tkey, plain_tkey) in wallet.tkeys().iter().zip(plain.tkeys()) {
+        assert_eq!(tkey.key(), plain_tkey.key());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_detect_mis_derived_keys() -> Result<()> {
+    let mut wallet = read_fixture("encrypted")?;
+
+    // The seed is needed to check derivation.
+    assert!(wallet.check_derivation(Network::Test)?.is_none());
+    wallet.decrypt(PASSWORD)?;
+
+    // z-key #1 was derived at m/32'/1'/7'.
+    let check = wallet.check_derivation(Network::Test)?.unwrap();
+    assert_eq!(check.checked(), 4);
+    assert_eq!(check.mis_derived().len(), 1);
+    assert_eq!(check.mis_derived()[0].hdkey_num(), 1);
+    assert_eq!(check.mis_derived()[0].path(), "m/32'/1'/1'");
+    assert_eq!(
+        check.mis_derived()[0].address(),
+        "ztestsapling18yw7qhh0mly5urrmcjgzs7d89dwtmhmm7negf9238cqz30lf5wn4s9ex3mgwyl23jlejvckxhzh"
+    );
+
+    let mut report = String::new();
+    check.write_report(&mut report)?;
+    assert!(report.contains("Keys reproduced from seed: 3/4"));
+    assert!(report.contains("zecwallet-cli 1.0"));
+    Ok(())
+}
+
+#[test]
+fn test_migrate_zecwallet() -> Result<()> {
+    let mut wallet = read_fixture("encrypted")?;
+    wallet.decrypt(PASSWORD)?;
+    let zewif = wallet.to_zewif(Network::Test)?;
+
+    let zewif_wallet = zewif.wallets().values().next().unwrap();
+    assert!(zewif_wallet.seed_material().is_some());
+
+    let mut accounts: Vec<_> = zewif_wallet
+        .accounts()
+        .values()
+        .map(|account| (account.name().to_string(), account.addresses().len()))
+        .collect();
+    accounts.sort();
+    assert_eq!(
+        accounts,
+        vec![
+            ("Account #0".to_string(), 3),
+            ("Mis-derived keys (zecwallet-cli 1.0)".to_string(), 1),
+        ]
+    );
+
+    // The spending keys are typed, so the key dump writes each of them.
+    let dump = write_keydump(&zewif, false)?;
+    assert!(dump.contains(EXTSK_0));
+    for wif in WIFS {
+        assert!(dump.contains(wif), "{} missing from the dump", wif);
+    }
+    assert_eq!(dump.matches("secret-extended-key-test1").count(), 2);
+    Ok(())
+}
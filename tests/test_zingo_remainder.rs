@@ -0,0 +1,152 @@
+//! # Test Suite: Zingo Remainder Tests
+//!
+//! These tests verify the report on the bytes a zingo parse leaves over:
+//! 1. The offset where parsing stopped and the last field parsed before it
+//! 2. Guesses at the next structure from the markers zingo writes
+//! 3. The hexdump of the trailing bytes, of which only the first are kept
+//! 4. A real zingo wallet with bytes appended reports where it stopped
+
+use std::io::Write;
+
+use anyhow::Result;
+use zmigrate::{
+    annotate::{FieldSpan, hex_lines},
+    json_output::OutputFormat,
+    zingo_cmd::dump_wallet_to,
+    zingo_remainder::{SHOWN_BYTES, ZingoRemainder},
+};
+
+// Import shared test utilities
+mod test_utils;
+use test_utils::fixtures_path;
+
+/// The wallet that `wallet_bytes` writes, as parsed.
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Wallet {
+    version: u64,
+    name: Option<String>,
+}
+
+const WALLET: Wallet = Wallet { version: 28, name: None };
+
+fn span(offset: usize, len: usize, path: &str) -> FieldSpan {
+    FieldSpan { offset, len, path: path.to_string() }
+}
+
+/// A version and a length-prefixed name, followed by `trailing`.
+fn wallet_bytes(trailing: &[u8]) -> Vec<u8> {
+    let mut bytes = b"\x1c\x00\x00\x00\x00\x00\x00\x00\x05hello".to_vec();
+    bytes.extend_from_slice(trailing);
+    bytes
+}
+
+fn spans() -> Vec<FieldSpan> {
+    vec![span(0, 8, "version"), span(8, 6, "name"), span(8, 1, "name.length"), span(9, 5, "name.text")]
+}
+
+#[test]
+fn test_offset_and_last_field() {
+    let bytes = wallet_bytes(b"\xde\xad");
+    let remainder = ZingoRemainder::new(&bytes, 2, &spans(), &WALLET);
+    assert_eq!(remainder.offset(), 14);
+    assert_eq!(remainder.trailing(), b"\xde\xad");
+    // Of the fields ending at the offset, the innermost is reported.
+    assert_eq!(remainder.last_field(), Some("name.text"));
+
+    // Without spans, it is the wallet's last field.
+    let remainder = ZingoRemainder::new(&bytes, 2, &[], &WALLET);
+    assert_eq!(remainder.last_field(), Some("name"));
+    let nested = Some(vec![WALLET, Wallet { version: 1, name: Some("x".to_string()) }]);
+    let remainder = ZingoRemainder::new(&bytes, 2, &[], &nested);
+    assert_eq!(remainder.last_field(), Some("1.name"));
+}
+
+#[test]
+fn test_guess_chain_name() {
+    let bytes = wallet_bytes(b"\x04\x00\x00\x00\x00\x00\x00\x00main");
+    let guesses = ZingoRemainder::new(&bytes, 12, &[], &WALLET).guesses();
+    assert!(guesses[0].contains("chain name \"main\""), "{:?}", guesses);
+
+    // A chain name further on is found along with its offset.
+    let bytes = wallet_bytes(b"\x01\x02\x07\x00\x00\x00\x00\x00\x00\x00regtest");
+    let guesses = ZingoRemainder::new(&bytes, 17, &[], &WALLET).guesses();
+    assert!(guesses[0].contains("\"regtest\" at offset 0x10"), "{:?}", guesses);
+}
+
+#[test]
+fn test_guess_version_and_count() {
+    let bytes = wallet_bytes(b"\x02\x00\x00\x00\x00\x00\x00\x00\x01");
+    let guesses = ZingoRemainder::new(&bytes, 9, &[], &WALLET).guesses();
+    assert_eq!(guesses.len(), 2, "{:?}", guesses);
+    assert!(guesses[0].starts_with("A u64 version marker (2)"));
+    assert!(guesses[1].starts_with("A CompactSize count (2)"));
+
+    let bytes = wallet_bytes(&[0; 12]);
+    let guesses = ZingoRemainder::new(&bytes, 12, &[], &WALLET).guesses();
+    assert_eq!(guesses.len(), 1);
+    assert!(guesses[0].starts_with("Only zero bytes"));
+}
+
+#[test]
+fn test_report() -> Result<()> {
+    let bytes = wallet_bytes(&[0xff; 300]);
+    let mut report = String::new();
+    ZingoRemainder::new(&bytes, 300, &[], &WALLET).write_report(&mut report)?;
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "🛑 Unparsed bytes: 300");
+    assert_eq!(lines[1], "- Parsing stopped at offset 0xe (14)");
+    assert_eq!(lines[2], "- Last parsed field: name");
+    assert_eq!(lines[3], "- Next structure: no known version marker");
+    assert_eq!(lines[4], "- Trailing bytes:");
+    // The hexdump is capped at 256 bytes, in sixteen lines.
+    assert!(lines[5].starts_with("  0000000e  ff ff"));
+    assert_eq!(lines.len(), 5 + 16 + 1);
+    assert_eq!(lines[21], "  … 44 more bytes");
+    Ok(())
+}
+
+#[test]
+fn test_trailing_bytes_capped() {
+    let bytes = wallet_bytes(&[0xff; 100_000]);
+    let remainder = ZingoRemainder::new(&bytes, 100_000, &[], &WALLET);
+    assert_eq!(remainder.trailing_len(), 100_000);
+    assert_eq!(remainder.trailing().len(), SHOWN_BYTES);
+    let json = remainder.to_json();
+    assert_eq!(json["trailing_bytes"].as_str().unwrap().len(), 2 * SHOWN_BYTES);
+}
+
+#[test]
+fn test_hex_lines() {
+    let lines = hex_lines(b"zingo wallet\x00\x01\x02\x03tail", 0x20);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "00000020  7a 69 6e 67 6f 20 77 61 6c 6c 65 74 00 01 02 03  |zingo wallet....|");
+    assert_eq!(lines[1], format!("00000030  {:<47}  |tail|", "74 61 69 6c"));
+}
+
+#[test]
+fn test_real_wallet_with_appended_bytes() -> Result<()> {
+    let fixture = fixtures_path(&["zingo", "regtest", "aaaaaaaaaaaaaaaaaaaaaaaa-v26.dat"]);
+    let bytes = std::fs::read(&fixture)?;
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&bytes)?;
+    file.write_all(b"\xde\xad\xbe\xef")?;
+    file.flush()?;
+
+    let mut out = Vec::new();
+    dump_wallet_to(file.path(), OutputFormat::Text, &mut out)?;
+    let dump = String::from_utf8(out)?;
+    assert!(dump.contains("🛑 Unparsed bytes: 4\n"), "{}", dump);
+    let stopped = format!("- Parsing stopped at offset {:#x} ({})", bytes.len(), bytes.len());
+    assert!(dump.contains(&stopped), "{}", dump);
+
+    // The last field is a real one: its first segment is one of the
+    // wallet's own fields.
+    let field = dump
+        .lines()
+        .find_map(|line| line.strip_prefix("- Last parsed field: "))
+        .expect("a last parsed field");
+    let top_level = field.split('.').next().unwrap();
+    assert!(dump.contains(&format!("\n    {}: ", top_level)), "{}", field);
+    Ok(())
+}